@@ -1,9 +1,13 @@
 use crate::config::{save_or_update_config, Config};
 use crate::BAIDU_PCS_APP;
 use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs_device_auth::{BaiduPanClient, BaiduPanDeviceAuthClient};
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::PcsAccessToken;
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::{AppError, AppErrorType, PcsAccessToken, PcsError};
 use log::{debug, error, info};
 use std::thread::sleep;
+use std::time::Duration;
+
+/// 使用 `--device-code` 授权时的轮询间隔（百度建议不小于 5 秒）
+const DEVICE_CODE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 pub fn device_auth() -> PcsAccessToken {
     device_auth_with_dns(None)
@@ -18,45 +22,90 @@ pub fn device_auth_with_dns(dns: Option<&str>) -> PcsAccessToken {
         ticket.get_verification_url(),
         ticket.get_user_code()
     );
+    let interval = Duration::from_secs(ticket.get_interval().unsigned_abs() + 1);
+    match poll_device_code(ticket.get_device_code(), interval, |code| {
+        client.get_access_token(code)
+    }) {
+        Ok(token) => token,
+        // 设备码过期（expired_token）或授权无效（invalid_grant）等，重新获取验证码
+        Err(error) if error.error_type == AppErrorType::Unauthorized => {
+            error!("{}", error);
+            println!("{}", error);
+            device_auth_with_dns(dns)
+        }
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// 轮询设备码授权结果：用户尚未完成授权（authorization_pending、slow_down，对应 `Client` 错误）时继续等待，
+/// 授权成功或出现其他错误（如设备码过期 expired_token，对应 `Unauthorized` 错误）时返回
+pub fn poll_device_code<G>(
+    device_code: &str,
+    interval: Duration,
+    mut get_access_token: G,
+) -> Result<PcsAccessToken, AppError>
+where
+    G: FnMut(String) -> Result<PcsAccessToken, PcsError>,
+{
     loop {
-        sleep(std::time::Duration::from_secs(
-            ticket.get_interval().unsigned_abs() + 1,
-        ));
-        let access_token = client.get_access_token(ticket.get_device_code().clone());
-        match access_token {
+        sleep(interval);
+        match get_access_token(device_code.to_string()).map_err(AppError::from) {
             Ok(token) => {
                 info!("device auth success");
-                return token;
+                return Ok(token);
             }
-            Err(error) => {
+            Err(error) if error.error_type == AppErrorType::Client => {
                 info!("error: {:?}  try again ...", error);
-                match error.error().as_str() {
-                    "pcs sdk error" => {
-                        panic!("{}", error.error_description())
-                    }
-                    "authorization_pending" => {
-                        continue;
-                    }
-                    _ => {
-                        // "invalid_grant"
-                        error!("{}", error.error());
-                        return device_auth_with_dns(dns);
-                    }
-                }
             }
+            Err(error) => return Err(error),
         }
     }
 }
 
-pub fn renew_token(config: &mut Config, custom_config: Option<&String>, dns: Option<&str>) {
+/// 使用此前已展示给用户的设备码完成授权，适用于展示验证码与轮询授权结果不在同一台机器的场景
+pub fn device_auth_with_code(
+    device_code: &str,
+    dns: Option<&str>,
+) -> Result<PcsAccessToken, AppError> {
+    debug!("device_auth_with_code");
+    let client: BaiduPanClient = BaiduPanDeviceAuthClient::with_dns(BAIDU_PCS_APP, dns);
+    poll_device_code(device_code, DEVICE_CODE_POLL_INTERVAL, |code| {
+        client.get_access_token(code)
+    })
+    .map_err(|error| {
+        AppError::new(
+            error.error_type,
+            format!("设备码授权失败: {}", error.message).as_str(),
+            None,
+        )
+    })
+}
+
+/// 非交互模式下无法完成设备码授权时的提示
+const NON_INTERACTIVE_AUTH_HINT: &str =
+    "非交互模式下无法进行设备码授权，请通过环境变量 BAIDU_PCS_ACCESS_TOKEN 提供凭据，或先在交互终端中执行 auth";
+
+/// 刷新 access_token，刷新失败时交互模式下重新进行设备码授权，非交互模式下直接返回错误
+/// refresh_token 来自环境变量时，刷新得到的凭据只在本次运行中使用，不写入配置文件
+pub fn renew_token(
+    config: &mut Config,
+    custom_config: Option<&String>,
+    dns: Option<&str>,
+    non_interactive: bool,
+) -> Result<(), AppError> {
     let auth_client: BaiduPanClient = BaiduPanDeviceAuthClient::with_dns(BAIDU_PCS_APP, dns);
+    let pan = config.runtime_pan();
     let token = auth_client.refresh_access_token(&PcsAccessToken::new(
-        config.baidu_pan.access_token.as_str(),
-        (config.baidu_pan.expires_at - chrono::Utc::now().timestamp()) as u32,
-        config.baidu_pan.refresh_token.as_str(),
+        pan.access_token.as_str(),
+        (pan.expires_at - chrono::Utc::now().timestamp()) as u32,
+        pan.refresh_token.as_str(),
         "basic,netdisk",
     ));
     match token {
+        Ok(token) if config.env.refresh_token.is_some() => {
+            config.env.access_token = Some(token.get_access_token().to_string());
+            config.env.refresh_token = Some(token.get_refresh_token().to_string());
+        }
         Ok(token) => {
             config.update_token(token);
             save_or_update_config(config, custom_config);
@@ -67,12 +116,56 @@ pub fn renew_token(config: &mut Config, custom_config: Option<&String>, dns: Opt
                 error.error(),
                 error.error_description()
             );
+            if non_interactive {
+                return Err(AppError::new(
+                    AppErrorType::Client,
+                    format!(
+                        "刷新凭据失败({})，{}",
+                        error.error(),
+                        NON_INTERACTIVE_AUTH_HINT
+                    )
+                    .as_str(),
+                    None,
+                ));
+            }
             info!("尝试重新认证授权...");
             let pcs_token: PcsAccessToken = device_auth_with_dns(dns);
             config.update_token(pcs_token);
             save_or_update_config(config, custom_config);
         }
     }
+    Ok(())
+}
+
+/// 首次使用检查：配置文件已存在或环境变量已提供凭据时直接继续，不做任何提示；
+/// 否则交互模式下调用 `prompt` 请用户确认，非交互模式下直接返回错误而不是阻塞等待输入
+/// # Returns
+/// * `Ok(true)` 继续执行，`Ok(false)` 用户取消
+pub fn check_first_use<P>(
+    config_exists: bool,
+    env_token: bool,
+    non_interactive: bool,
+    prompt: P,
+) -> Result<bool, AppError>
+where
+    P: FnOnce() -> bool,
+{
+    if config_exists || env_token {
+        return Ok(true);
+    }
+    if non_interactive {
+        return Err(AppError::new(
+            AppErrorType::Client,
+            format!("配置文件不存在，{}", NON_INTERACTIVE_AUTH_HINT).as_str(),
+            None,
+        ));
+    }
+    Ok(prompt())
+}
+
+/// 非交互模式下执行 auth 命令时的错误
+pub fn non_interactive_auth_error() -> AppError {
+    AppError::new(AppErrorType::Client, NON_INTERACTIVE_AUTH_HINT, None)
 }
 
 pub fn first_app_use() -> bool {
@@ -92,3 +185,59 @@ pub fn first_app_use() -> bool {
     // 如果 readline 成功则继续 否则 一般是 control c 退出
     std::io::stdin().read_line(&mut String::new()).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{check_first_use, poll_device_code};
+    use baidu_pcs_rs_sdk::baidu_pcs_sdk::{AppErrorType, PcsError};
+    use std::time::Duration;
+
+    #[test]
+    fn test_poll_device_code() {
+        let pending = || -> PcsError {
+            serde_json::from_value(serde_json::json!({
+                "error": "authorization_pending", "error_description": "User has not yet completed the authorization"
+            }))
+            .unwrap()
+        };
+        let mut codes = Vec::new();
+        let token = poll_device_code("abc123", Duration::from_millis(1), |code| {
+            codes.push(code);
+            if codes.len() < 2 {
+                Err(pending())
+            } else {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "access_token": "at", "expires_in": 2592000,
+                    "refresh_token": "rt", "scope": "basic netdisk"
+                }))
+                .unwrap())
+            }
+        })
+        .unwrap();
+        assert_eq!(token.get_access_token(), "at");
+        assert_eq!(codes, vec!["abc123".to_string(), "abc123".to_string()]);
+
+        let result = poll_device_code("abc123", Duration::from_millis(1), |_| {
+            Err(serde_json::from_value(serde_json::json!({
+                "error": "expired_token", "error_description": "expired"
+            }))
+            .unwrap())
+        });
+        let error = result.unwrap_err();
+        assert_eq!(error.error_type, AppErrorType::Unauthorized);
+        assert!(error.message.contains("expired_token"));
+    }
+
+    #[test]
+    fn test_check_first_use() {
+        // 非交互模式 + 环境变量凭据：直接继续，不提示
+        let proceed = check_first_use(false, true, true, || panic!("不应提示")).unwrap();
+        assert!(proceed);
+        // 配置文件已存在：不提示
+        assert!(check_first_use(true, false, false, || panic!("不应提示")).unwrap());
+        // 非交互模式且无凭据：快速失败
+        assert!(check_first_use(false, false, true, || panic!("不应提示")).is_err());
+        // 交互模式：由用户决定
+        assert!(!check_first_use(false, false, false, || false).unwrap());
+    }
+}
@@ -1,30 +1,37 @@
-use log::{debug, info};
+use log::{debug, info, warn};
 use md5::{Digest, Md5};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::{Body, Client};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Semaphore;
 
 use crate::baidu_pcs_sdk::pcs::HttpMethod::{Get, Post};
 pub use crate::baidu_pcs_sdk::{
-    AppError, AppErrorType, BaiduPcsApp, PcsApiError, PcsCreateFolderResult, PcsDiskQuota,
-    PcsFileListResult, PcsFileMetaResult, PcsFileSearchResult, PcsFileSlicePrepareResult,
-    PcsFileUploadResult, PcsUserInfo, ShareDownloadResult, ShareFileListResult, ShareVerifyResult,
-    UploadServerResult,
+    AccountSummary, AppError, AppErrorType, BackupPlan, BaiduPcsApp, CategoryCounts, PcsApiError,
+    PcsCreateFolderResult, PcsDiskQuota, PcsFileItem, PcsFileListResult, PcsFileMetaInfo,
+    PcsFileMetaResult, PcsFileSearchInfo, PcsFileSearchResult, PcsFileSlicePrepareResult,
+    PcsFileTaskOperationResult, PcsFileTaskStatus, PcsFileTaskStatusItem, PcsFileUploadResult,
+    PcsUserInfo, ShareDownloadResult, ShareFileListResult, ShareVerifyResult, UploadServerResult,
 };
 
 use crate::dns;
 use futures::TryStreamExt;
 use tokio_util::io::ReaderStream;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PcsUploadPolicy {
-    /// 失败
+    /// 失败：远程已存在同名文件时返回错误（rtype 0；0.2.2 及之前版本按覆盖处理）
     Fail,
     /// 重命名
     Rename,
@@ -34,12 +41,447 @@ pub enum PcsUploadPolicy {
     NewCopy,
 }
 
+/// 下载时本地目标文件已存在的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcsDownloadPolicy {
+    /// 覆盖
+    Overwrite,
+    /// 跳过，不下载
+    Skip,
+    /// 失败
+    Fail,
+}
+
+/// 大文件上传选项，控制预上传（precreate）与创建文件（create）请求中的属性
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadOptions {
+    /// 上传的文件绝对路径冲突时的策略
+    pub policy: PcsUploadPolicy,
+    /// 客户端创建时间(精确到秒)，为 None 时取本地文件的创建时间
+    pub local_ctime: Option<i64>,
+    /// 客户端修改时间(精确到秒)，为 None 时取本地文件的修改时间
+    pub local_mtime: Option<i64>,
+    /// 上传方式：1 手动、2 批量上传、3 文件自动备份、4 相册自动备份、5 视频自动备份
+    pub mode: i32,
+    /// 是否需要多版本支持（开启时服务端会忽略重命名策略，同名文件总是保留历史版本）
+    /// 由命名策略构造时仅 `Overwrite` 默认开启，`Rename`/`NewCopy` 默认关闭以使命名策略生效
+    pub is_revision: bool,
+    /// 图片压缩程度，有效值 50、70、100，需与 `zip_sign` 一并提交
+    pub zip_quality: Option<i32>,
+    /// 未压缩原始图片文件的真实 MD5，设置了 `zip_quality` 而未指定时上传过程中自动计算
+    pub zip_sign: Option<String>,
+    /// 照片的 EXIF 信息，指定时优先于自动提取的结果
+    pub exif_info: Option<PcsExifInfo>,
+    /// 上传 JPEG 图片且未指定 `exif_info` 时是否自动从文件中提取，默认开启（需启用 `exif` 特性，未启用时不提取）
+    pub detect_exif: bool,
+    /// 分片上传时的 Content-Type，为 None 时按扩展名推断（见 `guess_content_type`）
+    pub content_type: Option<String>,
+    /// 暂停控制，上传过程中可通过其克隆暂停/恢复
+    pub pause: Option<PauseControl>,
+}
+
+/// 暂停状态的轮询间隔
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 上传暂停控制，克隆后共享同一状态
+/// 暂停在分片之间生效：当前分片传完后阻塞，恢复后继续上传下一分片，不会丢弃 uploadid 与已上传的分片
+// 服务端 uploadid 有效期有限，暂停过久恢复后可能因 uploadid 失效而失败
+#[derive(Debug, Clone, Default)]
+pub struct PauseControl(Arc<AtomicBool>);
+
+impl PartialEq for PauseControl {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for PauseControl {}
+
+impl PauseControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 暂停上传（当前分片传完后生效）
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// 恢复上传
+    pub fn resume(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// 处于暂停状态时阻塞，直至恢复
+    fn wait_while_paused(&self) {
+        if !self.is_paused() {
+            return;
+        }
+        info!("上传已暂停");
+        while self.is_paused() {
+            std::thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+        info!("上传已恢复");
+    }
+}
+
+/// 照片的 EXIF 信息，创建文件时以 json 串提交，百度网盘据此按拍摄方向显示照片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PcsExifInfo {
+    /// 拍摄方向，取值 1-8，含义同 EXIF Orientation 标签
+    pub orientation: u16,
+    /// 图片宽度（像素）
+    pub width: u32,
+    /// 图片高度（像素）
+    pub height: u32,
+    /// 固定为 0
+    pub recovery: i32,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        UploadOptions {
+            policy: PcsUploadPolicy::Overwrite,
+            local_ctime: None,
+            local_mtime: None,
+            mode: 2,
+            is_revision: true,
+            zip_quality: None,
+            zip_sign: None,
+            exif_info: None,
+            detect_exif: true,
+            content_type: None,
+            pause: None,
+        }
+    }
+}
+
+impl From<PcsUploadPolicy> for UploadOptions {
+    fn from(policy: PcsUploadPolicy) -> Self {
+        UploadOptions {
+            policy,
+            is_revision: policy == PcsUploadPolicy::Overwrite,
+            ..Default::default()
+        }
+    }
+}
+
+/// 文件搜索选项，对应搜索接口的可选参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// 搜索目录，为 None 时搜索根目录
+    pub dir: Option<String>,
+    /// 文件类型，1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
+    pub category: Option<i32>,
+    /// 是否递归搜索子目录，默认开启
+    pub recursion: bool,
+    /// 是否返回缩略图信息
+    pub web: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            dir: None,
+            category: None,
+            recursion: true,
+            web: false,
+        }
+    }
+}
+
+/// 按页依次获取结果的迭代器：当前页的条目取完且 `has_more` 时才请求下一页，页码从 1 开始
+/// 请求出错时返回该错误并结束迭代
+fn paginate<T, F>(mut fetch: F) -> impl Iterator<Item = Result<T, AppError>>
+where
+    F: FnMut(i32) -> Result<(Vec<T>, bool), AppError>,
+{
+    let mut buffer = std::collections::VecDeque::new();
+    let mut next_page = Some(1);
+    std::iter::from_fn(move || loop {
+        if let Some(item) = buffer.pop_front() {
+            return Some(Ok(item));
+        }
+        let page = next_page.take()?;
+        match fetch(page) {
+            Ok((items, has_more)) => {
+                buffer.extend(items);
+                next_page = has_more.then_some(page + 1);
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    })
+}
+
+/// 列目录接口单次返回的条目数上限
+const LIST_DIR_PAGE_SIZE: u64 = 1000;
+
+/// 按 start/limit 分页列出单个目录的全部条目，`fetch` 负责请求从 `start` 开始的一页
+/// 返回的条目数不足一页时结束
+fn list_dir_pages<F>(mut fetch: F) -> Result<Vec<PcsFileItem>, AppError>
+where
+    F: FnMut(u64) -> Result<Vec<PcsFileItem>, AppError>,
+{
+    paginate(|page| {
+        let items = fetch((page as u64 - 1) * LIST_DIR_PAGE_SIZE)?;
+        let has_more = items.len() as u64 >= LIST_DIR_PAGE_SIZE;
+        Ok((items, has_more))
+    })
+    .collect()
+}
+
+/// 将 `/apps/{from_app}` 下的路径替换为 `/apps/{to_app}` 下的对应路径
+fn replace_app_dir(path: &str, from_app: &str, to_app: &str) -> String {
+    let from = format!("/apps/{}", from_app.trim_matches('/'));
+    match path.strip_prefix(from.as_str()) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("/apps/{}{}", to_app, rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// 单次查询文件信息的 fs_id 数量上限
+const FILE_INFO_BATCH_SIZE: usize = 100;
+
+/// 将文件信息列表转换为以 fs_id 为键的映射
+fn index_by_fs_id(list: Vec<PcsFileMetaInfo>) -> HashMap<u64, PcsFileMetaInfo> {
+    list.into_iter().map(|info| (*info.fs_id(), info)).collect()
+}
+
+/// 将本地拼接得到的路径转换为云盘路径，云盘路径始终以 `/` 分隔
+/// Windows 下 `PathBuf::join` 会插入 `\`，不转换的话会在云盘上生成名称中带 `\` 的文件
+pub fn to_remote_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// 递归收集目录下待备份的文件，返回 (本地路径, 云盘路径) 列表，云盘路径保持相对 `local_dir` 的目录结构
+/// 与命令行同步时扫描本地文件的规则一致：跳过隐藏文件（目录）和文件名不是有效 UTF-8 的路径
+fn backup_targets(local_dir: &Path, pcs_path: &str) -> Result<Vec<(String, String)>, AppError> {
+    let mut targets = Vec::new();
+    let mut entries = std::fs::read_dir(local_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Some(name) = name.to_str() else {
+            warn!(
+                "跳过文件名不是有效 UTF-8 的路径: {}",
+                path.to_string_lossy()
+            );
+            continue;
+        };
+        let remote = to_remote_path(&Path::new(pcs_path).join(name));
+        if path.is_dir() {
+            targets.extend(backup_targets(&path, remote.as_str())?);
+        } else if path.is_file() {
+            targets.push((path.to_string_lossy().to_string(), remote));
+        }
+    }
+    Ok(targets)
+}
+
+/// `upload_as` 为避开同名文件最多尝试的候选名称数量
+const UPLOAD_AS_MAX_CANDIDATES: u32 = 1000;
+
+/// `upload_as` 默认的重命名方案：在扩展名前追加 `_n`，如 `a.txt` -> `a_2.txt`，`README` -> `README_2`
+/// 以 `.` 开头且不含其他 `.` 的隐藏文件视为没有扩展名，如 `.env` -> `.env_2`
+pub fn suffixed_name(name: &str, n: u32) -> String {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => format!("{}_{}{}", &name[..pos], n, &name[pos..]),
+        _ => format!("{}_{}", name, n),
+    }
+}
+
+/// 依次尝试 `desired` 及 `scheme(文件名, 2)`、`scheme(文件名, 3)`…，返回第一个不在 `existing` 中的远程路径
+fn pick_upload_path<S>(desired: &str, existing: &HashSet<String>, scheme: S) -> Option<String>
+where
+    S: Fn(&str, u32) -> String,
+{
+    let path = Path::new(desired);
+    let parent = path.parent().unwrap_or(Path::new("/"));
+    let name = path.file_name()?.to_str()?;
+    std::iter::once(desired.to_string())
+        .chain(
+            (2..=UPLOAD_AS_MAX_CANDIDATES).map(|n| to_remote_path(&parent.join(scheme(name, n)))),
+        )
+        .find(|candidate| !existing.contains(candidate))
+}
+
+/// 共享目录在 filemetas、list 接口 `path` 参数中的表示：`/uk-fsid`
+/// `uk` 为共享目录创建者 ID，`shared_fs_id` 为共享目录的 fs_id
+pub fn shared_dir_path(uk: u64, shared_fs_id: u64) -> String {
+    format!("/{}-{}", uk, shared_fs_id)
+}
+
+/// 以 `cwd` 为基准解析远程路径，绝对路径原样返回
+/// 相对路径中的 `.` 被忽略，`..` 回到上一级（已在根目录时保持在根目录），结果不带末尾的 /
+pub fn resolve_remote_path(cwd: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+    let mut parts: Vec<&str> = Vec::new();
+    for part in cwd.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            name => parts.push(name),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
+
+/// 时间差超过 `CLOCK_SKEW_WARN_SECS` 时返回该时间差，否则返回 None
+fn clock_skew(offset: i64) -> Option<i64> {
+    (offset.abs() > CLOCK_SKEW_WARN_SECS).then_some(offset)
+}
+
+/// 计算本地文件的 MD5（十六进制小写）
+fn local_file_md5(path: &str) -> Result<String, AppError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 将一次已认证请求的结果转换为凭据有效性：成功为 true，认证失败为 false，其他错误原样返回
+fn token_validity<T>(result: Result<T, AppError>) -> Result<bool, AppError> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) if e.error_type == AppErrorType::Unauthorized => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// 应用专属空间根目录
+pub const APPDATA_ROOT: &str = "/_pcs_.appdata/xpan";
+
+/// 将专属空间内的相对路径转换为完整路径，已带有专属空间前缀的路径原样返回
+pub fn appdata_path(subpath: &str) -> String {
+    if subpath == APPDATA_ROOT || subpath.starts_with(&format!("{APPDATA_ROOT}/")) {
+        return subpath.to_string();
+    }
+    let sub = subpath.trim_matches('/');
+    if sub.is_empty() {
+        format!("{APPDATA_ROOT}/")
+    } else {
+        format!("{APPDATA_ROOT}/{sub}")
+    }
+}
+
+/// 按文件扩展名推断 Content-Type，无法识别时为 `application/octet-stream`
+pub fn guess_content_type(path: &str) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string()
+}
+
+/// 是否为 JPEG 图片（按扩展名判断）
+#[cfg(feature = "exif")]
+fn is_jpeg_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
+        .unwrap_or(false)
+}
+
+/// 从 JPEG 文件中提取 EXIF 信息：IFD0 中的 Orientation 标签，以及 EXIF 记录的图片宽高
+/// 没有 Orientation 标签时视为 1（正常方向）；没有 EXIF 或缺少尺寸信息时返回 None
+#[cfg(feature = "exif")]
+fn read_jpeg_exif(path: &str) -> Option<PcsExifInfo> {
+    use exif::{In, Tag};
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::BufReader::new(file))
+        .ok()?;
+    let uint = |tags: &[Tag]| {
+        tags.iter()
+            .find_map(|&tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0))
+    };
+    Some(PcsExifInfo {
+        orientation: uint(&[Tag::Orientation]).unwrap_or(1) as u16,
+        width: uint(&[Tag::PixelXDimension, Tag::ImageWidth])?,
+        height: uint(&[Tag::PixelYDimension, Tag::ImageLength])?,
+        recovery: 0,
+    })
+}
+
+/// 上传 JPEG 图片且未指定 `exif_info` 时，按 `detect_exif` 从文件中提取
+#[cfg(feature = "exif")]
+fn with_jpeg_exif(local_file: &str, options: &UploadOptions) -> UploadOptions {
+    let mut options = options.clone();
+    if options.exif_info.is_none() && options.detect_exif && is_jpeg_file(local_file) {
+        options.exif_info = read_jpeg_exif(local_file);
+        debug!("照片 {} 的 EXIF 信息 {:?}", local_file, options.exif_info);
+    }
+    options
+}
+
+/// 图片压缩程度的有效值
+const ZIP_QUALITIES: [i32; 3] = [50, 70, 100];
+
+/// 校验图片压缩参数：`zip_quality` 与 `zip_sign` 必须同时提交，且压缩程度只能为 50、70、100
+fn check_zip_options(zip_quality: Option<i32>, has_zip_sign: bool) -> Result<(), AppError> {
+    match (zip_quality, has_zip_sign) {
+        (None, false) => Ok(()),
+        (Some(quality), _) if !ZIP_QUALITIES.contains(&quality) => Err(AppError::new(
+            AppErrorType::Client,
+            format!("zip_quality 仅支持 50、70、100，当前为 {}", quality).as_str(),
+            None,
+        )),
+        (Some(_), true) => Ok(()),
+        _ => Err(AppError::new(
+            AppErrorType::Client,
+            "zip_quality 与 zip_sign 必须同时设置",
+            None,
+        )),
+    }
+}
+
 /// @see https://pan.baidu.com/union/doc/Cksg0s9ic
 const PREFIX: &str = "https://pan.baidu.com";
 // 根据文档和测试， 若api管理用 pan.baidu.com， 文件上传下载用 d.pcs.baidu.com
 const PREFIX_FILE_SERVER: &str = "https://d.pcs.baidu.com";
 /// 分片文件头部摘要大小 256KB
 const HEADER_SLICE_SIZE: u64 = 256 * 1024;
+/// 轮询文件管理异步任务状态的间隔
+const FILE_TASK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 批量删除时单次请求携带的最大路径数，避免 filelist 超出服务端限制
+const DELETE_BATCH_SIZE: usize = 1000;
+/// 下载写入本地文件时默认的缓冲区大小 1MB
+const DOWNLOAD_BUFFER_SIZE: usize = 1024 * 1024;
+/// 本机时钟与上传服务器时间相差超过该秒数时输出警告
+const CLOCK_SKEW_WARN_SECS: i64 = 300;
+/// 目录下载时每批查询下载地址并下载的文件数，也是进度回调的粒度
+const DOWNLOAD_DIR_BATCH_SIZE: usize = 10;
+/// `shutdown` 等待运行时中未完成的阻塞任务（如 tokio::fs 的文件写入）的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// 将文件进行切片后的文件信息
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,10 +490,10 @@ pub struct PcsFileSliceInfo {
     path: String,
     /// 本地文件大小
     size: u64,
-    /// 本地文件MD5，32位小写
-    content_md5: String,
-    /// 文件校验段的MD5，32位小写，校验段对应文件前256KB
-    slice_md5: String,
+    /// 本地文件MD5，32位小写，快速上传模式下不计算
+    content_md5: Option<String>,
+    /// 文件校验段的MD5，32位小写，校验段对应文件前256KB，快速上传模式下不计算
+    slice_md5: Option<String>,
     /// 文件各分片md5数组的json串
     block_list: Vec<String>,
     /// 本地文件创建时间(精确到秒)
@@ -60,6 +502,197 @@ pub struct PcsFileSliceInfo {
     mtime: i64,
 }
 
+impl PcsFileSliceInfo {
+    /// 由预先计算的校验和构造分片信息，用于 `BaiduPcsClient::upload_with_checksums`
+    /// # Arguments
+    /// * `size` - 数据大小，单位B
+    /// * `content_md5` - 整体数据的 MD5，为 None 时无法秒传
+    /// * `slice_md5` - 前 256KB 数据的 MD5，为 None 时无法秒传
+    /// * `block_list` - 按账号分片大小划分的各分片 MD5，32位小写
+    pub fn with_checksums(
+        size: u64,
+        content_md5: Option<String>,
+        slice_md5: Option<String>,
+        block_list: Vec<String>,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        PcsFileSliceInfo {
+            path: String::new(),
+            size,
+            content_md5,
+            slice_md5,
+            block_list,
+            ctime: now,
+            mtime: now,
+        }
+    }
+}
+
+/// 分片数据来源
+enum SliceSource<'a> {
+    /// 从本地文件的对应位置读取
+    File(&'a str),
+    /// 已读入内存的分片数据
+    Bytes(&'a [u8]),
+}
+
+/// 预上传（precreate）请求体
+#[derive(Serialize)]
+struct PreCreateAttributes<'a> {
+    /// 上传后使用的文件绝对路径，需要urlencode
+    path: &'a str,
+    /// 文件和目录两种情况：上传文件时，表示文件的大小，单位B；上传目录时，表示目录的大小，目录的话大小默认为0
+    size: u64,
+    /// 是否为目录，0 文件，1 目录
+    #[serde(rename = "isdir")]
+    is_dir: i32,
+    /// 文件各分片MD5数组的json串。block_list的含义如下，如果上传的文件小于4MB，其md5值（32位小写）即为block_list字符串数组的唯一元素；如果上传的文件大于4MB，需要将上传的文件按照4MB大小在本地切分成分片，不足4MB的分片自动成为最后一个分片，所有分片的md5值（32位小写）组成的字符串数组即为block_list。
+    block_list: String,
+    /// 固定值1
+    #[serde(rename = "autoinit")]
+    auto_init: i32,
+    /// 文件命名策略。
+    // 1 表示当path冲突时，进行重命名
+    // 2 表示当path冲突且block_list不同时，进行重命名
+    // 3 当云端存在同名文件时，对该文件进行覆盖
+    #[serde(rename = "rtype")]
+    r_type: Option<i32>,
+    /// 上传ID
+    #[serde(rename = "uploadid")]
+    upload_id: Option<String>,
+    /// 文件MD5，32位小写，快速上传模式下不提交
+    #[serde(alias = "content-md5", skip_serializing_if = "Option::is_none")]
+    content_md5: Option<String>,
+    /// 文件校验段的MD5，32位小写，校验段对应文件前256KB，快速上传模式下不提交
+    #[serde(alias = "slice-md5", skip_serializing_if = "Option::is_none")]
+    slice_md5: Option<String>,
+    /// 客户端创建时间(精确到秒)，默认为当前时间戳
+    local_ctime: Option<i64>,
+    /// 客户端修改时间(精确到秒)，默认为当前时间戳
+    local_mtime: Option<i64>,
+}
+
+/// 文件命名策略对应的 rtype
+fn upload_r_type(police: &PcsUploadPolicy) -> Option<i32> {
+    match police {
+        PcsUploadPolicy::Fail => Some(0),
+        PcsUploadPolicy::Rename => Some(1),
+        PcsUploadPolicy::NewCopy => Some(2),
+        PcsUploadPolicy::Overwrite => Some(3),
+    }
+}
+
+/// 根据本地文件分片信息构造预上传请求体
+fn precreate_attributes<'a>(
+    pcs_path: &'a str,
+    fs_meta: &PcsFileSliceInfo,
+    options: &UploadOptions,
+) -> Result<PreCreateAttributes<'a>, AppError> {
+    Ok(PreCreateAttributes {
+        path: pcs_path,
+        size: fs_meta.size,
+        is_dir: 0,
+        block_list: serde_json::to_string(fs_meta.block_list.as_slice())?,
+        auto_init: 1,
+        r_type: upload_r_type(&options.policy),
+        upload_id: None,
+        content_md5: fs_meta.content_md5.clone(),
+        slice_md5: fs_meta.slice_md5.clone(),
+        local_ctime: Some(options.local_ctime.unwrap_or(fs_meta.ctime)),
+        local_mtime: Some(options.local_mtime.unwrap_or(fs_meta.mtime)),
+    })
+}
+
+/// 创建文件（create）请求体
+#[derive(Serialize)]
+struct MergeAttributes<'a> {
+    /// 上传后使用的文件绝对路径，需要urlencode
+    path: &'a str,
+    /// 文件或目录的大小，必须要和文件真实大小保持一致，需要与预上传precreate接口中的size保持一致
+    size: u64,
+    /// 是否目录，0 文件、1 目录，需要与预上传precreate接口中的isdir保持一致
+    #[serde(rename = "isdir")]
+    is_dir: &'a str,
+    /// 文件各分片md5数组的json串
+    // 需要与预上传precreate接口中的block_list保持一致，同时对应分片上传superfile2接口返回的md5，且要按照序号顺序排列，组成md5数组的json串。
+    block_list: &'a str,
+    /// 预上传precreate接口下发的uploadid
+    #[serde(rename = "uploadid")]
+    upload_id: &'a str,
+    /// 文件命名策略，默认 `0`
+    /// - `0` 为不重命名，返回冲突
+    /// - `1` 为只要path冲突即重命名
+    /// - `2` 为path冲突且block_list不同才重命名
+    /// - `3` 为覆盖，需要与预上传precreate接口中的rtype保持一致
+    #[serde(rename = "rtype")]
+    r_type: Option<i32>,
+    /// 客户端创建时间(精确到秒)，默认为当前时间戳
+    local_ctime: Option<i64>,
+    /// 客户端修改时间(精确到秒)，默认为当前时间戳
+    local_mtime: Option<i64>,
+    /// 图片压缩程度，有效值50、70、100（带此参数时，zip_sign 参数需要一并带上）
+    zip_quality: Option<i32>,
+    /// 未压缩原始图片文件真实md5（带此参数时，zip_quality 参数需要一并带上）
+    zip_sign: Option<String>,
+    /// 是否需要多版本支持, 默认为0 (带此参数会忽略重命名策略)
+    /// - `1` 为支持
+    /// - `0` 为不支持
+    // 不需要多版本时不携带此参数，以免命名策略被忽略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_revision: Option<i32>,
+    /// 上传方式
+    /// - `1` 手动
+    /// - `2` 批量上传
+    /// - `3` 文件自动备份
+    /// - `4` 相册自动备份
+    /// - `5` 视频自动备份
+    mode: Option<i32>,
+    /// json字符串，orientation、width、height、recovery为必传字段，其他字段如果没有可以不传
+    exif_info: Option<String>,
+}
+
+/// 根据预上传结果与已上传分片的 md5 构造创建文件请求体，时间与命名策略需与预上传保持一致
+/// 设置了 `zip_quality` 而未指定 `zip_sign` 时使用本地文件的整文件 MD5
+fn merge_attributes<'a>(
+    upload_task: &'a PcsFileSlicePrepareResult,
+    fs: &PcsFileSliceInfo,
+    block_list_json: &'a str,
+    options: &UploadOptions,
+) -> Result<MergeAttributes<'a>, AppError> {
+    let zip_sign = match options.zip_quality {
+        Some(_) => options.zip_sign.clone().or_else(|| fs.content_md5.clone()),
+        None => options.zip_sign.clone(),
+    };
+    check_zip_options(options.zip_quality, zip_sign.is_some())?;
+    // 创建文件的 rtype 需要与预上传保持一致，优先沿用预上传时实际提交的值
+    let r_type = upload_r_type(&options.policy);
+    debug_assert!(
+        upload_task.r_type.is_none() || upload_task.r_type == r_type,
+        "创建文件的 rtype {:?} 与预上传的 rtype {:?} 不一致",
+        r_type,
+        upload_task.r_type
+    );
+    Ok(MergeAttributes {
+        path: upload_task.path().as_str(),
+        size: fs.size,
+        is_dir: "0",
+        block_list: block_list_json,
+        upload_id: upload_task.upload_id.as_str(),
+        r_type: upload_task.r_type.or(r_type),
+        local_ctime: Some(options.local_ctime.unwrap_or(fs.ctime)),
+        local_mtime: Some(options.local_mtime.unwrap_or(fs.mtime)),
+        zip_quality: options.zip_quality,
+        zip_sign,
+        is_revision: options.is_revision.then_some(1),
+        mode: Some(options.mode),
+        exif_info: options
+            .exif_info
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+    })
+}
+
 /// 百度网盘--网盘客户端
 pub struct BaiduPcsClient {
     runtime: tokio::runtime::Runtime,
@@ -70,57 +703,226 @@ pub struct BaiduPcsClient {
     disk_quota: Option<PcsDiskQuota>,
     /// 指定的 DNS 服务器（逗号分隔），用于网络请求解析域名
     dns: Option<String>,
+    /// 小文件上传时是否强制将路径限定在 /apps/{app-name}/ 下，默认为 true
+    // 部分应用拥有 netdisk 全盘权限，可关闭此选项以上传到任意路径
+    enforce_apps_dir: bool,
+    /// 下载写入本地文件时的缓冲区大小（字节），0 表示不使用缓冲
+    download_buffer_size: usize,
+    /// 快速上传模式：预上传时不计算、不提交整文件 MD5 与校验段 MD5（无法秒传）
+    fast_upload: bool,
+    /// 备份目录时内容相同的文件只上传一次，其余通过云端复制生成
+    backup_dedup: bool,
+    /// 本地文件校验和缓存，未变化的文件跳过重新计算 MD5
+    md5_cache: Option<Md5Cache>,
+    /// 当前远程工作目录，列目录、上传、下载时的相对路径以此为基准，默认为 /
+    cwd: String,
+    /// 全局并发限制：同时进行中的接口请求、上传与下载数量上限
+    // 并发过高容易触发 31034 接口频控
+    request_limiter: Arc<Semaphore>,
+    max_concurrent_requests: usize,
+    /// 接口请求的服务地址，默认 https://pan.baidu.com
+    api_base_url: String,
+    /// 文件上传服务地址，默认 https://d.pcs.baidu.com
+    file_server_url: String,
+}
+
+/// 依次上传各分片，返回按分片顺序排列的分片 MD5
+/// 每个分片上传前检查暂停状态；`upload` 为实际上传单个分片的函数，参数为该分片的进度信息
+fn upload_slices<U>(
+    total_parts: usize,
+    total_bytes: u64,
+    slice_size: u64,
+    pause: Option<&PauseControl>,
+    mut upload: U,
+) -> Result<Vec<String>, AppError>
+where
+    U: FnMut(ProgressInfo) -> Result<String, AppError>,
+{
+    let mut uploaded_bytes: u64 = 0;
+    let mut md5s: Vec<String> = Vec::with_capacity(total_parts);
+    for i in 0..total_parts {
+        if let Some(pause) = pause {
+            pause.wait_while_paused();
+        }
+        let part_bytes = if i == total_parts - 1 {
+            total_bytes - slice_size * (i as u64)
+        } else {
+            slice_size
+        };
+        let md5 = upload(ProgressInfo {
+            total_bytes,
+            uploaded_bytes,
+            current_part: i as u32,
+            current_part_bytes: part_bytes,
+        })?;
+        info!("分片 {}/{} 上传完成 {}", i + 1, total_parts, md5);
+        uploaded_bytes = uploaded_bytes.saturating_add(part_bytes);
+        md5s.push(md5);
+    }
+    Ok(md5s)
+}
+
+/// 分片上传可用的服务器列表：依次为 `servers`、`bak_servers`，均为空时使用默认上传域名
+/// 同一文件的所有分片共用一次定位结果
+fn upload_server_candidates(server: &UploadServerResult, default_server: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for s in server.servers().iter().chain(server.bak_servers().iter()) {
+        if !candidates.contains(s.server()) {
+            candidates.push(s.server().clone());
+        }
+    }
+    if candidates.is_empty() {
+        warn!(
+            "未定位到可用的上传服务器(servers 与 bak_servers 均为空)，改用默认上传服务器 {}",
+            default_server
+        );
+        candidates.push(default_server.to_string());
+    }
+    candidates
+}
+
+/// 默认上传服务器也不可达时，给出明确的错误信息
+fn default_server_unreachable(e: AppError, default_server: &str) -> AppError {
+    if e.error_type != AppErrorType::Network {
+        return e;
+    }
+    let errno = e.errno;
+    AppError::new(
+        AppErrorType::Network,
+        format!(
+            "未定位到可用的上传服务器，且默认上传服务器 {} 不可达: {}",
+            default_server,
+            String::from(e)
+        )
+        .as_str(),
+        errno,
+    )
+}
+
+/// 依次尝试各上传服务器，网络错误时切换到下一个服务器，其他错误（如服务端拒绝）直接返回
+/// 全部服务器均失败时返回最后一个错误
+fn with_server_failover<T, F>(servers: &[String], mut upload: F) -> Result<T, AppError>
+where
+    F: FnMut(&str) -> Result<T, AppError>,
+{
+    let mut last_error = AppError::new(AppErrorType::Client, "没有可用的上传服务器", None);
+    for (i, server) in servers.iter().enumerate() {
+        match upload(server) {
+            Err(e) if e.error_type == AppErrorType::Network => {
+                warn!(
+                    "上传服务器 {} 不可用({}/{}): {}",
+                    server,
+                    i + 1,
+                    servers.len(),
+                    e
+                );
+                last_error = e;
+            }
+            result => return result,
+        }
+    }
+    Err(last_error)
+}
+
+/// 由 `(名称, 值)` 列表构造请求头，名称或值不合法时返回错误
+pub fn parse_headers(headers: &[(&str, &str)]) -> Result<HeaderMap, AppError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = |what: &str| {
+            AppError::new(
+                AppErrorType::Client,
+                format!("无效的请求头{}: {}: {}", what, name, value).as_str(),
+                None,
+            )
+        };
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid("名称"))?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|_| invalid("值"))?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+/// 默认的最大并发请求数
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// HTTP 连接池与 TCP 调优选项，由 `BaiduPcsClient::new_with_http_options` 使用
+/// 默认值面向并发分片上传：按并发数保留空闲连接以便复用，并开启 TCP keepalive 避免长时间上传时连接被中间设备断开
+/// 客户端只使用 HTTP/1.1（未启用 reqwest 的 `http2` 特性），并发请求依靠连接池中的多个连接
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpOptions {
+    /// 每个主机保留的空闲连接数上限
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接的保留时长，None 表示一直保留
+    pub pool_idle_timeout: Option<Duration>,
+    /// TCP keepalive 探测间隔，None 表示不启用
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: DEFAULT_MAX_CONCURRENT_REQUESTS * 2,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl HttpOptions {
+    fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        builder
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+    }
+}
+
+/// 取得并发许可后再执行 `fut`，许可在 `fut` 完成后释放
+async fn with_permit<F: std::future::Future>(limiter: &Semaphore, fut: F) -> F::Output {
+    // 信号量不会被关闭，acquire 不会失败；持有结果即持有许可
+    let _permit = limiter.acquire().await;
+    fut.await
 }
 
+/// 计算文件的分片 MD5 列表，每计算完一个分片调用一次 `hash_progress(已计算字节, 总字节)`
+/// `with_checksums` 为 false 时不计算整文件 MD5 与校验段 MD5
+/// 文件的路径、大小、修改时间与 `cache` 中的记录一致时直接使用缓存的校验和，
+/// 此时只调用一次 `hash_progress(总字节, 总字节)`；重新计算的结果写回缓存
 fn get_file_block_list(
     user_info: &PcsUserInfo,
     file_path: &str,
+    with_checksums: bool,
+    cache: Option<&Md5Cache>,
+    hash_progress: &mut dyn FnMut(u64, u64),
 ) -> Result<PcsFileSliceInfo, AppError> {
     let mut file = File::open(file_path)?;
     let file_meta = file.metadata()?;
     let file_size = file_meta.len();
     let slice_size = user_info.get_user_block_slice_size();
-    let parts = if slice_size == 0 {
-        0
-    } else {
-        file_size.div_ceil(slice_size)
-    };
-
-    // slice_md5: 文件前 256KB
-    let slice_md5 = {
-        let slice_len = HEADER_SLICE_SIZE.min(file_size) as usize;
-        let mut buffer = vec![0u8; slice_len];
-        file.read_exact(&mut buffer)?;
-        let mut hasher = Md5::new();
-        Digest::update(&mut hasher, &buffer);
-        hex::encode(hasher.finalize())
-    };
-
-    file.rewind()?;
-
-    // content_md5 与每块 md5
-    let mut file_hasher = Md5::new();
-    let mut block_list = Vec::with_capacity(parts as usize);
-    for i in 0..parts {
-        let is_last = i == parts - 1;
-        let this_len = if is_last {
-            let rem = file_size % slice_size;
-            if rem == 0 {
-                slice_size
-            } else {
-                rem
+    let cache = cache.map(|cache| (cache, Md5CacheKey::new(file_path, &file_meta, slice_size)));
+    let cached = cache
+        .as_ref()
+        .and_then(|(cache, key)| cache.get(key, with_checksums));
+    let (block_list, content_md5, slice_md5) = match cached {
+        Some(checksums) => {
+            debug!("文件 {} 未变化，使用缓存的校验和", file_path);
+            hash_progress(file_size, file_size);
+            checksums
+        }
+        None => {
+            let checksums = slice_checksums(
+                &mut file,
+                file_size,
+                slice_size,
+                with_checksums,
+                hash_progress,
+            )?;
+            if let Some((cache, key)) = cache {
+                cache.insert(key, &checksums);
             }
-        } else {
-            slice_size
-        } as usize;
-        let mut buffer = vec![0u8; this_len];
-        file.read_exact(&mut buffer)?;
-        Digest::update(&mut file_hasher, &buffer);
-        let mut part_hasher = Md5::new();
-        Digest::update(&mut part_hasher, &buffer);
-        block_list.push(hex::encode(part_hasher.finalize()));
-    }
-    let content_md5 = hex::encode(file_hasher.finalize());
+            checksums
+        }
+    };
 
     Ok(PcsFileSliceInfo {
         path: file_path.to_string(),
@@ -142,36 +944,571 @@ fn get_file_block_list(
     })
 }
 
-fn if_rest_ok_then_get_else_err<R>(text: String) -> Result<R, AppError>
-where
-    R: DeserializeOwned,
-{
-    let status: PcsApiError = serde_json::from_str(text.as_str()).unwrap_or_else(|_| PcsApiError {
-        errno: i32::MIN,
-        err_msg: None,
-        request_id: None,
-        raw: text.clone(),
-    });
-    match status.errno {
-        0 => {
-            let resp: R = serde_json::from_str(text.as_str())?;
-            Ok(resp)
+/// 各分片 md5、整体 md5、校验段 md5
+type SliceChecksums = (Vec<String>, Option<String>, Option<String>);
+
+/// 校验和缓存的键：文件路径、大小、修改时间（纳秒）及计算所用的分片大小，任一变化即视为文件已改变
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Md5CacheKey {
+    path: String,
+    size: u64,
+    mtime_ns: u64,
+    slice_size: u64,
+}
+
+impl Md5CacheKey {
+    fn new(file_path: &str, meta: &std::fs::Metadata, slice_size: u64) -> Self {
+        let path = std::fs::canonicalize(file_path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| file_path.to_string());
+        let mtime_ns = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self {
+            path,
+            size: meta.len(),
+            mtime_ns,
+            slice_size,
         }
-        _ => Err(status.into()),
     }
 }
 
-enum HttpMethod {
-    Get,
-    Post,
+/// 校验和缓存文件中的一行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Md5CacheEntry {
+    #[serde(flatten)]
+    key: Md5CacheKey,
+    block_list: Vec<String>,
+    content_md5: Option<String>,
+    slice_md5: Option<String>,
 }
 
-/// payload 的编码格式
-enum PayloadFormat {
-    /// application/x-www-form-urlencoded（默认）
-    UrlEncoded,
-    /// multipart/form-data
-    Multipart,
+/// 本地文件校验和缓存，由 `BaiduPcsClient::set_md5_cache` 启用
+/// 以 (路径, 大小, 修改时间) 为键记录分片上传所需的各分片 MD5、整文件 MD5 与校验段 MD5，
+/// 重复备份未变化的文件时无需重新读取计算。缓存文件为 JSON Lines 格式，每次计算后追加一行，
+/// 同一文件以最后一条为准；加载时过期记录多于有效记录则重写文件
+pub struct Md5Cache {
+    /// 缓存文件路径，为 None 时只在内存中缓存
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Md5CacheEntry>>,
+}
+
+impl Md5Cache {
+    /// 仅在内存中缓存，客户端释放后失效
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从缓存文件加载，文件不存在时为空；无法解析的行被忽略
+    pub fn load<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let mut entries = HashMap::new();
+        let mut lines = 0usize;
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            for line in content.lines().filter(|line| !line.is_empty()) {
+                lines += 1;
+                if let Ok(entry) = serde_json::from_str::<Md5CacheEntry>(line) {
+                    entries.insert(entry.key.path.clone(), entry);
+                }
+            }
+        }
+        let cache = Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+        };
+        if lines > cache.len() * 2 {
+            cache.compact();
+        }
+        cache
+    }
+
+    /// 缓存的文件数量
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 查找与 `key` 完全一致的记录；需要整文件校验和而记录中没有时（快速上传模式下计算的）视为未命中
+    fn get(&self, key: &Md5CacheKey, with_checksums: bool) -> Option<SliceChecksums> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&key.path).filter(|entry| entry.key == *key)?;
+        if !with_checksums {
+            return Some((entry.block_list.clone(), None, None));
+        }
+        entry.content_md5.as_ref()?;
+        Some((
+            entry.block_list.clone(),
+            entry.content_md5.clone(),
+            entry.slice_md5.clone(),
+        ))
+    }
+
+    fn insert(&self, key: Md5CacheKey, checksums: &SliceChecksums) {
+        use std::io::Write;
+        let (block_list, content_md5, slice_md5) = checksums.clone();
+        let entry = Md5CacheEntry {
+            key,
+            block_list,
+            content_md5,
+            slice_md5,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(path) = &self.path {
+            let written = serde_json::to_string(&entry)
+                .map_err(std::io::Error::from)
+                .and_then(|line| {
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .and_then(|mut f| writeln!(f, "{}", line))
+                });
+            if let Err(e) = written {
+                warn!("写入校验和缓存失败: {} - {}", path.display(), e);
+            }
+        }
+        entries.insert(entry.key.path.clone(), entry);
+    }
+
+    /// 只保留每个文件的最新记录，重写缓存文件
+    fn compact(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let entries = self.entries.lock().unwrap();
+        let content: String = entries
+            .values()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect();
+        let tmp = path.with_extension("tmp");
+        let result = std::fs::write(&tmp, content).and_then(|_| std::fs::rename(&tmp, path));
+        if let Err(e) = result {
+            warn!("整理校验和缓存失败: {} - {}", path.display(), e);
+            let _ = std::fs::remove_file(&tmp);
+        }
+    }
+}
+
+/// 按分片大小读取 `size` 字节数据，计算各分片 md5，以及（`with_checksums` 时）整体 md5 与校验段 md5
+/// # Returns
+/// * `(block_list, content_md5, slice_md5)`
+fn slice_checksums<R: Read>(
+    reader: &mut R,
+    file_size: u64,
+    slice_size: u64,
+    with_checksums: bool,
+    hash_progress: &mut dyn FnMut(u64, u64),
+) -> Result<SliceChecksums, AppError> {
+    let parts = if slice_size == 0 {
+        0
+    } else {
+        file_size.div_ceil(slice_size)
+    };
+
+    let md5_hex = |data: &[u8]| {
+        let mut hasher = Md5::new();
+        Digest::update(&mut hasher, data);
+        hex::encode(hasher.finalize())
+    };
+    // 每块 md5，以及（非快速模式下）content_md5 与 slice_md5，单次读取完成
+    // slice_md5 为文件前 256KB 的 md5，分片大小不小于 4MB，直接取自第一个分片
+    let mut file_hasher = with_checksums.then(Md5::new);
+    let mut slice_md5 = with_checksums.then(|| md5_hex(&[]));
+    let mut block_list = Vec::with_capacity(parts as usize);
+    let mut hashed: u64 = 0;
+    for i in 0..parts {
+        let is_last = i == parts - 1;
+        let this_len = if is_last {
+            let rem = file_size % slice_size;
+            if rem == 0 {
+                slice_size
+            } else {
+                rem
+            }
+        } else {
+            slice_size
+        } as usize;
+        let mut buffer = vec![0u8; this_len];
+        reader.read_exact(&mut buffer)?;
+        if let Some(file_hasher) = file_hasher.as_mut() {
+            if i == 0 {
+                let slice_len = HEADER_SLICE_SIZE.min(this_len as u64) as usize;
+                slice_md5 = Some(md5_hex(&buffer[..slice_len]));
+            }
+            Digest::update(file_hasher, &buffer);
+        }
+        block_list.push(md5_hex(&buffer));
+        hashed += this_len as u64;
+        hash_progress(hashed, file_size);
+    }
+    let content_md5 = file_hasher.map(|hasher| hex::encode(hasher.finalize()));
+    Ok((block_list, content_md5, slice_md5))
+}
+
+fn if_rest_ok_then_get_else_err<R>(text: String) -> Result<R, AppError>
+where
+    R: DeserializeOwned,
+{
+    let status: PcsApiError = serde_json::from_str(text.as_str()).unwrap_or_else(|_| PcsApiError {
+        errno: i32::MIN,
+        err_msg: None,
+        request_id: None,
+        raw: text.clone(),
+    });
+    match status.errno {
+        0 => {
+            let resp: R = serde_json::from_str(text.as_str())?;
+            Ok(resp)
+        }
+        _ => Err(status.into()),
+    }
+}
+
+/// 将字符串中残留的 `\uXXXX` 转义序列解码为对应字符（如 dlink 中的 `\u0026` → `&`）
+/// 无法解析的转义序列原样保留
+fn decode_unicode_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find("\\u") {
+        out.push_str(&rest[..idx]);
+        let decoded = rest
+            .get(idx + 2..idx + 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32);
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[idx + 6..];
+            }
+            None => {
+                out.push_str("\\u");
+                rest = &rest[idx + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 深度优先遍历目录，`list` 负责列出单个目录下的条目
+/// 目录条目本身也包含在结果中，且位于其子条目之前
+/// `dir_empty` 为 1 只表示目录下没有子目录，仍可能有文件，因此每个目录都会展开
+fn walk_dir<L>(
+    path: &str,
+    max_depth: Option<usize>,
+    list: &mut L,
+) -> Result<Vec<PcsFileItem>, AppError>
+where
+    L: FnMut(&str) -> Result<Vec<PcsFileItem>, AppError>,
+{
+    let mut result = Vec::new();
+    if max_depth == Some(0) {
+        return Ok(result);
+    }
+    for item in list(path)? {
+        let sub_dir = (*item.is_dir() == 1).then(|| item.path().clone());
+        result.push(item);
+        if let Some(dir) = sub_dir {
+            result.extend(walk_dir(dir.as_str(), max_depth.map(|d| d - 1), list)?);
+        }
+    }
+    Ok(result)
+}
+
+/// 递归统计目录下所有文件的总大小，`list` 负责列出单个目录下的条目
+fn dir_size_with<L>(path: &str, list: &mut L) -> Result<u64, AppError>
+where
+    L: FnMut(&str) -> Result<Vec<PcsFileItem>, AppError>,
+{
+    Ok(walk_dir(path, None, list)?
+        .iter()
+        .filter(|item| *item.is_dir() != 1)
+        .map(|item| *item.size())
+        .sum())
+}
+
+/// 统计目录下各直接子目录的递归大小，按大小降序（相同时按路径）排列
+fn size_breakdown<L>(path: &str, list: &mut L) -> Result<Vec<(String, u64)>, AppError>
+where
+    L: FnMut(&str) -> Result<Vec<PcsFileItem>, AppError>,
+{
+    let mut sizes = Vec::new();
+    for item in list(path)? {
+        if *item.is_dir() == 1 {
+            let size = dir_size_with(item.path(), list)?;
+            sizes.push((item.path().clone(), size));
+        }
+    }
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(sizes)
+}
+
+/// 目录下载结果：每个文件的 (远程路径, 下载结果)，按远程路径排序
+pub type DownloadDirResult = Vec<(String, Result<(), AppError>)>;
+
+/// 目录下载计划：需要创建的本地目录，以及 (文件ID, 远程路径, 本地路径) 列表
+type DownloadDirPlan = (Vec<PathBuf>, Vec<(u64, String, String)>);
+
+/// 按远程目录树生成本地的目录与文件路径，本地路径与 `remote_dir` 下的相对路径一致
+/// 不在 `remote_dir` 下的条目会被忽略
+fn download_dir_plan(remote_dir: &str, local_dir: &str, items: &[PcsFileItem]) -> DownloadDirPlan {
+    let prefix = format!("{}/", remote_dir.trim_end_matches('/'));
+    let mut dirs = vec![PathBuf::from(local_dir)];
+    let mut files = Vec::new();
+    for item in items {
+        let Some(relative) = item.path().strip_prefix(prefix.as_str()) else {
+            continue;
+        };
+        let local_path = Path::new(local_dir).join(relative);
+        if *item.is_dir() == 1 {
+            dirs.push(local_path);
+        } else {
+            files.push((
+                *item.fs_id(),
+                item.path().clone(),
+                local_path.to_string_lossy().to_string(),
+            ));
+        }
+    }
+    (dirs, files)
+}
+
+/// 删除接口的 async 参数：`Some(true)` 同步(0)，`None` 自适应(1)，`Some(false)` 异步(2)
+fn delete_async_mode(is_async: Option<bool>) -> u8 {
+    match is_async {
+        Some(false) => 2,
+        Some(true) => 0,
+        None => 1,
+    }
+}
+
+/// 将路径分批交给 `submit` 提交，并合并各批次的结果
+/// 合并后的 `task_id` 取最后一个返回任务ID的批次
+fn delete_in_batches<F>(
+    paths: &[String],
+    batch_size: usize,
+    mut submit: F,
+) -> Result<PcsFileTaskOperationResult, AppError>
+where
+    F: FnMut(&[String]) -> Result<PcsFileTaskOperationResult, AppError>,
+{
+    let mut merged = PcsFileTaskOperationResult {
+        info: Vec::new(),
+        task_id: None,
+    };
+    for batch in paths.chunks(batch_size.max(1)) {
+        let result = submit(batch)?;
+        merged.info.extend(result.info);
+        if result.task_id.is_some() {
+            merged.task_id = result.task_id;
+        }
+    }
+    Ok(merged)
+}
+
+/// 按每个文件的 errno 确定任务的最终状态：有文件处理失败时整体视为失败，并逐项记录失败的文件
+fn settle_file_task(mut status: PcsFileTaskStatus) -> PcsFileTaskStatus {
+    let failed = status.failures().len();
+    for item in status.failures() {
+        warn!(
+            "文件任务中 {} 处理失败(errno {})",
+            item.from().as_deref().unwrap_or_default(),
+            item.errno()
+        );
+    }
+    if failed > 0 {
+        status.status = "failed".to_string();
+    }
+    status
+}
+
+/// 轮询文件管理任务直到结束或超时
+/// `query` 每次调用返回任务的最新状态，两次查询之间间隔 `interval`
+/// 任务结束时按每个文件的 errno 确定最终状态，见 `settle_file_task`
+fn poll_file_task<Q>(
+    mut query: Q,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<PcsFileTaskStatus, AppError>
+where
+    Q: FnMut() -> Result<PcsFileTaskStatus, AppError>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        let status = query()?;
+        debug!("文件任务状态: {}", status.status());
+        if status.is_finished() {
+            return Ok(settle_file_task(status));
+        }
+        if Instant::now() + interval > deadline {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!(
+                    "等待文件任务超时({:?})，最后状态: {}",
+                    timeout,
+                    status.status()
+                )
+                .as_str(),
+                None,
+            ));
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// 将（递归）列出的文件按路径组织为树形文本，类似 `tree` 命令的输出
+/// 每棵树以其所在的上级目录为根，同一目录下的条目按名称排序，目录与文件使用不同的图标
+pub fn format_tree(items: &[PcsFileItem]) -> String {
+    let parent_of = |path: &str| match path.rfind('/') {
+        Some(0) => "/".to_string(),
+        Some(idx) => path[..idx].to_string(),
+        None => String::new(),
+    };
+    let mut children: BTreeMap<String, Vec<&PcsFileItem>> = BTreeMap::new();
+    for item in items {
+        children
+            .entry(parent_of(item.path()))
+            .or_default()
+            .push(item);
+    }
+    for list in children.values_mut() {
+        list.sort_by(|a, b| a.server_filename().cmp(b.server_filename()));
+    }
+    let listed: HashSet<&str> = items.iter().map(|item| item.path().as_str()).collect();
+    let mut lines = Vec::new();
+    for root in children.keys().filter(|p| !listed.contains(p.as_str())) {
+        lines.push(root.clone());
+        format_tree_level(&children, root, "", &mut lines);
+    }
+    lines.join("\n")
+}
+
+fn format_tree_level(
+    children: &BTreeMap<String, Vec<&PcsFileItem>>,
+    parent: &str,
+    prefix: &str,
+    lines: &mut Vec<String>,
+) {
+    let Some(list) = children.get(parent) else {
+        return;
+    };
+    for (i, item) in list.iter().enumerate() {
+        let last = i == list.len() - 1;
+        let is_dir = *item.is_dir() == 1;
+        lines.push(format!(
+            "{}{}{} {}",
+            prefix,
+            if last { "└── " } else { "├── " },
+            if is_dir { "📁" } else { "📄" },
+            item.server_filename()
+        ));
+        if is_dir {
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            format_tree_level(children, item.path(), &prefix, lines);
+        }
+    }
+}
+
+/// 下载地址（dlink）过期的表现：请求返回 HTTP 403/404
+fn is_expired_link_error(error: &AppError) -> bool {
+    error.error_type == AppErrorType::Network && matches!(error.errno, Some(403) | Some(404))
+}
+
+/// 使用 `link` 下载，若地址已过期则通过 `refresh` 重新获取下载地址后重试一次
+fn download_with_link_refresh<D, R>(link: &str, mut download: D, refresh: R) -> Result<(), AppError>
+where
+    D: FnMut(&str) -> Result<(), AppError>,
+    R: FnOnce() -> Result<String, AppError>,
+{
+    match download(link) {
+        Err(e) if is_expired_link_error(&e) => {
+            warn!("下载地址可能已过期({})，重新获取下载地址后重试", e);
+            download(refresh()?.as_str())
+        }
+        result => result,
+    }
+}
+
+/// 下载过程中使用的临时文件路径，下载完成后重命名为 `local_path`
+pub fn download_part_path(local_path: &str) -> String {
+    format!("{}.part", local_path)
+}
+
+/// 与 `.part` 文件一同保存的下载记录路径（`{local_path}.part.meta`），下载完成后删除
+pub fn download_sidecar_path(local_path: &str) -> String {
+    format!("{}.meta", download_part_path(local_path))
+}
+
+/// 断点续传的下载记录：远程文件的大小与云端哈希，用于确认已下载的部分属于同一远程文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DownloadSidecar {
+    size: u64,
+    md5: String,
+}
+
+impl DownloadSidecar {
+    /// 文件信息中没有 md5 时无法确认是否为同一文件，不支持续传
+    fn from_meta(meta: &PcsFileMetaInfo) -> Option<Self> {
+        meta.md5.as_ref().map(|md5| Self {
+            size: meta.size,
+            md5: md5.clone(),
+        })
+    }
+
+    fn load(path: &str) -> Option<Self> {
+        serde_json::from_str(std::fs::read_to_string(path).ok()?.as_str()).ok()
+    }
+}
+
+/// 按下载记录判断能否续传：记录与 `expected` 一致时返回 `.part` 中已下载的字节数；
+/// 否则丢弃已下载的部分并写入新的记录，返回 0。`expected` 为 None 时只删除旧记录
+fn prepare_resume(local_path: &str, expected: Option<&DownloadSidecar>) -> Result<u64, AppError> {
+    let part_path = download_part_path(local_path);
+    let sidecar_path = download_sidecar_path(local_path);
+    let Some(expected) = expected else {
+        let _ = std::fs::remove_file(&sidecar_path);
+        return Ok(0);
+    };
+    let part_len = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    if part_len > 0 {
+        if part_len <= expected.size
+            && DownloadSidecar::load(sidecar_path.as_str()).as_ref() == Some(expected)
+        {
+            info!("继续下载 {}，已下载 {} 字节", local_path, part_len);
+            return Ok(part_len);
+        }
+        info!(
+            "远程文件已变化或缺少下载记录，丢弃已下载的部分 {}",
+            part_path
+        );
+        std::fs::remove_file(&part_path)?;
+    }
+    std::fs::write(&sidecar_path, serde_json::to_string(expected)?)?;
+    Ok(0)
+}
+
+enum HttpMethod {
+    Get,
+    Post,
+}
+
+/// payload 的编码格式
+enum PayloadFormat {
+    /// application/x-www-form-urlencoded（默认）
+    UrlEncoded,
+    /// multipart/form-data
+    Multipart,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -202,7 +1539,36 @@ impl BaiduPcsClient {
     }
 
     pub fn new_with_dns(access_token: &str, app: BaiduPcsApp, dns: Option<&str>) -> Self {
-        let builder = Client::builder();
+        Self::new_with_headers(access_token, app, dns, HeaderMap::new())
+    }
+
+    /// 创建附带自定义请求头的客户端，例如经过需要认证的代理时附加代理令牌或 Cookie
+    /// `extra_headers` 合并到默认请求头中，同名时覆盖默认值；可通过 `parse_headers` 由字符串构造并校验
+    pub fn new_with_headers(
+        access_token: &str,
+        app: BaiduPcsApp,
+        dns: Option<&str>,
+        extra_headers: HeaderMap,
+    ) -> Self {
+        Self::new_with_http_options(
+            access_token,
+            app,
+            dns,
+            extra_headers,
+            &HttpOptions::default(),
+        )
+    }
+
+    /// 创建使用指定连接池与 TCP 选项的客户端，其余参数同 `new_with_headers`
+    /// 高延迟网络下并发上传时，可调大 `pool_max_idle_per_host` 以复用更多连接
+    pub fn new_with_http_options(
+        access_token: &str,
+        app: BaiduPcsApp,
+        dns: Option<&str>,
+        extra_headers: HeaderMap,
+        http_options: &HttpOptions,
+    ) -> Self {
+        let builder = http_options.apply(Client::builder());
         // 应用用户代理与通用头
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert("User-Agent", "pan.baidu.com".parse().unwrap());
@@ -213,6 +1579,7 @@ impl BaiduPcsClient {
         let builder = crate::dns::use_custom_dns_if_present(builder, dns);
 
         headers.insert("Accept", "application/json".parse().unwrap());
+        headers.extend(extra_headers);
         Self {
             pcs_app: app,
             client: builder.default_headers(headers).build().unwrap(),
@@ -221,19 +1588,152 @@ impl BaiduPcsClient {
             user_info: None,
             disk_quota: None,
             dns: dns.map(|s| s.to_string()),
+            enforce_apps_dir: true,
+            download_buffer_size: DOWNLOAD_BUFFER_SIZE,
+            fast_upload: false,
+            backup_dedup: false,
+            md5_cache: None,
+            cwd: String::from("/"),
+            request_limiter: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS)),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            api_base_url: String::from(PREFIX),
+            file_server_url: String::from(PREFIX_FILE_SERVER),
         }
     }
 
+    /// 关闭客户端及其异步运行时，最多等待 10 秒让未完成的文件写入等阻塞任务结束
+    /// 直接 drop 客户端时会无限期等待这些任务；嵌入到其他应用中时建议在退出前显式调用
+    pub fn shutdown(self) {
+        self.shutdown_timeout(SHUTDOWN_TIMEOUT);
+    }
+
+    /// 关闭客户端及其异步运行时，最多等待 `timeout`，超时后未完成的任务被放弃
+    pub fn shutdown_timeout(self, timeout: Duration) {
+        debug!("关闭客户端运行时，最长等待 {:?}", timeout);
+        self.runtime.shutdown_timeout(timeout);
+    }
+
+    /// 设置小文件上传时是否自动将路径改写到 /apps/{app-name}/ 下
+    /// # Arguments
+    /// * `enforce` - true（默认）自动添加前缀；false 按原路径上传（需应用拥有相应权限）
+    pub fn set_enforce_apps_dir(&mut self, enforce: bool) {
+        self.enforce_apps_dir = enforce;
+    }
+
+    /// 小文件上传时是否自动将路径改写到 /apps/{app-name}/ 下
+    pub fn is_enforce_apps_dir(&self) -> bool {
+        self.enforce_apps_dir
+    }
+
+    /// 设置下载写入本地文件时的缓冲区大小（字节），默认 1MB，0 表示每个分块直接写入文件
+    pub fn set_download_buffer_size(&mut self, size: usize) {
+        self.download_buffer_size = size;
+    }
+
+    /// 设置快速上传模式
+    /// 开启后大文件上传只计算接口必需的分片 MD5，不计算整文件 MD5 与校验段 MD5，
+    /// 可减少 CPU 开销，但服务端无法据此秒传，适合仅覆盖上传的场景
+    pub fn set_fast_upload(&mut self, fast: bool) {
+        self.fast_upload = fast;
+    }
+
+    /// 是否为快速上传模式
+    pub fn is_fast_upload(&self) -> bool {
+        self.fast_upload
+    }
+
+    /// 设置本地文件校验和缓存，上传前计算分片 MD5 时先查缓存，文件未变化（路径、大小、修改时间一致）时直接复用
+    /// 重复备份大量未变化的文件时可省去绝大部分读取与计算，`None` 关闭缓存（默认）
+    pub fn set_md5_cache(&mut self, cache: Option<Md5Cache>) {
+        self.md5_cache = cache;
+    }
+
+    /// 设置 `backup_file` 备份目录时是否对相同内容的文件去重，默认关闭
+    /// 开启后会先计算本地文件的 MD5，内容相同的文件只上传第一个，其余从已上传的位置云端复制，
+    /// 省去重复文件的预上传等请求；复制失败时（如目标已存在）回退为正常上传
+    pub fn set_backup_dedup(&mut self, dedup: bool) {
+        self.backup_dedup = dedup;
+    }
+
+    /// 备份目录时是否对相同内容的文件去重
+    pub fn is_backup_dedup(&self) -> bool {
+        self.backup_dedup
+    }
+
+    /// 设置当前远程工作目录，`path` 为相对路径时以当前工作目录为基准
+    /// 仅改变客户端状态，不检查远程目录是否存在
+    pub fn set_cwd(&mut self, path: &str) {
+        self.cwd = self.resolve_path(path);
+    }
+
+    /// 当前远程工作目录
+    pub fn cwd(&self) -> &str {
+        self.cwd.as_str()
+    }
+
+    /// 将远程路径解析为绝对路径：以 / 开头的路径原样返回，否则相对当前工作目录并规范化 `.` 与 `..`
+    pub fn resolve_path(&self, path: &str) -> String {
+        resolve_remote_path(self.cwd.as_str(), path)
+    }
+
+    /// 设置同时进行中的请求数量上限（接口请求、上传与下载共用），默认 4，最小为 1
+    /// 多线程共享同一客户端并行操作时，可避免请求过多触发接口频控
+    pub fn set_max_concurrent_requests(&mut self, max: usize) {
+        let max = max.max(1);
+        self.request_limiter = Arc::new(Semaphore::new(max));
+        self.max_concurrent_requests = max;
+    }
+
+    /// 同时进行中的请求数量上限
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    /// 设置接口请求与文件上传的服务地址，用于代理转发或对接模拟服务器测试
+    /// # Arguments
+    /// * `api_base_url` - 接口请求地址，默认 `https://pan.baidu.com`
+    /// * `file_server_url` - 文件上传地址，默认 `https://d.pcs.baidu.com`
+    pub fn set_base_urls(&mut self, api_base_url: &str, file_server_url: &str) {
+        self.api_base_url = api_base_url.trim_end_matches('/').to_string();
+        self.file_server_url = file_server_url.trim_end_matches('/').to_string();
+    }
+
+    /// 接口请求的服务地址
+    pub fn api_base_url(&self) -> &str {
+        self.api_base_url.as_str()
+    }
+
+    /// 文件上传的服务地址
+    pub fn file_server_url(&self) -> &str {
+        self.file_server_url.as_str()
+    }
+
     pub fn ware(&mut self) -> Result<(), AppError> {
         self.user_info = Some(self.get_user_info()?);
         self.disk_quota = Some(self.get_user_quota(false, false)?);
         Ok(())
     }
 
+    /// 获取账号概况：重新拉取用户信息与容量（同时更新 `ware` 缓存的信息），汇总会员类型及容量使用百分比
+    pub fn account_summary(&mut self) -> Result<AccountSummary, AppError> {
+        let user = self.get_user_info()?;
+        let quota = self.get_user_quota(false, false)?;
+        let summary = AccountSummary::new(user.clone(), &quota);
+        self.user_info = Some(user);
+        self.disk_quota = Some(quota);
+        Ok(summary)
+    }
+
     pub fn get_apps_path(&self) -> PathBuf {
         PathBuf::from("/apps").join(self.pcs_app.get_app_name())
     }
 
+    /// 将其他应用目录下的路径映射到当前应用目录，用于恢复其他应用创建的备份
+    /// 例如 `/apps/{from_app}/a/b.txt` -> `/apps/{当前应用}/a/b.txt`，不在 `/apps/{from_app}` 下的路径原样返回
+    pub fn remap_app_path(&self, path: &str, from_app: &str) -> String {
+        replace_app_dir(path, from_app, self.pcs_app.get_app_name().as_str())
+    }
+
     fn request<T, P, R>(
         &self,
         m: HttpMethod,
@@ -246,7 +1746,7 @@ impl BaiduPcsClient {
         P: Serialize,
         R: DeserializeOwned,
     {
-        let url = format!("{}{}", PREFIX, path);
+        let url = format!("{}{}", self.api_base_url, path);
         self._request(url, m, params, payload, PayloadFormat::UrlEncoded)
     }
 
@@ -262,7 +1762,7 @@ impl BaiduPcsClient {
         P: Serialize,
         R: DeserializeOwned,
     {
-        let url = format!("{}{}", PREFIX, path);
+        let url = format!("{}{}", self.api_base_url, path);
         self._request(url, m, params, payload, PayloadFormat::Multipart)
     }
 
@@ -328,14 +1828,13 @@ impl BaiduPcsClient {
             .query(&params)
             .query(&[("access_token", self.access_token.as_str())])
             .send()
-            .await
-            .unwrap()
+            .await?
             .text()
             .await
         };
         let text = self
             .runtime
-            .block_on(fetch)
+            .block_on(with_permit(&self.request_limiter, fetch))
             .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), None))?;
         debug!("_request response text: {}", text);
         if_rest_ok_then_get_else_err(text)
@@ -355,6 +1854,14 @@ impl BaiduPcsClient {
         self.request(Get, PATH, PARAMS, None::<()>)
     }
 
+    /// 检查 access token 是否仍然有效
+    ///
+    /// 使用最轻量的用户信息接口发起一次请求，凭据失效时返回 `Ok(false)`，网络等其他错误返回 `Err`，
+    /// 以便调用方区分“需要重新认证”与“暂时无法确认”
+    pub fn check_token(&self) -> Result<bool, AppError> {
+        token_validity(self.get_user_info())
+    }
+
     /// 获取网盘容量信息
     ///
     /// 本接口用于获取用户的网盘空间的使用情况，包括总空间大小，已用空间和剩余可用空间情况。
@@ -446,10 +1953,38 @@ impl BaiduPcsClient {
     /// # Arguments
     /// * `path` - 文件或目录的绝对路径
     /// * `async` - 是否异步删除，默认为0
+    ///
+    /// 路径较多时按每批 `DELETE_BATCH_SIZE` 个分批提交，结果合并为一个返回值
+    ///
+    /// 被删除的文件会进入网盘回收站（普通用户保留 10 天，会员更久），并非永久删除，参见 `trash`
     pub fn delete(
         &self,
-        paths: &Vec<String>,
+        paths: &[String],
         is_async: Option<bool>,
+    ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
+        let async_mode = delete_async_mode(is_async);
+        delete_in_batches(paths, DELETE_BATCH_SIZE, |batch| {
+            self.delete_with_mode(batch, async_mode)
+        })
+    }
+
+    /// 将文件或目录移入回收站（即 `delete` 的默认行为，由服务端自适应同步/异步）
+    ///
+    /// 开放平台的删除接口没有“永久删除”参数，也没有提供清空回收站的接口：
+    /// 移入回收站的文件在保留期内仍可在百度网盘客户端/网页中还原，
+    /// 需要彻底删除时请在百度网盘客户端/网页中清空回收站
+    pub fn trash(
+        &self,
+        paths: &[String],
+    ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
+        self.delete(paths, None)
+    }
+
+    /// 以指定的异步模式删除文件，`async_mode`：0 同步，1 自适应，2 异步
+    fn delete_with_mode(
+        &self,
+        paths: &[String],
+        async_mode: u8,
     ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/file";
         #[derive(Serialize)]
@@ -467,11 +2002,7 @@ impl BaiduPcsClient {
             file_list: String,
         }
         let files = DeleteAttributes {
-            r#async: match is_async {
-                Some(false) => 2,
-                Some(true) => 0,
-                None => 1,
-            },
+            r#async: async_mode,
             file_list: serde_json::to_string(paths)?,
         };
         self.request(
@@ -495,7 +2026,7 @@ impl BaiduPcsClient {
         src: &str,
         dest: &str,
     ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
-        self.file_manager_copy_or_move("copy", src, dest)
+        self.file_manager_copy_or_move("copy", src, dest, 1)
     }
 
     /// 移动/重命名远程文件或目录
@@ -508,15 +2039,118 @@ impl BaiduPcsClient {
         src: &str,
         dest: &str,
     ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
-        self.file_manager_copy_or_move("move", src, dest)
+        self.file_manager_copy_or_move("move", src, dest, 1)
     }
 
-    /// filemanager 接口的 copy/move 通用实现
-    fn file_manager_copy_or_move(
+    /// 异步删除文件并等待任务完成
+    /// 以 async=2 提交删除任务，随后轮询任务状态直到结束或超时，适合大批量删除
+    /// # Arguments
+    /// * `paths` - 待删除的文件/目录绝对路径
+    /// * `timeout` - 等待任务完成的最长时间
+    /// # Returns
+    /// * `PcsFileTaskStatus` - 任务最终状态及每个文件的处理结果
+    pub fn delete_and_wait(
+        &self,
+        paths: &[String],
+        timeout: Duration,
+    ) -> Result<PcsFileTaskStatus, AppError> {
+        let deadline = Instant::now() + timeout;
+        let mut status = PcsFileTaskStatus {
+            status: "success".to_string(),
+            list: Vec::new(),
+        };
+        for batch in paths.chunks(DELETE_BATCH_SIZE) {
+            let submitted = self.delete_with_mode(batch, 2)?;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let batch_status = self.wait_file_task(submitted, remaining)?;
+            if !batch_status.is_success() {
+                status.status = batch_status.status.clone();
+            }
+            status.list.extend(batch_status.list);
+        }
+        Ok(status)
+    }
+
+    /// 异步复制文件并等待任务完成，参数同 `copy_file`
+    pub fn copy_and_wait(
+        &self,
+        src: &str,
+        dest: &str,
+        timeout: Duration,
+    ) -> Result<PcsFileTaskStatus, AppError> {
+        let submitted = self.file_manager_copy_or_move("copy", src, dest, 2)?;
+        self.wait_file_task(submitted, timeout)
+    }
+
+    /// 异步移动文件并等待任务完成，参数同 `move_file`
+    pub fn move_and_wait(
+        &self,
+        src: &str,
+        dest: &str,
+        timeout: Duration,
+    ) -> Result<PcsFileTaskStatus, AppError> {
+        let submitted = self.file_manager_copy_or_move("move", src, dest, 2)?;
+        self.wait_file_task(submitted, timeout)
+    }
+
+    /// 查询文件管理异步任务的状态
+    /// # Arguments
+    /// * `task_id` - 提交异步任务时返回的任务ID
+    pub fn query_file_task(&self, task_id: &str) -> Result<PcsFileTaskStatus, AppError> {
+        const PATH: &str = "/rest/2.0/xpan/file";
+        #[derive(Serialize)]
+        struct Params<'a> {
+            /// 本接口固定为`taskquery`
+            method: &'a str,
+            taskid: &'a str,
+        }
+        self.request(
+            Get,
+            PATH,
+            Params {
+                method: "taskquery",
+                taskid: task_id,
+            },
+            None::<()>,
+        )
+    }
+
+    /// 等待已提交的文件管理任务完成
+    /// 未返回任务ID时说明服务端已同步处理完成，直接以提交结果作为最终状态
+    /// 有文件的 errno 不为 0 时最终状态为 failed，失败的文件可通过 `PcsFileTaskStatus::failures` 获取
+    fn wait_file_task(
+        &self,
+        submitted: PcsFileTaskOperationResult,
+        timeout: Duration,
+    ) -> Result<PcsFileTaskStatus, AppError> {
+        match submitted.task_id() {
+            Some(task_id) => poll_file_task(
+                || self.query_file_task(task_id),
+                timeout,
+                FILE_TASK_POLL_INTERVAL,
+            ),
+            None => Ok(settle_file_task(PcsFileTaskStatus {
+                status: "success".to_string(),
+                list: submitted
+                    .info()
+                    .iter()
+                    .map(|task| PcsFileTaskStatusItem {
+                        from: Some(task.path().clone()),
+                        to: None,
+                        errno: *task.errno(),
+                    })
+                    .collect(),
+            })),
+        }
+    }
+
+    /// filemanager 接口的 copy/move 通用实现
+    fn file_manager_copy_or_move(
         &self,
         opera: &str,
         src: &str,
         dest: &str,
+        async_mode: u8,
     ) -> Result<crate::baidu_pcs_sdk::PcsFileTaskOperationResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/file";
         #[derive(Serialize)]
@@ -540,7 +2174,7 @@ impl BaiduPcsClient {
         } else {
             let dir = dest_path
                 .parent()
-                .map(|p| p.to_string_lossy().to_string())
+                .map(to_remote_path)
                 .unwrap_or_else(|| "/".to_string());
             let name = dest_path
                 .file_name()
@@ -555,7 +2189,7 @@ impl BaiduPcsClient {
             "ondup": "fail"
         }]);
         let attrs = FileManagerAttributes {
-            r#async: 1,
+            r#async: async_mode,
             file_list: file_list.to_string(),
         };
         self.request(
@@ -590,7 +2224,7 @@ impl BaiduPcsClient {
             ///版本号，本接口固定为2.0
             upload_version: &'a str,
         }
-        let url = format!("{}{}", PREFIX_FILE_SERVER, PATH);
+        let url = format!("{}{}", self.file_server_url, PATH);
         self._request(
             url,
             Get,
@@ -604,11 +2238,98 @@ impl BaiduPcsClient {
             None::<()>,
             PayloadFormat::UrlEncoded,
         )
+        .inspect(|located: &UploadServerResult| {
+            if let Some(offset) = clock_skew(located.server_time_offset()) {
+                warn!(
+                    "本机时钟与服务器相差 {} 秒，文件的 local_ctime/local_mtime 等时间可能不准确，请校准系统时间",
+                    offset
+                );
+            }
+        })
     }
 
     /// 列出目录文件
     /// 本接口用于列出指定目录下的文件和子目录信息。 https://pan.baidu.com/union/doc/mksg0s9l4
     pub fn list_dir(&self, path: &str) -> Result<PcsFileListResult, AppError> {
+        self.list_dir_with(path, false)
+    }
+
+    /// 列出目录文件（web 模式）
+    /// 与 `list_dir` 相同，但会携带 `web=1` 和 `showempty=1`，返回图片的缩略图地址 `thumbs` 及目录的 `dir_empty` 属性
+    pub fn list_dir_web(&self, path: &str) -> Result<PcsFileListResult, AppError> {
+        self.list_dir_with(path, true)
+    }
+
+    /// 列出应用专属空间（`/_pcs_.appdata/xpan/`）下的文件
+    /// 专属空间用于存放应用自身的数据，`subpath` 为相对专属空间根目录的路径，空串表示根目录
+    pub fn list_appdata(&self, subpath: &str) -> Result<PcsFileListResult, AppError> {
+        self.list_dir(appdata_path(subpath).as_str())
+    }
+
+    /// 递归列出目录下的所有文件与子目录
+    /// # Arguments
+    /// * `path` - 目录路径
+    /// * `max_depth` - 最大遍历深度，1 表示仅列出当前目录，`None` 表示不限制
+    pub fn list_dir_recursive(
+        &self,
+        path: &str,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<PcsFileItem>, AppError> {
+        walk_dir(path, max_depth, &mut |dir| self.list_dir_all(dir))
+    }
+
+    /// 递归统计目录下所有文件的总大小（字节）
+    pub fn dir_size(&self, path: &str) -> Result<u64, AppError> {
+        dir_size_with(path, &mut |dir| self.list_dir_all(dir))
+    }
+
+    /// 列出目录下的直接子目录及各自的递归大小，按大小降序排列
+    /// 用于查找占用空间较多的目录；目录下直接存放的文件不计入
+    pub fn dir_size_breakdown(&self, path: &str) -> Result<Vec<(String, u64)>, AppError> {
+        size_breakdown(path, &mut |dir| self.list_dir_all(dir))
+    }
+
+    /// 列出共享目录中的文件
+    /// # Arguments
+    /// * `uk` - 共享目录创建者 ID
+    /// * `shared_fs_id` - 共享目录的 fs_id
+    /// * `subpath` - 共享目录内的路径，`/` 或空字符串表示共享目录本身
+    pub fn list_shared_dir(
+        &self,
+        uk: u64,
+        shared_fs_id: u64,
+        subpath: &str,
+    ) -> Result<PcsFileListResult, AppError> {
+        let share = shared_dir_path(uk, shared_fs_id);
+        let dir = resolve_remote_path("/", subpath);
+        self.list_dir_request(dir.as_str(), false, false, Some(share.as_str()), None)
+    }
+
+    fn list_dir_with(&self, path: &str, web: bool) -> Result<PcsFileListResult, AppError> {
+        let path = self.resolve_path(path);
+        self.list_dir_request(path.as_str(), web, web, None, None)
+    }
+
+    /// 列出目录下的全部条目，条目超过单页上限（1000）时按 start/limit 自动分页
+    /// 请求携带 `showempty=1`，目录条目带有 `dir_empty` 属性（1 表示该目录下没有子目录）
+    pub fn list_dir_all(&self, path: &str) -> Result<Vec<PcsFileItem>, AppError> {
+        let path = self.resolve_path(path);
+        list_dir_pages(|start| {
+            self.list_dir_request(path.as_str(), false, true, None, Some(start))
+                .map(|res| res.list)
+        })
+    }
+
+    /// `share` 为共享目录的 `/uk-fsid`，此时 `dir` 为共享目录内的路径
+    /// `start` 不为 None 时按 `LIST_DIR_PAGE_SIZE` 分页请求，从第 `start` 个条目开始
+    fn list_dir_request(
+        &self,
+        dir: &str,
+        web: bool,
+        show_empty: bool,
+        share: Option<&str>,
+        start: Option<u64>,
+    ) -> Result<PcsFileListResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/file";
         #[derive(Serialize)]
         struct Params<'a> {
@@ -636,32 +2357,40 @@ impl BaiduPcsClient {
             ///是否返回dir_empty属性，0 不返回，1 返回
             #[serde(rename = "showempty")]
             show_empty: Option<i32>,
+            /// 查询共享目录时需要，格式为 `/uk-fsid`
+            path: Option<&'a str>,
         }
 
         let params = Params {
             method: "list",
-            dir: path,
+            dir,
             order: None,
             desc: None,
-            start: None,
-            limit: None,
-            web: None,
+            start,
+            limit: start.map(|_| LIST_DIR_PAGE_SIZE),
+            web: web.then_some(1),
             folder: None,
-            show_empty: None,
+            show_empty: show_empty.then_some(1),
+            path: share,
         };
         self.request(Get, PATH, params, None::<()>)
     }
     async fn create_form(
-        local_file: &str,
+        source: &SliceSource<'_>,
+        content_type: &str,
         progress_info: &ProgressInfo,
         progress_cb: Option<ProgressCallback>,
     ) -> Result<reqwest::multipart::Form, AppError> {
-        let mut file = tokio::fs::File::open(local_file).await?;
-        file.seek(SeekFrom::Start(progress_info.uploaded_bytes))
-            .await?;
-
-        let limited = file.take(progress_info.current_part_bytes);
-        let reader_stream = ReaderStream::new(limited);
+        let reader: Box<dyn tokio::io::AsyncRead + Send + Sync + Unpin> = match source {
+            SliceSource::File(local_file) => {
+                let mut file = tokio::fs::File::open(local_file).await?;
+                file.seek(SeekFrom::Start(progress_info.uploaded_bytes))
+                    .await?;
+                Box::new(file.take(progress_info.current_part_bytes))
+            }
+            SliceSource::Bytes(data) => Box::new(std::io::Cursor::new(data.to_vec())),
+        };
+        let reader_stream = ReaderStream::new(reader);
 
         let base_uploaded = progress_info.uploaded_bytes;
         let total_bytes = progress_info.total_bytes;
@@ -692,7 +2421,7 @@ impl BaiduPcsClient {
         let file_name = format!("file_{}", current_part);
         let part = reqwest::multipart::Part::stream_with_length(body, part_len)
             .file_name(file_name)
-            .mime_str("application/octet-stream")?;
+            .mime_str(content_type)?;
 
         Ok(reqwest::multipart::Form::new().part("file", part))
     }
@@ -702,6 +2431,7 @@ impl BaiduPcsClient {
     /// # Arguments
     /// * `local_file` - 本地文件路径(待上传文件的绝对路径)
     /// * `pcs_path` - 上传后使用的文件绝对路径，云盘的存储路径，需要注意的是有限制只能上传到 /apps/{app-name}/目录下，其他目录会返回 31064
+    ///   默认会自动添加该前缀，可通过 `set_enforce_apps_dir(false)` 关闭
     /// * `when_exists` - 上传的文件绝对路径冲突时的策略。0（默认：冲突时失败）1（冲突时覆盖） 2（冲突时重命名），其他值按照1 处理
     /// # Returns
     /// * `FileUpload` - 文件上传结果
@@ -717,23 +2447,13 @@ impl BaiduPcsClient {
         // 如果用 pan.baidu.com/rest/2.0/xpan/file 会返回 413
         const PATH: &str = "/rest/2.0/pcs/file";
         // 正常小文件上传
-        let mut path_buf = PathBuf::new();
-        path_buf.push("/apps");
-        path_buf.push(self.pcs_app.get_app_name());
-        // 根据限制，只能上传到 /apps/{app-name}/目录下 因此需要检查并自动添加
-        let path_src = PathBuf::from(pcs_path);
-        let pcs_path: String = if path_src.starts_with(&path_buf) {
-            path_src.as_path().to_string_lossy().to_string()
-        } else {
-            // 如果不是 /apps/{app-name}/ 目录下，自动添加
-            path_buf.push(pcs_path.strip_prefix("/").unwrap());
-            path_buf.as_path().to_string_lossy().to_string()
-        };
+        let pcs_path = self.resolve_single_upload_path(pcs_path);
         let pcs_path = pcs_path.as_str();
 
         let future = async {
             let form = Self::create_form(
-                local_file,
+                &SliceSource::File(local_file),
+                guess_content_type(local_file).as_str(),
                 &ProgressInfo {
                     total_bytes: file.metadata().unwrap().len(),
                     uploaded_bytes: 0,
@@ -746,7 +2466,7 @@ impl BaiduPcsClient {
             .unwrap();
             debug!("file len: {}", file.metadata().unwrap().len());
             self.client
-                .post(format!("{}{}", PREFIX_FILE_SERVER, PATH))
+                .post(format!("{}{}", self.file_server_url, PATH))
                 .query(&[
                     // 本接口固定为upload
                     ("method", "upload"),
@@ -773,7 +2493,7 @@ impl BaiduPcsClient {
         };
         // 文件上传使用单独的runtime
         let runtime = tokio::runtime::Runtime::new()?;
-        let text = runtime.block_on(future)?;
+        let text = runtime.block_on(with_permit(&self.request_limiter, future))?;
         debug!("upload_single_file {} ->text: {}", pcs_path, text);
         let resp: serde_json::error::Result<PcsFileUploadResult> = serde_json::from_str(&text);
         match resp {
@@ -790,6 +2510,98 @@ impl BaiduPcsClient {
         }
     }
 
+    /// 计算小文件上传时实际使用的云盘路径
+    /// 开启 `enforce_apps_dir` 时，不在 /apps/{app-name}/ 下的路径会自动添加该前缀，否则原样返回
+    pub(crate) fn resolve_single_upload_path(&self, pcs_path: &str) -> String {
+        let pcs_path = self.resolve_path(pcs_path);
+        if !self.enforce_apps_dir {
+            return pcs_path;
+        }
+        let mut path_buf = self.get_apps_path();
+        // 根据限制，只能上传到 /apps/{app-name}/目录下 因此需要检查并自动添加
+        let path_src = PathBuf::from(pcs_path.as_str());
+        if path_src.starts_with(&path_buf) {
+            to_remote_path(&path_src)
+        } else {
+            // 如果不是 /apps/{app-name}/ 目录下，自动添加
+            path_buf.push(pcs_path.trim_start_matches('/'));
+            to_remote_path(&path_buf)
+        }
+    }
+
+    /// 上传文件并报告是否发生了重命名
+    /// 使用 `PcsUploadPolicy::Rename`/`NewCopy` 时，服务端可能将文件保存为其他名称（如 `text(1).txt`），
+    /// 调用方可据此用真实的远程路径更新本地状态
+    /// # Returns
+    /// * `(PcsFileUploadResult, bool)` - 上传结果，以及实际路径是否与 `pcs_path` 不同
+    pub fn upload_and_report(
+        &self,
+        local_file: &str,
+        pcs_path: &str,
+        policy: PcsUploadPolicy,
+    ) -> Result<(PcsFileUploadResult, bool), AppError> {
+        let result = self.upload_large_file(local_file, pcs_path, policy, |_| {})?;
+        let renamed = result.is_renamed_from(self.resolve_path(pcs_path).as_str());
+        if renamed {
+            info!("文件 {} 已被服务端重命名为 {}", pcs_path, result.path());
+        }
+        Ok((result, renamed))
+    }
+
+    /// 上传文件并由调用方决定同名冲突时的文件名
+    /// 策略为 `Rename`/`NewCopy` 时不再由服务端自动命名，而是先列出目标目录，
+    /// 按 `suffixed_name` 方案（`a.txt`、`a_2.txt`、`a_3.txt`…）选取第一个未被占用的名称，
+    /// 再以 `Fail` 策略上传，保证结果可预期；其他策略直接上传到 `desired_path`
+    /// # Returns
+    /// * `PcsFileUploadResult` - 上传结果，`path()` 为实际使用的远程路径
+    pub fn upload_as(
+        &self,
+        local_file: &str,
+        desired_path: &str,
+        policy: PcsUploadPolicy,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        self.upload_as_with(local_file, desired_path, policy, suffixed_name)
+    }
+
+    /// 同 `upload_as`，使用自定义的重命名方案 `scheme(文件名, n)`，n 从 2 开始递增
+    pub fn upload_as_with<S>(
+        &self,
+        local_file: &str,
+        desired_path: &str,
+        policy: PcsUploadPolicy,
+        scheme: S,
+    ) -> Result<PcsFileUploadResult, AppError>
+    where
+        S: Fn(&str, u32) -> String,
+    {
+        let desired = self.resolve_path(desired_path);
+        if !matches!(policy, PcsUploadPolicy::Rename | PcsUploadPolicy::NewCopy) {
+            return self.upload_large_file(local_file, desired.as_str(), policy, |_| {});
+        }
+        let parent = Path::new(desired.as_str())
+            .parent()
+            .map(to_remote_path)
+            .unwrap_or_else(|| "/".to_string());
+        let existing: HashSet<String> = match self.list_dir_all(parent.as_str()) {
+            Ok(items) => items.into_iter().map(|item| item.path).collect(),
+            // 目标目录不存在，上传时自动创建
+            Err(e) if e.errno == Some(-9) => HashSet::new(),
+            Err(e) => return Err(e),
+        };
+        let path = pick_upload_path(desired.as_str(), &existing, scheme).ok_or_else(|| {
+            AppError::new(
+                AppErrorType::Client,
+                format!("无法为 {} 找到未被占用的文件名", desired).as_str(),
+                None,
+            )
+        })?;
+        if path != desired {
+            info!("{} 已存在，改为上传到 {}", desired, path);
+        }
+        // 以 Fail 策略上传，避免期间出现同名文件时再被服务端自动重命名
+        self.upload_large_file(local_file, path.as_str(), PcsUploadPolicy::Fail, |_| {})
+    }
+
     /// 分片上传文件（大文件）
     /// 这个接口不受“必须在 /apps/{app-name}/ 目录下”的限制
     /// https://pan.baidu.com/union/doc/3ksg0s9ye
@@ -813,55 +2625,320 @@ impl BaiduPcsClient {
     ) -> Result<PcsFileUploadResult, AppError>
     where
         F: FnMut(ProgressInfo) + Send + 'static,
+    {
+        self.upload_large_file_with_hash_progress(
+            local_file,
+            pcs_path,
+            police,
+            |_, _| {},
+            progress_callback,
+        )
+    }
+
+    /// 分片上传文件（大文件），并报告上传前计算校验和阶段的进度
+    /// 大文件在上传前需要完整读取一遍计算各分片 MD5，耗时较长，可通过 `hash_progress` 显示进度
+    /// # Arguments
+    /// * `hash_progress` - 校验和计算进度回调，参数为 (已计算字节数, 总字节数)
+    /// * 其余参数同 `upload_large_file`
+    pub fn upload_large_file_with_hash_progress<H, F>(
+        &self,
+        local_file: &str,
+        pcs_path: &str,
+        police: PcsUploadPolicy,
+        hash_progress: H,
+        progress_callback: F,
+    ) -> Result<PcsFileUploadResult, AppError>
+    where
+        H: FnMut(u64, u64),
+        F: FnMut(ProgressInfo) + Send + 'static,
+    {
+        self.upload_large_file_with_options(
+            local_file,
+            pcs_path,
+            &UploadOptions::from(police),
+            hash_progress,
+            progress_callback,
+        )
+    }
+
+    /// 分片上传文件（大文件），可完整控制预上传与创建文件请求中的属性
+    /// 例如从归档恢复时指定文件的创建/修改时间，而不是取本地文件的元数据
+    /// # Arguments
+    /// * `options` - 上传选项（命名策略、时间、上传方式、多版本）
+    /// * 其余参数同 `upload_large_file_with_hash_progress`
+    pub fn upload_large_file_with_options<H, F>(
+        &self,
+        local_file: &str,
+        pcs_path: &str,
+        options: &UploadOptions,
+        hash_progress: H,
+        progress_callback: F,
+    ) -> Result<PcsFileUploadResult, AppError>
+    where
+        H: FnMut(u64, u64),
+        F: FnMut(ProgressInfo) + Send + 'static,
+    {
+        self.upload_large_file_with_slice_md5s(
+            local_file,
+            pcs_path,
+            options,
+            hash_progress,
+            progress_callback,
+        )
+        .map(|(result, _)| result)
+    }
+
+    /// 分片上传文件（大文件），同时返回各分片上传后服务端返回的 MD5
+    /// 可将分片 MD5 保存下来，用于之后的完整性核对
+    /// # Returns
+    /// * `(PcsFileUploadResult, Vec<String>)` - 上传结果，以及按分片顺序排列的分片 MD5
+    pub fn upload_large_file_with_slice_md5s<H, F>(
+        &self,
+        local_file: &str,
+        pcs_path: &str,
+        options: &UploadOptions,
+        mut hash_progress: H,
+        progress_callback: F,
+    ) -> Result<(PcsFileUploadResult, Vec<String>), AppError>
+    where
+        H: FnMut(u64, u64),
+        F: FnMut(ProgressInfo) + Send + 'static,
     {
         info!("准备上传大文件 {}", local_file);
+        // 上传前提前校验，zip_sign 未指定时由整文件 MD5 补全
+        check_zip_options(
+            options.zip_quality,
+            options.zip_sign.is_some() || options.zip_quality.is_some(),
+        )?;
 
-        let (task, fs_meta) = self.file_slice_prepare(local_file, pcs_path, &police)?;
+        #[cfg(feature = "exif")]
+        let options = &with_jpeg_exif(local_file, options);
+
+        let (task, fs_meta) =
+            self.file_slice_prepare(local_file, pcs_path, options, &mut hash_progress)?;
 
         info!("预上传准备完成: {:?} , 文件信息 {:?}", task, fs_meta);
 
-        let servers = self.get_upload_server(&task)?;
+        let located = self.get_upload_server(&task)?;
+        let servers = upload_server_candidates(&located, self.file_server_url());
         let total_parts = task.block_list().len();
         let total_bytes = fs_meta.size;
-        let mut uploaded_bytes: u64 = 0;
 
         let cb_arc: Arc<Mutex<dyn FnMut(ProgressInfo) + Send>> =
             Arc::new(Mutex::new(progress_callback));
         let slice_size = self.user_info.as_ref().unwrap().get_user_block_slice_size();
 
-        let mut md5s: Vec<String> = Vec::with_capacity(total_parts);
-        for i in 0..total_parts {
-            let part_bytes = if i == total_parts - 1 {
-                total_bytes - slice_size * (i as u64)
-            } else {
-                slice_size
-            };
-            let md5 = self.file_slice_upload(
-                &fs_meta,
-                &task,
-                ProgressInfo {
-                    total_bytes,
-                    uploaded_bytes,
-                    current_part: i as u32,
-                    current_part_bytes: part_bytes,
-                },
-                &servers,
-                Some(cb_arc.clone()),
-            )?;
-            info!("分片 {}/{} 上传完成 {}", i + 1, total_parts, md5);
-            uploaded_bytes = uploaded_bytes.saturating_add(part_bytes);
-            md5s.push(md5);
-        }
+        let content_type = options
+            .content_type
+            .clone()
+            .unwrap_or_else(|| guess_content_type(local_file));
+        let md5s = upload_slices(
+            total_parts,
+            total_bytes,
+            slice_size,
+            options.pause.as_ref(),
+            |progress| {
+                self.file_slice_upload(
+                    &fs_meta,
+                    &task,
+                    progress,
+                    &servers,
+                    content_type.as_str(),
+                    Some(cb_arc.clone()),
+                )
+            },
+        )?;
 
         info!("所有分片上传完成: {:?}", md5s);
-        self.file_slice_merge(task, fs_meta, md5s, &police)
+        self.file_slice_merge_with_options(task, fs_meta, md5s.clone(), options)
+            .map(|result| (result, md5s))
+    }
+
+    /// 从数据流分片上传，适用于内存中生成的归档、其他进程的输出等没有本地文件路径的数据
+    /// 预上传需要提前提交各分片 MD5，因此 `reader` 须支持 `Seek`：先完整读取一遍计算校验和，再回到起始位置顺序读取上传
+    /// # Arguments
+    /// * `reader` - 数据来源，从起始位置读取 `size` 字节
+    /// * `size` - 上传的数据大小，单位B
+    /// * `pcs_path` - 上传后使用的文件绝对路径
+    /// * `policy` - 文件已存在时的处理策略
+    pub fn upload_from_reader<R: Read + Seek>(
+        &self,
+        reader: R,
+        size: u64,
+        pcs_path: &str,
+        policy: PcsUploadPolicy,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        self.upload_from_reader_with_options(reader, size, pcs_path, &UploadOptions::from(policy))
+    }
+
+    /// 从数据流分片上传，可完整控制上传选项
+    /// 数据流没有本地文件：`content_type` 为 None 时按 `pcs_path` 的扩展名推断，时间为 None 时由服务端决定，
+    /// 也不会自动提取 EXIF（`detect_exif` 无效，可通过 `exif_info` 手动指定）
+    /// # Arguments
+    /// * `options` - 上传选项，其中 `pause` 在分片之间生效
+    /// * 其余参数同 `upload_from_reader`
+    pub fn upload_from_reader_with_options<R: Read + Seek>(
+        &self,
+        mut reader: R,
+        size: u64,
+        pcs_path: &str,
+        options: &UploadOptions,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        info!("准备从数据流上传 {} 字节到 {}", size, pcs_path);
+        // zip_sign 未指定时由整文件 MD5 补全
+        check_zip_options(
+            options.zip_quality,
+            options.zip_sign.is_some() || options.zip_quality.is_some(),
+        )?;
+        let slice_size = self.get_user_info()?.get_user_block_slice_size();
+
+        reader.seek(SeekFrom::Start(0))?;
+        let (block_list, content_md5, slice_md5) = slice_checksums(
+            &mut reader,
+            size,
+            slice_size,
+            !self.fast_upload || options.zip_quality.is_some(),
+            &mut |_, _| {},
+        )?;
+        let fs_meta = PcsFileSliceInfo::with_checksums(size, content_md5, slice_md5, block_list);
+        reader.seek(SeekFrom::Start(0))?;
+        self.upload_stream(reader, fs_meta, slice_size, pcs_path, options)
+    }
+
+    /// 使用调用方预先计算的校验和，单次顺序读取数据流完成分片上传
+    /// 适用于边生成边计算 MD5 的管道，数据流无需支持 `Seek`
+    /// # Arguments
+    /// * `reader` - 数据来源，顺序读取 `checksums` 中记录的大小
+    /// * `checksums` - 由 `PcsFileSliceInfo::with_checksums` 构造，分片须按账号的分片大小（`PcsUserInfo::get_user_block_slice_size`）划分
+    /// * `pcs_path` - 上传后使用的文件绝对路径
+    /// * `policy` - 文件已存在时的处理策略
+    pub fn upload_with_checksums<R: Read>(
+        &self,
+        reader: R,
+        checksums: PcsFileSliceInfo,
+        pcs_path: &str,
+        policy: PcsUploadPolicy,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        self.upload_with_checksums_with_options(
+            reader,
+            checksums,
+            pcs_path,
+            &UploadOptions::from(policy),
+        )
+    }
+
+    /// 使用预先计算的校验和上传数据流，可完整控制上传选项（限制同 `upload_from_reader_with_options`）
+    /// 仅设置 `zip_quality` 时以 `checksums` 中的整文件 MD5 作为 `zip_sign`，两者皆无时报错
+    /// # Arguments
+    /// * `options` - 上传选项，其中 `pause` 在分片之间生效
+    /// * 其余参数同 `upload_with_checksums`
+    pub fn upload_with_checksums_with_options<R: Read>(
+        &self,
+        reader: R,
+        checksums: PcsFileSliceInfo,
+        pcs_path: &str,
+        options: &UploadOptions,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        info!(
+            "准备按预先计算的校验和上传 {} 字节到 {}",
+            checksums.size, pcs_path
+        );
+        check_zip_options(
+            options.zip_quality,
+            options.zip_sign.is_some()
+                || (options.zip_quality.is_some() && checksums.content_md5.is_some()),
+        )?;
+        let slice_size = self.get_user_info()?.get_user_block_slice_size();
+        let expected_parts = if slice_size == 0 {
+            0
+        } else {
+            checksums.size.div_ceil(slice_size)
+        };
+        if checksums.block_list.len() as u64 != expected_parts {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!(
+                    "分片 MD5 数量 {} 与数据大小 {} 按分片大小 {} 划分的分片数 {} 不一致",
+                    checksums.block_list.len(),
+                    checksums.size,
+                    slice_size,
+                    expected_parts
+                )
+                .as_str(),
+                None,
+            ));
+        }
+        self.upload_stream(reader, checksums, slice_size, pcs_path, options)
+    }
+
+    /// 按已计算好的分片信息预上传，并顺序读取数据流逐个上传分片后合并
+    fn upload_stream<R: Read>(
+        &self,
+        mut reader: R,
+        fs_meta: PcsFileSliceInfo,
+        slice_size: u64,
+        pcs_path: &str,
+        options: &UploadOptions,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        let task = self.precreate(pcs_path, &fs_meta, options)?;
+        let servers =
+            upload_server_candidates(&self.get_upload_server(&task)?, self.file_server_url());
+
+        let content_type = options
+            .content_type
+            .clone()
+            .unwrap_or_else(|| guess_content_type(pcs_path));
+        let md5s = upload_slices(
+            fs_meta.block_list.len(),
+            fs_meta.size,
+            slice_size,
+            options.pause.as_ref(),
+            |progress| {
+                let mut data = vec![0u8; progress.current_part_bytes as usize];
+                reader.read_exact(&mut data)?;
+                self.slice_upload(
+                    SliceSource::Bytes(&data),
+                    &task,
+                    progress,
+                    &servers,
+                    content_type.as_str(),
+                    None,
+                )
+            },
+        )?;
+        info!("数据流所有分片上传完成: {:?}", md5s);
+        self.file_slice_merge_with_options(task, fs_meta, md5s, options)
+    }
+
+    /// 检查本地文件能否秒传，仅执行预上传，不上传分片也不创建文件
+    /// 预上传返回 `return_type == 2` 表示云端已有相同内容的文件，可直接秒传
+    /// 探测时按冲突失败策略提交，且总是计算秒传所需的整文件 MD5 与校验段 MD5（不受快速上传模式影响）；
+    /// 开放平台未提供取消 uploadid 的接口，探测产生的 uploadid 由服务端过期后自动清理
+    /// # Arguments
+    /// * `local_file` - 本地文件路径
+    /// * `pcs_path` - 计划上传到的云盘路径
+    pub fn can_rapid_upload(&self, local_file: &str, pcs_path: &str) -> Result<bool, AppError> {
+        let fs_meta = get_file_block_list(
+            &self.get_user_info()?,
+            local_file,
+            true,
+            self.md5_cache.as_ref(),
+            &mut |_, _| {},
+        )?;
+        let options = UploadOptions::from(PcsUploadPolicy::Fail);
+        let task = self.precreate(pcs_path, &fs_meta, &options)?;
+        debug!(
+            "秒传探测 {} -> {} return_type={} uploadid={}",
+            local_file, pcs_path, task.return_type, task.upload_id
+        );
+        Ok(task.return_type == 2)
     }
 
     /// 预上传文件
     /// # Arguments
     /// * `local_file` - 本地文件路径(待上传文件的绝对路径)
     /// * `pcs_path` - 上传后使用的文件绝对路径，云盘的存储路径，需要注意的是有限制只能上传到 /apps/{app-name}/目录下，其他目录会返回 31064
-    /// * `police` - 上传的文件绝对路径冲突时的策略 注意仅支持 {重命名, 覆盖}, 否则默认为覆盖
+    /// * `options` - 上传选项，其中命名策略注意仅支持 {重命名, 覆盖}, 否则默认为覆盖
     /// # Returns
     /// * `FileUploadSlice` - 文件上传结果
     /// * `FileSlice` - 文件分片信息
@@ -871,8 +2948,28 @@ impl BaiduPcsClient {
         &self,
         local_file: &str,
         pcs_path: &str,
-        police: &PcsUploadPolicy,
+        options: &UploadOptions,
+        hash_progress: &mut dyn FnMut(u64, u64),
     ) -> Result<(PcsFileSlicePrepareResult, PcsFileSliceInfo), AppError> {
+        let fs_meta = get_file_block_list(
+            &self.get_user_info()?,
+            local_file,
+            // 图片压缩需要原始文件 MD5 作为 zip_sign，快速上传模式下也需计算
+            !self.fast_upload || options.zip_quality.is_some(),
+            self.md5_cache.as_ref(),
+            hash_progress,
+        )?;
+        self.precreate(pcs_path, &fs_meta, options)
+            .map(|r| (r, fs_meta))
+    }
+
+    /// 以已计算好的分片信息发起预上传
+    fn precreate(
+        &self,
+        pcs_path: &str,
+        fs_meta: &PcsFileSliceInfo,
+        options: &UploadOptions,
+    ) -> Result<PcsFileSlicePrepareResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/file";
         #[derive(Serialize)]
         struct Params<'a> {
@@ -882,99 +2979,106 @@ impl BaiduPcsClient {
         const PARAMS: Params = Params {
             method: "precreate",
         };
-        #[derive(Serialize)]
-        struct PreCreateAttributes<'a> {
-            /// 上传后使用的文件绝对路径，需要urlencode
-            path: &'a str,
-            /// 文件和目录两种情况：上传文件时，表示文件的大小，单位B；上传目录时，表示目录的大小，目录的话大小默认为0
-            size: u64,
-            /// 是否为目录，0 文件，1 目录
-            #[serde(rename = "isdir")]
-            is_dir: i32,
-            /// 文件各分片MD5数组的json串。block_list的含义如下，如果上传的文件小于4MB，其md5值（32位小写）即为block_list字符串数组的唯一元素；如果上传的文件大于4MB，需要将上传的文件按照4MB大小在本地切分成分片，不足4MB的分片自动成为最后一个分片，所有分片的md5值（32位小写）组成的字符串数组即为block_list。
-            block_list: String,
-            /// 固定值1
-            #[serde(rename = "autoinit")]
-            auto_init: i32,
-            /// 文件命名策略。
-            // 1 表示当path冲突时，进行重命名
-            // 2 表示当path冲突且block_list不同时，进行重命名
-            // 3 当云端存在同名文件时，对该文件进行覆盖
-            #[serde(rename = "rtype")]
-            r_type: Option<i32>,
-            /// 上传ID
-            #[serde(rename = "uploadid")]
-            upload_id: Option<String>,
-            /// 文件MD5，32位小写
-            #[serde(alias = "content-md5")]
-            content_md5: Option<String>,
-            /// 文件校验段的MD5，32位小写，校验段对应文件前256KB
-            #[serde(alias = "slice-md5")]
-            slice_md5: Option<String>,
-            /// 客户端创建时间(精确到秒)，默认为当前时间戳
-            local_ctime: Option<i64>,
-            /// 客户端修改时间(精确到秒)，默认为当前时间戳
-            local_mtime: Option<i64>,
-        }
-
-        let fs_meta = get_file_block_list(&self.get_user_info()?, local_file)?;
-        let payload = PreCreateAttributes {
-            path: pcs_path,
-            size: fs_meta.size,
-            is_dir: 0,
-            block_list: serde_json::to_string(fs_meta.block_list.as_slice())?,
-            auto_init: 1,
-            r_type: match police {
-                PcsUploadPolicy::Rename => Some(1),
-                PcsUploadPolicy::Overwrite => Some(3),
-                PcsUploadPolicy::NewCopy => Some(2),
-                _ => Some(3),
-            },
-            upload_id: None,
-            content_md5: Some(fs_meta.content_md5.clone()),
-            slice_md5: Some(fs_meta.slice_md5.clone()),
-            local_ctime: Some(fs_meta.ctime),
-            local_mtime: Some(fs_meta.mtime),
-        };
+        let pcs_path = self.resolve_path(pcs_path);
+        let payload = precreate_attributes(pcs_path.as_str(), fs_meta, options)?;
+        let r_type = payload.r_type;
 
         self.request(Post, PATH, PARAMS, Some(payload))
-            .map(|x: PcsFileSlicePrepareResult| {
-                if x.path.is_empty() {
-                    PcsFileSlicePrepareResult {
-                        path: pcs_path.to_string(),
-                        upload_id: x.upload_id,
-                        return_type: x.return_type,
-                        block_list: x.block_list,
-                    }
+            .map(|x: PcsFileSlicePrepareResult| PcsFileSlicePrepareResult {
+                path: if x.path.is_empty() {
+                    pcs_path.to_string()
                 } else {
-                    x
-                }
+                    x.path
+                },
+                upload_id: x.upload_id,
+                return_type: x.return_type,
+                block_list: x.block_list,
+                r_type,
             })
-            .map(|r| (r, fs_meta))
     }
 
     /// 分片上传文件
     /// 参见[官方文档](https://pan.baidu.com/union/doc/nksg0s9vi)
+    /// 上传服务器网络不可达时依次改用 `servers` 中的下一个服务器重试该分片
     pub(crate) fn file_slice_upload(
         &self,
         local_file: &PcsFileSliceInfo,
         upload_task: &PcsFileSlicePrepareResult,
         progress_info: ProgressInfo,
-        server: &UploadServerResult,
+        servers: &[String],
+        content_type: &str,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<String, AppError> {
+        self.slice_upload(
+            SliceSource::File(local_file.path.as_str()),
+            upload_task,
+            progress_info,
+            servers,
+            content_type,
+            progress_cb,
+        )
+    }
+
+    /// 上传单个分片（数据可来自文件或内存），返回服务端计算的分片 MD5
+    fn slice_upload(
+        &self,
+        source: SliceSource,
+        upload_task: &PcsFileSlicePrepareResult,
+        progress_info: ProgressInfo,
+        servers: &[String],
+        content_type: &str,
         progress_cb: Option<ProgressCallback>,
     ) -> Result<String, AppError> {
-        const PATH: &str = "/rest/2.0/pcs/superfile2";
         #[derive(Serialize, Deserialize, Debug)]
         struct UploadResultDTO {
             md5: String,
         }
 
-        let upload_server = server
-            .servers()
-            .first()
-            .or_else(|| server.bak_servers().first())
-            .map(|s| s.server().clone())
-            .unwrap_or_else(|| String::from(PREFIX_FILE_SERVER));
+        let fallback_only = servers == [self.file_server_url()];
+        let text = with_server_failover(servers, |upload_server| {
+            self.file_slice_upload_to(
+                upload_server,
+                &source,
+                upload_task,
+                &progress_info,
+                content_type,
+                progress_cb.clone(),
+            )
+        })
+        .map_err(|e| {
+            if fallback_only {
+                default_server_unreachable(e, self.file_server_url())
+            } else {
+                e
+            }
+        })?;
+        debug!("text: {}", text);
+        let resp: serde_json::error::Result<UploadResultDTO> = serde_json::from_str(text.as_str());
+        match resp {
+            Ok(v) => Ok(v.md5),
+            Err(_) => {
+                let e: PcsApiError = serde_json::from_str(text.as_str()).unwrap_or(PcsApiError {
+                    errno: i32::MIN,
+                    err_msg: None,
+                    request_id: None,
+                    raw: text,
+                });
+                Err(e.into())
+            }
+        }
+    }
+
+    /// 将单个分片上传到指定的服务器，返回服务端响应文本
+    fn file_slice_upload_to(
+        &self,
+        upload_server: &str,
+        source: &SliceSource<'_>,
+        upload_task: &PcsFileSlicePrepareResult,
+        progress_info: &ProgressInfo,
+        content_type: &str,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<String, AppError> {
+        const PATH: &str = "/rest/2.0/pcs/superfile2";
         info!("上传分片 {} 到服务器 {}", progress_info, upload_server);
         #[derive(Serialize)]
         struct Query<'a> {
@@ -996,10 +3100,9 @@ impl BaiduPcsClient {
         }
 
         let fut = async {
-            let form = Self::create_form(local_file.path.as_str(), &progress_info, progress_cb)
-                .await
-                .unwrap();
-            self.client
+            let form = Self::create_form(source, content_type, progress_info, progress_cb).await?;
+            let text = self
+                .client
                 .post(format!("{}{}", upload_server, PATH))
                 .query(&Query {
                     method: "upload",
@@ -1011,28 +3114,14 @@ impl BaiduPcsClient {
                 })
                 .multipart(form)
                 .send()
-                .await
-                .unwrap()
+                .await?
                 .text()
-                .await
+                .await?;
+            Ok::<String, AppError>(text)
         };
 
         let runtime = tokio::runtime::Runtime::new()?;
-        let text = runtime.block_on(fut)?;
-        debug!("text: {}", text);
-        let resp: serde_json::error::Result<UploadResultDTO> = serde_json::from_str(text.as_str());
-        match resp {
-            Ok(v) => Ok(v.md5),
-            Err(_) => {
-                let e: PcsApiError = serde_json::from_str(text.as_str()).unwrap_or(PcsApiError {
-                    errno: i32::MIN,
-                    err_msg: None,
-                    request_id: None,
-                    raw: text,
-                });
-                Err(e.into())
-            }
-        }
+        runtime.block_on(with_permit(&self.request_limiter, fut))
     }
 
     /// 创建文件
@@ -1043,6 +3132,17 @@ impl BaiduPcsClient {
         fs: PcsFileSliceInfo,
         hashes: Vec<String>,
         police: &PcsUploadPolicy,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        self.file_slice_merge_with_options(upload_task, fs, hashes, &UploadOptions::from(*police))
+    }
+
+    /// 创建文件，时间、上传方式等属性取自 `options`
+    pub(crate) fn file_slice_merge_with_options(
+        &self,
+        upload_task: PcsFileSlicePrepareResult,
+        fs: PcsFileSliceInfo,
+        hashes: Vec<String>,
+        options: &UploadOptions,
     ) -> Result<PcsFileUploadResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/file";
         #[derive(Serialize)]
@@ -1051,101 +3151,21 @@ impl BaiduPcsClient {
             method: &'a str,
         }
         const PARAMS: Params = Params { method: "create" };
-        #[derive(Serialize)]
-        struct MergeAttributes<'a> {
-            /// 上传后使用的文件绝对路径，需要urlencode
-            path: &'a str,
-            /// 文件或目录的大小，必须要和文件真实大小保持一致，需要与预上传precreate接口中的size保持一致
-            size: u64,
-            /// 是否目录，0 文件、1 目录，需要与预上传precreate接口中的isdir保持一致
-            #[serde(rename = "isdir")]
-            is_dir: &'a str,
-            /// 文件各分片md5数组的json串
-            // 需要与预上传precreate接口中的block_list保持一致，同时对应分片上传superfile2接口返回的md5，且要按照序号顺序排列，组成md5数组的json串。
-            block_list: &'a str,
-            /// 预上传precreate接口下发的uploadid
-            #[serde(rename = "uploadid")]
-            upload_id: &'a str,
-            /// 文件命名策略，默认 `0`
-            /// - `0` 为不重命名，返回冲突
-            /// - `1` 为只要path冲突即重命名
-            /// - `2` 为path冲突且block_list不同才重命名
-            /// - `3` 为覆盖，需要与预上传precreate接口中的rtype保持一致
-            #[serde(rename = "rtype")]
-            r_type: Option<i32>,
-            /// 客户端创建时间(精确到秒)，默认为当前时间戳
-            local_ctime: Option<i64>,
-            /// 客户端修改时间(精确到秒)，默认为当前时间戳
-            local_mtime: Option<i64>,
-            /// 图片压缩程度，有效值50、70、100（带此参数时，zip_sign 参数需要一并带上）
-            zip_quality: Option<i32>,
-            /// 未压缩原始图片文件真实md5（带此参数时，zip_quality 参数需要一并带上）
-            zip_sign: Option<String>,
-            /// 是否需要多版本支持, 默认为0 (带此参数会忽略重命名策略)
-            /// - `1` 为支持
-            /// - `0` 为不支持
-            is_revision: Option<i32>,
-            /// 上传方式
-            /// - `1` 手动
-            /// - `2` 批量上传
-            /// - `3` 文件自动备份
-            /// - `4` 相册自动备份
-            /// - `5` 视频自动备份
-            mode: Option<i32>,
-            /// json字符串，orientation、width、height、recovery为必传字段，其他字段如果没有可以不传
-            exif_info: Option<String>,
-        }
         let block_list_json = serde_json::to_string(&hashes)?;
         self.request(
             Post,
             PATH,
             PARAMS,
-            Some(MergeAttributes {
-                path: upload_task.path().as_str(),
-                size: fs.size,
-                is_dir: "0",
-                block_list: block_list_json.as_str(),
-                upload_id: upload_task.upload_id.as_str(),
-                r_type: match police {
-                    PcsUploadPolicy::Rename => Some(1),
-                    PcsUploadPolicy::Overwrite => Some(3),
-                    PcsUploadPolicy::NewCopy => Some(2),
-                    _ => Some(3),
-                },
-                local_ctime: Some(fs.ctime),
-                local_mtime: Some(fs.mtime),
-                zip_quality: None,
-                zip_sign: None,
-                is_revision: Some(1),
-                mode: Some(2),
-                exif_info: None,
-            }),
+            Some(merge_attributes(
+                &upload_task,
+                &fs,
+                block_list_json.as_str(),
+                options,
+            )?),
         )
     }
 
     pub fn search_file(&self, name_or_path: &str) -> Result<PcsFileSearchResult, AppError> {
-        const PATH: &str = "/rest/2.0/xpan/file";
-        #[derive(Serialize)]
-        struct Params<'a> {
-            /// 本接口固定为`search`
-            method: &'a str,
-            /// 搜索关键字，最大30字符（UTF8格式）
-            key: &'a str,
-            /// 搜索目录，默认根目录
-            dir: Option<&'a str>,
-            /// 文件类型，1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
-            category: Option<i32>,
-            /// 分页
-            page: Option<i32>,
-            /// 默认为500，不能修改
-            num: Option<i32>,
-            /// 是否递归，带这个参数就会递归，否则不递归
-            recursion: Option<i32>,
-            /// 是否展示缩略图信息，带这个参数会返回缩略图信息，否则不展示缩略图信息
-            web: Option<i32>,
-            /// 设备ID，设备注册接口下发，硬件设备必传
-            device_id: Option<&'a str>,
-        }
         // 取 "/a/c/bddeeaaae.ext" 中的 "bddeeaaae" 的最后最多30字符
         let name = name_or_path
             .split("/")
@@ -1177,15 +3197,69 @@ impl BaiduPcsClient {
         } else {
             name_or_path.rfind("/").map(|idx| &name_or_path[..idx])
         };
+        let options = SearchOptions {
+            dir: path.map(|p| p.to_string()),
+            ..Default::default()
+        };
+        self.search_page(name.as_str(), &options, None)
+    }
+
+    /// 按关键字搜索文件，逐页惰性获取结果
+    /// 只有当前页的条目被取完且 `has_more` 时才会请求下一页，适合结果很多时边取边处理
+    /// # Arguments
+    /// * `key` - 搜索关键字，最大30字符（UTF8格式）
+    /// * `options` - 搜索目录、文件类型等选项
+    /// # Returns
+    /// * 搜索到的文件，请求出错时返回该错误并结束迭代
+    pub fn search_stream<'a>(
+        &'a self,
+        key: &'a str,
+        options: SearchOptions,
+    ) -> impl Iterator<Item = Result<PcsFileSearchInfo, AppError>> + 'a {
+        paginate(move |page| {
+            self.search_page(key, &options, Some(page))
+                .map(|result| (result.list, result.has_more == 1))
+        })
+    }
+
+    /// 请求一页搜索结果，`page` 为 None 时由服务端决定（第一页）
+    fn search_page(
+        &self,
+        key: &str,
+        options: &SearchOptions,
+        page: Option<i32>,
+    ) -> Result<PcsFileSearchResult, AppError> {
+        const PATH: &str = "/rest/2.0/xpan/file";
+        #[derive(Serialize)]
+        struct Params<'a> {
+            /// 本接口固定为`search`
+            method: &'a str,
+            /// 搜索关键字，最大30字符（UTF8格式）
+            key: &'a str,
+            /// 搜索目录，默认根目录
+            dir: Option<&'a str>,
+            /// 文件类型，1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
+            category: Option<i32>,
+            /// 分页
+            page: Option<i32>,
+            /// 默认为500，不能修改
+            num: Option<i32>,
+            /// 是否递归，带这个参数就会递归，否则不递归
+            recursion: Option<i32>,
+            /// 是否展示缩略图信息，带这个参数会返回缩略图信息，否则不展示缩略图信息
+            web: Option<i32>,
+            /// 设备ID，设备注册接口下发，硬件设备必传
+            device_id: Option<&'a str>,
+        }
         let params = Params {
             method: "search",
-            key: name.as_str(),
-            dir: path,
-            category: None,
-            page: None,
+            key,
+            dir: options.dir.as_deref(),
+            category: options.category,
+            page,
             num: None,
-            recursion: Some(1),
-            web: None,
+            recursion: options.recursion.then_some(1),
+            web: options.web.then_some(1),
             device_id: None,
         };
         self.request(Get, PATH, params, None::<()>)
@@ -1205,6 +3279,33 @@ impl BaiduPcsClient {
         &self,
         down: bool,
         fs_ids: Vec<u64>,
+    ) -> Result<PcsFileMetaResult, AppError> {
+        self.file_metas(down, fs_ids, None)
+    }
+
+    /// 查询共享目录中文件的信息
+    /// # Arguments
+    /// * `uk` - 共享目录创建者 ID
+    /// * `shared_fs_id` - 共享目录的 fs_id
+    /// * `down` - 是否需要下载地址
+    /// * `fs_ids` - 共享目录中的文件 ID，上限 100
+    pub fn get_shared_file_info(
+        &self,
+        uk: u64,
+        shared_fs_id: u64,
+        down: bool,
+        fs_ids: Vec<u64>,
+    ) -> Result<PcsFileMetaResult, AppError> {
+        let share = shared_dir_path(uk, shared_fs_id);
+        self.file_metas(down, fs_ids, Some(share.as_str()))
+    }
+
+    /// `path` 为共享目录 `/uk-fsid` 或专属空间路径，见 `get_file_info` 中的参数说明
+    fn file_metas(
+        &self,
+        down: bool,
+        fs_ids: Vec<u64>,
+        path: Option<&str>,
     ) -> Result<PcsFileMetaResult, AppError> {
         const PATH: &str = "/rest/2.0/xpan/multimedia";
         // 参数名称	类型	是否必填	示例	参数位置	描述
@@ -1252,7 +3353,7 @@ impl BaiduPcsClient {
             method: "filemetas",
             fsids: serde_json::to_string(&fs_ids)?,
             dlink: down.then_some(1),
-            path: None,
+            path,
             thumb: None,
             extra: None,
             needmedia: None,
@@ -1263,6 +3364,94 @@ impl BaiduPcsClient {
         self.request(Get, PATH, params, None::<()>)
     }
 
+    /// 批量查询文件信息，结果以 fs_id 为键
+    /// 超过单次上限（100）的 ID 会分批查询；不存在的文件不会出现在结果中
+    /// # Arguments
+    /// * `down` - 是否需要下载地址
+    /// * `fs_ids` - 文件id数组
+    pub fn get_file_info_map(
+        &self,
+        down: bool,
+        fs_ids: Vec<u64>,
+    ) -> Result<HashMap<u64, PcsFileMetaInfo>, AppError> {
+        let mut list = Vec::with_capacity(fs_ids.len());
+        for chunk in fs_ids.chunks(FILE_INFO_BATCH_SIZE) {
+            list.extend(self.get_file_info(down, chunk.to_vec())?.list);
+        }
+        Ok(index_by_fs_id(list))
+    }
+
+    /// 获取各分类（视频、音频、图片、文档等）的文件数量与总大小
+    /// 参见[官方文档](https://pan.baidu.com/union/doc/dksg0sanx)，接口每次查询一个分类，此处逐个查询并合并
+    pub fn category_info(&self) -> Result<CategoryCounts, AppError> {
+        const PATH: &str = "/api/categoryinfo";
+        #[derive(Serialize)]
+        struct Params<'a> {
+            /// 文件类型，1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
+            category: i32,
+            /// 统计的目录
+            parent_path: &'a str,
+            /// 是否递归统计子目录，0 否、1 是
+            recursion: i32,
+        }
+        let mut counts = CategoryCounts::default();
+        for category in 1..=7 {
+            let params = Params {
+                category,
+                parent_path: "/",
+                recursion: 1,
+            };
+            counts.merge(self.request(Get, PATH, params, None::<()>)?);
+        }
+        Ok(counts)
+    }
+
+    /// 按文件ID查询单个文件的元信息
+    /// # Arguments
+    /// * `fs_id` - 文件ID
+    /// * `dlink` - 是否同时返回下载地址
+    pub fn stat_by_id(&self, fs_id: u64, dlink: bool) -> Result<PcsFileMetaInfo, AppError> {
+        self.get_file_info(dlink, vec![fs_id])?
+            .list
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                AppError::new(
+                    AppErrorType::Client,
+                    format!("文件不存在: fs_id={}", fs_id).as_str(),
+                    None,
+                )
+            })
+    }
+
+    /// 按路径查询单个文件的元信息
+    /// 先列出父目录找到对应的文件ID，再查询元信息
+    /// # Arguments
+    /// * `path` - 文件或目录的绝对路径
+    /// * `dlink` - 是否同时返回下载地址
+    pub fn stat(&self, path: &str, dlink: bool) -> Result<PcsFileMetaInfo, AppError> {
+        let path = self.resolve_path(path);
+        let path = path.trim_end_matches('/');
+        let parent = Path::new(path)
+            .parent()
+            .and_then(|p| p.to_str())
+            .filter(|p| !p.is_empty())
+            .unwrap_or("/");
+        let fs_id = self
+            .list_dir_all(parent)?
+            .iter()
+            .find(|item| item.path() == path)
+            .map(|item| *item.fs_id())
+            .ok_or_else(|| {
+                AppError::new(
+                    AppErrorType::Client,
+                    format!("远程路径不存在: {}", path).as_str(),
+                    None,
+                )
+            })?;
+        self.stat_by_id(fs_id, dlink)
+    }
+
     /// 分享提取码验证
     /// 参见[官方文档](https://pan.baidu.com/union/doc/Rlaaolmfw)
     /// 验证分享提取码是否正确，返回加密后的 spwd 供后续接口使用
@@ -1408,45 +3597,151 @@ impl BaiduPcsClient {
     where
         F: Fn(u64, u64) + Send + Sync + 'static,
     {
-        let full_url = format!(
-            "{}&access_token={}",
+        self.download_with_policy(
             download_link,
-            self.access_token.as_str()
-        );
-        let fut = async {
-            let mut resp = self
-                .client
-                .get(full_url.as_str())
-                .send()
-                .await
-                .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), None))?;
+            local_path,
+            PcsDownloadPolicy::Overwrite,
+            progress,
+        )
+    }
 
-            let total_bytes = resp.content_length().unwrap_or(0);
-            let mut file = tokio::fs::File::options()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(local_path)
-                .await?;
+    /// 下载文件，本地文件已存在时跳过，不覆盖
+    pub fn download_no_clobber<F>(
+        &self,
+        download_link: &str,
+        local_path: &str,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.download_with_policy(download_link, local_path, PcsDownloadPolicy::Skip, progress)
+    }
 
-            let mut downloaded: u64 = 0;
-            while let Some(chunk) = resp
-                .chunk()
-                .await
-                .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), None))?
-            {
-                file.write_all(&chunk).await?;
-                downloaded += chunk.len() as u64;
-                if let Some(ref cb) = progress {
-                    cb(downloaded, total_bytes);
+    /// 下载文件，并按 `policy` 处理本地已存在的目标文件
+    /// 下载内容先写入 `{local_path}.part`，完成后重命名为 `local_path`；下载失败时保留 `.part` 文件
+    /// # Arguments
+    /// * `download_link` - 文件下载地址(dlink)
+    /// * `local_path` - 本地保存路径
+    /// * `policy` - 本地文件已存在时的处理策略
+    /// * `progress` - 进度回调（已下载字节, 总字节）
+    pub fn download_with_policy<F>(
+        &self,
+        download_link: &str,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.download_resumable(download_link, local_path, policy, progress, None)
+    }
+
+    /// 同 `download_with_policy`，`expected` 为远程文件的下载记录时支持断点续传：
+    /// 记录写入 `{local_path}.part.meta`，再次下载时记录一致则通过 Range 请求从 `.part` 的末尾继续，
+    /// 远程文件的大小或 md5 已变化时丢弃 `.part` 重新下载；服务端不支持 Range 时同样从头下载
+    fn download_resumable<F>(
+        &self,
+        download_link: &str,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+        expected: Option<&DownloadSidecar>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        if policy != PcsDownloadPolicy::Overwrite && Path::new(local_path).exists() {
+            return match policy {
+                PcsDownloadPolicy::Skip => {
+                    info!("本地文件 {} 已存在，跳过下载", local_path);
+                    Ok(())
                 }
-            }
-            file.flush().await?;
-            Ok::<(), AppError>(())
-        };
+                _ => Err(AppError::new(
+                    AppErrorType::Client,
+                    format!("本地文件 {} 已存在", local_path).as_str(),
+                    None,
+                )),
+            };
+        }
+        let part_path = download_part_path(local_path);
+        let offset = prepare_resume(local_path, expected)?;
+        let full_url = self.build_download_url(download_link);
+        let fut =
+            async {
+                if offset > 0 && expected.is_some_and(|expected| expected.size == offset) {
+                    // 上次已下载完整，只差重命名
+                    return Ok::<(), AppError>(());
+                }
+                let mut request = self.client.get(full_url.as_str());
+                if offset > 0 {
+                    request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+                }
+                let mut resp = request.send().await.map_err(|e| {
+                    AppError::new(AppErrorType::Network, e.to_string().as_str(), None)
+                })?;
+                let status = resp.status();
+                if !status.is_success() {
+                    // errno 记录 HTTP 状态码，便于识别下载地址过期（403/404）
+                    return Err(AppError::new(
+                        AppErrorType::Network,
+                        format!("http error {}", status).as_str(),
+                        Some(status.as_u16() as i64),
+                    ));
+                }
+                let resumed = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+                if offset > 0 && !resumed {
+                    info!("服务端不支持 Range 请求，从头下载 {}", local_path);
+                }
+                let start = if resumed { offset } else { 0 };
+
+                let total_bytes = resp.content_length().map_or(0, |len| len + start);
+                // 先写入 .part 临时文件，下载完成后再重命名，避免中断时留下不完整的目标文件
+                let file = tokio::fs::File::options()
+                    .create(true)
+                    .truncate(!resumed)
+                    .append(resumed)
+                    .write(true)
+                    .open(part_path.as_str())
+                    .await?;
+                // 合并小分块写入，减少系统调用次数
+                let mut file = tokio::io::BufWriter::with_capacity(self.download_buffer_size, file);
+
+                let mut downloaded: u64 = start;
+                while let Some(chunk) = resp.chunk().await.map_err(|e| {
+                    AppError::new(AppErrorType::Network, e.to_string().as_str(), None)
+                })? {
+                    file.write_all(&chunk).await?;
+                    downloaded += chunk.len() as u64;
+                    if let Some(ref cb) = progress {
+                        cb(downloaded, total_bytes);
+                    }
+                }
+                file.flush().await?;
+                Ok::<(), AppError>(())
+            };
         self.runtime
-            .block_on(fut)
-            .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), None))
+            .block_on(with_permit(&self.request_limiter, fut))
+            .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), e.errno))?;
+        // 下载期间目标文件可能被其他进程创建，重命名前再次确认
+        if policy != PcsDownloadPolicy::Overwrite && Path::new(local_path).exists() {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!(
+                    "本地文件 {} 已存在，已下载内容保留在 {}",
+                    local_path, part_path
+                )
+                .as_str(),
+                None,
+            ));
+        }
+        std::fs::rename(part_path.as_str(), local_path)
+            .map_err(|e| AppError::new(AppErrorType::Client, e.to_string().as_str(), None))?;
+        if expected.is_some() {
+            let _ = std::fs::remove_file(download_sidecar_path(local_path));
+        }
+        Ok(())
     }
 
     /// Download a byte range of the remote file identified by path. Returns the bytes read.
@@ -1478,7 +3773,52 @@ impl BaiduPcsClient {
                 ))
             }
         };
-        let url = format!("{}&access_token={}", dlink, self.access_token.as_str());
+        let url = self.build_download_url(dlink.as_str());
+        self.fetch_range(url.as_str(), offset, len)
+    }
+
+    /// 获取可直接下载的文件地址（已解码并附加 access_token）
+    /// 可交给 aria2、wget 等外部下载工具使用
+    /// 注意：下载地址(dlink)有效期为8小时，且下载时需设置 `User-Agent: pan.baidu.com`
+    /// # Arguments
+    /// * `fs_id` - 文件在云端的唯一标识ID
+    pub fn get_download_url(&self, fs_id: u64) -> Result<String, AppError> {
+        let (dlink, _) = self.query_dlink(fs_id)?;
+        Ok(self.build_download_url(dlink.as_str()))
+    }
+
+    /// 查询文件的下载地址(dlink)与文件大小
+    fn query_dlink(&self, fs_id: u64) -> Result<(String, u64), AppError> {
+        let meta = self.get_file_info(true, vec![fs_id])?;
+        let file_info = meta.list.first().ok_or_else(|| {
+            AppError::new(
+                AppErrorType::Unknown,
+                format!("未找到文件 {}", fs_id).as_str(),
+                None,
+            )
+        })?;
+        let dlink = file_info.dlink.as_ref().ok_or_else(|| {
+            AppError::new(
+                AppErrorType::Unknown,
+                format!("未找到文件下载链接 {}", fs_id).as_str(),
+                None,
+            )
+        })?;
+        Ok((dlink.clone(), file_info.size))
+    }
+
+    /// 解码 dlink 中的 unicode 转义并附加 access_token
+    fn build_download_url(&self, dlink: &str) -> String {
+        format!(
+            "{}&access_token={}",
+            decode_unicode_escapes(dlink),
+            self.access_token.as_str()
+        )
+    }
+
+    /// 通过 Range 请求读取指定 url 的一段字节
+    /// 服务端不支持 Range 时会返回完整内容，调用方需自行处理
+    fn fetch_range(&self, url: &str, offset: u64, len: usize) -> Result<Vec<u8>, AppError> {
         let range_header = format!(
             "bytes={}-{}",
             offset,
@@ -1488,7 +3828,7 @@ impl BaiduPcsClient {
         let fut = async {
             let resp = self
                 .client
-                .get(url.as_str())
+                .get(url)
                 .header(reqwest::header::RANGE, range_header)
                 .send()
                 .await
@@ -1509,54 +3849,207 @@ impl BaiduPcsClient {
             Ok::<Vec<u8>, AppError>(bytes.to_vec())
         };
 
-        let bytes = self.runtime.block_on(fut)?;
+        let bytes = self
+            .runtime
+            .block_on(with_permit(&self.request_limiter, fut))?;
         Ok(bytes)
     }
 
-    /// 通过文件路径反向查询百度网盘云端的文件ID
+    /// 分段并行下载文件
+    /// 将文件等分为 `segments` 段，并发发起多个 Range 请求，分别写入预分配文件的对应偏移处，可显著提升大文件下载速度
+    /// 服务端不支持 Range（未返回 206）时自动回退为单连接下载
     /// # Arguments
-    /// * `path` - 文件路径
-    /// # Returns
-    /// * `u64` - 文件ID
-    /// # Errors
-    /// * `AppError` - 文件ID查询失败
-    pub(crate) fn get_fs_id_by_path(&self, path: &str) -> Result<u64, AppError> {
-        // 百度网盘在下载等操作时 需要用fsid，但是一般我们都是通过path管理 需要维护一个表
-        if path.ends_with("/") {
-            // 目录
-            return Err(AppError::new(
-                AppErrorType::Unknown,
-                "目录不支持获取fsid",
-                None,
-            ));
-        }
-        let binding = PathBuf::from(path.to_string());
-        let parent = binding.parent().unwrap();
-        // load cached path list
-        let list = self.list_dir(parent.to_str().unwrap())?;
-        for item in list.list {
-            if item.path == path {
-                return Ok(item.fs_id);
-            }
-        }
-        Err(AppError::new(
-            AppErrorType::Unknown,
-            format!("未找到文件 {}", path).as_str(),
-            None,
-        ))
-    }
-
-    pub fn down_file<F>(
+    /// * `fs_id` - 文件在云端的唯一标识ID
+    /// * `local_path` - 本地保存路径
+    /// * `segments` - 并发分段数，0 或 1 时等同于普通下载
+    /// * `progress` - 进度回调（已下载字节, 总字节）
+    pub fn download_segmented<F>(
         &self,
-        remote: &str,
+        fs_id: u64,
         local_path: &str,
+        segments: usize,
         progress: Option<F>,
     ) -> Result<(), AppError>
     where
         F: Fn(u64, u64) + Send + Sync + 'static,
     {
-        self.get_fs_id_by_path(remote)
-            .and_then(|fs_id| self.down_file_by_id(fs_id, local_path, progress))
+        let (dlink, size) = self.query_dlink(fs_id)?;
+        self.download_segmented_link(dlink.as_str(), size, local_path, segments, progress)
+    }
+
+    fn download_segmented_link<F>(
+        &self,
+        download_link: &str,
+        size: u64,
+        local_path: &str,
+        segments: usize,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        let segments = segments.max(1) as u64;
+        if segments == 1 || size < segments {
+            return self.download(download_link, local_path, progress);
+        }
+        let url = self.build_download_url(download_link);
+        let part_path = download_part_path(local_path);
+        let segment_size = size.div_ceil(segments);
+        let ranges: Vec<(u64, u64)> = (0..segments)
+            .map(|i| (i * segment_size, ((i + 1) * segment_size).min(size)))
+            .filter(|(start, end)| start < end)
+            .collect();
+        let downloaded = AtomicU64::new(0);
+
+        let fut = async {
+            // 预分配文件，各分段写入各自的偏移处
+            tokio::fs::File::create(part_path.as_str())
+                .await?
+                .set_len(size)
+                .await?;
+            let tasks = ranges.iter().map(|&(start, end)| {
+                let (url, part_path) = (url.as_str(), part_path.as_str());
+                let (downloaded, progress) = (&downloaded, &progress);
+                async move {
+                    // 每个分段各自占用一个请求许可，分段数超过上限时排队，而不是共用一个许可同时发起
+                    let _permit = self.request_limiter.acquire().await;
+                    let mut resp = self
+                        .client
+                        .get(url)
+                        .header(
+                            reqwest::header::RANGE,
+                            format!("bytes={}-{}", start, end - 1),
+                        )
+                        .send()
+                        .await
+                        .map_err(|e| {
+                            AppError::new(AppErrorType::Network, e.to_string().as_str(), None)
+                        })?;
+                    if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                        return Ok::<bool, AppError>(false);
+                    }
+                    let mut file = tokio::fs::File::options()
+                        .write(true)
+                        .open(part_path)
+                        .await?;
+                    file.seek(SeekFrom::Start(start)).await?;
+                    let mut file =
+                        tokio::io::BufWriter::with_capacity(self.download_buffer_size, file);
+                    let mut written: u64 = 0;
+                    while let Some(chunk) = resp.chunk().await.map_err(|e| {
+                        AppError::new(AppErrorType::Network, e.to_string().as_str(), None)
+                    })? {
+                        file.write_all(&chunk).await?;
+                        written += chunk.len() as u64;
+                        let total = downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed)
+                            + chunk.len() as u64;
+                        if let Some(cb) = progress {
+                            cb(total, size);
+                        }
+                    }
+                    file.flush().await?;
+                    if written != end - start {
+                        return Err(AppError::new(
+                            AppErrorType::Network,
+                            format!(
+                                "分段 {}-{} 数据不完整: 期望 {} 字节，实际 {} 字节",
+                                start,
+                                end - 1,
+                                end - start,
+                                written
+                            )
+                            .as_str(),
+                            None,
+                        ));
+                    }
+                    Ok(true)
+                }
+            });
+            let results = futures::future::try_join_all(tasks).await?;
+            Ok::<bool, AppError>(results.into_iter().all(|ranged| ranged))
+        };
+        let ranged = self
+            .runtime
+            .block_on(fut)
+            .map_err(|e| AppError::new(AppErrorType::Network, e.to_string().as_str(), None))?;
+        if !ranged {
+            info!("服务端不支持 Range 请求，回退为单连接下载");
+            return self.download(download_link, local_path, progress);
+        }
+        std::fs::rename(part_path.as_str(), local_path)
+            .map_err(|e| AppError::new(AppErrorType::Client, e.to_string().as_str(), None))
+    }
+
+    /// 打开远程文件的流式读取句柄
+    /// 返回的 `RemoteFileReader` 实现了 `Read` 与 `Seek`，按需通过 Range 请求分段读取，无需完整下载文件
+    /// 注意：下载地址(dlink)有效期为8小时，超时后需要重新打开
+    /// # Arguments
+    /// * `fs_id` - 文件在云端的唯一标识ID
+    pub fn open_remote(&self, fs_id: u64) -> Result<RemoteFileReader<'_>, AppError> {
+        let (dlink, size) = self.query_dlink(fs_id)?;
+        let url = self.build_download_url(dlink.as_str());
+        Ok(RemoteFileReader::new(self, url, size))
+    }
+
+    /// 通过文件路径反向查询百度网盘云端的文件ID
+    /// # Arguments
+    /// * `path` - 文件路径
+    /// # Returns
+    /// * `u64` - 文件ID
+    /// # Errors
+    /// * `AppError` - 文件ID查询失败
+    pub(crate) fn get_fs_id_by_path(&self, path: &str) -> Result<u64, AppError> {
+        // 百度网盘在下载等操作时 需要用fsid，但是一般我们都是通过path管理 需要维护一个表
+        if path.ends_with("/") {
+            // 目录
+            return Err(AppError::new(
+                AppErrorType::Unknown,
+                "目录不支持获取fsid",
+                None,
+            ));
+        }
+        let path = self.resolve_path(path);
+        let binding = PathBuf::from(path.as_str());
+        let parent = binding.parent().unwrap();
+        // load cached path list
+        let list = self.list_dir(parent.to_str().unwrap())?;
+        for item in list.list {
+            if item.path == path {
+                return Ok(item.fs_id);
+            }
+        }
+        Err(AppError::new(
+            AppErrorType::Unknown,
+            format!("未找到文件 {}", path).as_str(),
+            None,
+        ))
+    }
+
+    pub fn down_file<F>(
+        &self,
+        remote: &str,
+        local_path: &str,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.down_file_with_policy(remote, local_path, PcsDownloadPolicy::Overwrite, progress)
+    }
+
+    /// 按远程路径下载文件，并按 `policy` 处理本地已存在的目标文件
+    pub fn down_file_with_policy<F>(
+        &self,
+        remote: &str,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.get_fs_id_by_path(remote)
+            .and_then(|fs_id| self.down_file_by_id_with_policy(fs_id, local_path, policy, progress))
     }
 
     pub fn down_file_by_id<F>(
@@ -1568,85 +4061,1582 @@ impl BaiduPcsClient {
     where
         F: Fn(u64, u64) + Send + Sync + 'static,
     {
-        self.get_file_info(true, vec![fs_id]).and_then(|meta_res| {
-            if meta_res.list.is_empty() {
-                Err(AppError::new(
-                    AppErrorType::Unknown,
-                    format!("未找到文件 {}", fs_id).as_str(),
-                    None,
-                ))
-            } else if meta_res.list[0].dlink.is_none() {
-                Err(AppError::new(
-                    AppErrorType::Unknown,
-                    format!("未找到文件下载链接 {}", fs_id).as_str(),
+        self.down_file_by_id_with_policy(fs_id, local_path, PcsDownloadPolicy::Overwrite, progress)
+    }
+
+    /// 按文件ID下载文件，并按 `policy` 处理本地已存在的目标文件
+    pub fn down_file_by_id_with_policy<F>(
+        &self,
+        fs_id: u64,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.get_file_info(true, vec![fs_id])
+            .and_then(|meta_res| self.download_meta(fs_id, meta_res, local_path, policy, progress))
+    }
+
+    /// 下载共享目录中的文件，并按 `policy` 处理本地已存在的目标文件
+    /// # Arguments
+    /// * `uk` - 共享目录创建者 ID
+    /// * `shared_fs_id` - 共享目录的 fs_id
+    /// * `fs_id` - 共享目录中待下载文件的 fs_id
+    pub fn down_shared_file<F>(
+        &self,
+        uk: u64,
+        shared_fs_id: u64,
+        fs_id: u64,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.get_shared_file_info(uk, shared_fs_id, true, vec![fs_id])
+            .and_then(|meta_res| self.download_meta(fs_id, meta_res, local_path, policy, progress))
+    }
+
+    /// 使用文件信息查询结果中的下载地址下载 `fs_id`
+    fn download_meta<F>(
+        &self,
+        fs_id: u64,
+        meta_res: PcsFileMetaResult,
+        local_path: &str,
+        policy: PcsDownloadPolicy,
+        progress: Option<F>,
+    ) -> Result<(), AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        if meta_res.list.is_empty() {
+            Err(AppError::new(
+                AppErrorType::Unknown,
+                format!("未找到文件 {}", fs_id).as_str(),
+                None,
+            ))
+        } else if meta_res.list[0].dlink.is_none() {
+            Err(AppError::new(
+                AppErrorType::Unknown,
+                format!("未找到文件下载链接 {}", fs_id).as_str(),
+                None,
+            ))
+        } else {
+            info!("准备下载文件 {:?}", meta_res.list[0]);
+            let meta = &meta_res.list[0];
+            let down_link = meta.dlink.as_ref().unwrap();
+            let expected = DownloadSidecar::from_meta(meta);
+            self.download_resumable(down_link, local_path, policy, progress, expected.as_ref())
+        }
+    }
+
+    /// 批量下载文件：先按每批 100 个一次性查询所有文件的下载地址，再依次下载
+    /// 下载地址有效期为 8 小时，批量下载耗时较长时靠后的地址可能已过期（返回 403/404），
+    /// 此时会重新查询该文件的下载地址并重试一次
+    /// # Arguments
+    /// * `targets` - (文件ID, 本地保存路径) 列表
+    /// * `policy` - 本地文件已存在时的处理策略
+    /// # Returns
+    /// * 每个文件的下载结果，顺序与 `targets` 一致
+    pub fn download_many(
+        &self,
+        targets: &[(u64, String)],
+        policy: PcsDownloadPolicy,
+    ) -> Result<Vec<Result<(), AppError>>, AppError> {
+        let mut links = HashMap::new();
+        for batch in targets.chunks(100) {
+            let fs_ids = batch.iter().map(|(fs_id, _)| *fs_id).collect();
+            for meta in self.get_file_info(true, fs_ids)?.list {
+                let expected = DownloadSidecar::from_meta(&meta);
+                if let Some(dlink) = meta.dlink {
+                    links.insert(meta.fs_id, (dlink, expected));
+                }
+            }
+        }
+        Ok(targets
+            .iter()
+            .map(|(fs_id, local_path)| {
+                let (link, expected) = links.get(fs_id).ok_or_else(|| {
+                    AppError::new(
+                        AppErrorType::Unknown,
+                        format!("未找到文件下载链接 {}", fs_id).as_str(),
+                        None,
+                    )
+                })?;
+                download_with_link_refresh(
+                    link,
+                    |link| {
+                        self.download_resumable(
+                            link,
+                            local_path,
+                            policy,
+                            None::<fn(u64, u64)>,
+                            expected.as_ref(),
+                        )
+                    },
+                    || self.query_dlink(*fs_id).map(|(dlink, _)| dlink),
+                )
+            })
+            .collect())
+    }
+
+    /// 下载整个远程目录到本地，保持目录结构（含空目录）
+    /// 文件按每批 `DOWNLOAD_DIR_BATCH_SIZE` 个交给 `download_many` 下载，`parallelism` 个线程同时处理不同批次
+    /// 实际并发仍受 `set_max_concurrent_requests` 的全局限制
+    /// # Arguments
+    /// * `remote_dir` - 远程目录路径
+    /// * `local_dir` - 本地保存目录，不存在时自动创建
+    /// * `parallelism` - 同时下载的线程数，0 按 1 处理
+    /// * `progress` - 进度回调，参数为 (已完成文件数, 文件总数)
+    /// # Returns
+    /// * 每个文件的 (远程路径, 下载结果)，本地已存在的文件按 `Overwrite` 覆盖
+    pub fn download_dir<F>(
+        &self,
+        remote_dir: &str,
+        local_dir: &str,
+        parallelism: usize,
+        progress: Option<F>,
+    ) -> Result<DownloadDirResult, AppError>
+    where
+        F: Fn(u64, u64) + Send + Sync,
+    {
+        let remote_dir = self.resolve_path(remote_dir);
+        let items = self.list_dir_recursive(remote_dir.as_str(), None)?;
+        let (dirs, files) = download_dir_plan(remote_dir.as_str(), local_dir, items.as_slice());
+        for dir in dirs.iter() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let batches: Vec<&[(u64, String, String)]> =
+            files.chunks(DOWNLOAD_DIR_BATCH_SIZE).collect();
+        let next_batch = AtomicU64::new(0);
+        let done = AtomicU64::new(0);
+        let total = files.len() as u64;
+        let results = Mutex::new(Vec::with_capacity(files.len()));
+        std::thread::scope(|scope| {
+            for _ in 0..parallelism.clamp(1, batches.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next_batch.fetch_add(1, Ordering::SeqCst) as usize;
+                    let Some(batch) = batches.get(index) else {
+                        break;
+                    };
+                    let targets: Vec<(u64, String)> = batch
+                        .iter()
+                        .map(|(fs_id, _, local_path)| (*fs_id, local_path.clone()))
+                        .collect();
+                    let batch_results = match self
+                        .download_many(targets.as_slice(), PcsDownloadPolicy::Overwrite)
+                    {
+                        Ok(rs) => rs,
+                        // 查询下载地址失败时，本批次的所有文件均记为失败
+                        Err(e) => batch
+                            .iter()
+                            .map(|_| Err(AppError::new(e.error_type, e.message.as_str(), e.errno)))
+                            .collect(),
+                    };
+                    let finished =
+                        done.fetch_add(batch.len() as u64, Ordering::SeqCst) + batch.len() as u64;
+                    if let Some(ref cb) = progress {
+                        cb(finished, total);
+                    }
+                    let mut results = results.lock().unwrap();
+                    for ((_, remote_path, _), rs) in batch.iter().zip(batch_results) {
+                        results.push((remote_path.clone(), rs));
+                    }
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(results)
+    }
+
+    /// 下载图片缩略图
+    /// 缩略图地址来自 `list_dir_web` 返回的 `thumbs`，按尺寸从小到大依次为 0、1、2
+    /// # Arguments
+    /// * `item` - 图片文件条目（category 为 3 且包含 thumbs）
+    /// * `size_index` - 缩略图尺寸序号，取值 0..3
+    /// * `local_path` - 本地保存路径
+    pub fn download_thumbnail(
+        &self,
+        item: &PcsFileItem,
+        size_index: usize,
+        local_path: &str,
+    ) -> Result<(), AppError> {
+        let url = Self::select_thumbnail(item, size_index)?;
+        self.download(url, local_path, None::<fn(u64, u64)>)
+    }
+
+    /// 校验并选取指定尺寸的缩略图地址
+    fn select_thumbnail(item: &PcsFileItem, size_index: usize) -> Result<&str, AppError> {
+        if size_index >= 3 {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!("缩略图尺寸序号 {} 无效，取值范围为 0..3", size_index).as_str(),
+                None,
+            ));
+        }
+        if item.category != 3 {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!("{} 不是图片文件，没有缩略图", item.path).as_str(),
+                None,
+            ));
+        }
+        item.thumbs
+            .as_ref()
+            .and_then(|thumbs| thumbs.get(size_index))
+            .map(|url| url.as_str())
+            .ok_or_else(|| {
+                AppError::new(
+                    AppErrorType::Client,
+                    format!("{} 没有缩略图地址，请使用 list_dir_web 获取", item.path).as_str(),
                     None,
-                ))
-            } else {
-                info!("准备下载文件 {:?}", meta_res.list[0]);
-                let down_link = meta_res.list[0].dlink.as_ref().unwrap();
-                self.download(down_link, local_path, progress)
+                )
+            })
+    }
+
+    /// 将云端文件 `src` 复制到 `dest`，并以上传结果的形式返回 `dest` 的文件信息
+    fn copy_as_upload_result(
+        &self,
+        src: &str,
+        dest: &str,
+    ) -> Result<PcsFileUploadResult, AppError> {
+        let copied = self.copy_file(src, dest)?;
+        if let Some(task) = copied.info().iter().find(|task| *task.errno() != 0) {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!("复制 {} 失败", task.path()).as_str(),
+                Some(*task.errno() as i64),
+            ));
+        }
+        let meta = self.stat(dest, false)?;
+        Ok(PcsFileUploadResult {
+            path: meta.path.unwrap_or_else(|| dest.to_string()),
+            size: meta.size,
+            ctime: meta.server_ctime,
+            mtime: meta.server_mtime,
+            md5: meta.md5,
+            fs_id: meta.fs_id,
+        })
+    }
+
+    /// 估算 `backup_file` 备份 `local_root` 需要的接口调用次数，不发起上传相关请求
+    /// 本地扫描规则与 `backup_file` 一致；分片大小取自缓存的用户信息，未缓存时请求一次用户信息
+    /// # Arguments
+    /// * `local_root` - 本地文件或目录路径
+    /// * `remote_root` - 计划备份到的云盘路径
+    pub fn plan(&self, local_root: &str, remote_root: &str) -> Result<BackupPlan, AppError> {
+        let remote_root = self.resolve_path(remote_root);
+        let metadata = std::fs::metadata(local_root)?;
+        let sizes = if metadata.is_file() {
+            vec![metadata.len()]
+        } else {
+            backup_targets(Path::new(local_root), remote_root.as_str())?
+                .iter()
+                .map(|(local, _)| std::fs::metadata(local).map(|m| m.len()))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let slice_size = match self.user_info.as_ref() {
+            Some(user) => user.get_user_block_slice_size(),
+            None => self.get_user_info()?.get_user_block_slice_size(),
+        };
+        Ok(BackupPlan::new(slice_size, sizes))
+    }
+
+    /// 自定义功能： 备份指定文件到应用目录下
+    /// 机制说明： 1. 如果文件小于 `FILE_MAX_SIZE` ，使用小文件上传接口，否则使用大文件上传接口
+    /// 2. 如果是目录，递归上传其下所有文件，并在 `pcs_path` 下保留子目录结构（跳过隐藏文件）
+    /// 3. 开启 `set_backup_dedup` 时，目录中内容相同的文件只上传一次，其余通过云端复制生成
+    /// # Arguments
+    /// * `local_file` - 本地文件或目录路径(待上传文件的绝对路径)
+    /// * `pcs_path` - 上传后使用的文件绝对路径，云盘的存储路径，需要注意的是有限制只能上传到 /apps/{app-name}/目录下，其他目录会返回 31064
+    /// # Returns
+    /// * `FileUpload` - 文件上传结果
+    /// # Errors
+    /// * `AppError` - 文件上传失败
+    pub fn backup_file(
+        &mut self,
+        local_file: &str,
+        pcs_path: &str,
+    ) -> Result<Vec<PcsFileUploadResult>, AppError> {
+        let pcs_path = self.resolve_path(pcs_path);
+        let pcs_path = pcs_path.as_str();
+        let file = File::open(local_file)?;
+        let mut rs: Vec<PcsFileUploadResult> = Vec::new();
+        if file.metadata()?.is_file() {
+            rs.push(self.upload_large_file(
+                local_file,
+                pcs_path,
+                PcsUploadPolicy::Overwrite,
+                |_| {},
+            )?)
+        } else if file.metadata()?.is_dir() {
+            // 本地文件 (MD5, 大小) -> 已上传的云端路径，仅在开启去重时使用
+            let mut uploaded: HashMap<(String, u64), String> = HashMap::new();
+            for (local, remote) in backup_targets(Path::new(local_file), pcs_path)? {
+                let key = if self.backup_dedup {
+                    Some((
+                        local_file_md5(local.as_str())?,
+                        std::fs::metadata(&local)?.len(),
+                    ))
+                } else {
+                    None
+                };
+                if let Some(src) = key.as_ref().and_then(|key| uploaded.get(key)) {
+                    match self.copy_as_upload_result(src.as_str(), remote.as_str()) {
+                        Ok(result) => {
+                            rs.push(result);
+                            continue;
+                        }
+                        Err(e) => warn!("从 {} 复制到 {} 失败，改为上传: {}", src, remote, e),
+                    }
+                }
+                let result = self.upload_large_file(
+                    local.as_str(),
+                    remote.as_str(),
+                    PcsUploadPolicy::Overwrite,
+                    |_| {},
+                )?;
+                if let Some(key) = key {
+                    uploaded.entry(key).or_insert_with(|| result.path().clone());
+                }
+                rs.push(result);
             }
+        }
+        Ok(rs)
+    }
+}
+
+/// 进度回调类型别名
+pub type ProgressCallback = Arc<Mutex<dyn FnMut(ProgressInfo) + Send>>;
+
+/// 远程文件流式读取默认缓冲大小 1MB
+const REMOTE_READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// 远程文件的流式读取句柄，由 `BaiduPcsClient::open_remote` 创建
+/// 内部按缓冲大小分段发起 Range 请求，Seek 到缓冲区外时重新请求对应区间
+pub struct RemoteFileReader<'a> {
+    client: &'a BaiduPcsClient,
+    /// 已附加 access_token 的下载地址
+    url: String,
+    /// 文件总大小
+    size: u64,
+    /// 当前读取位置
+    pos: u64,
+    /// 缓冲区数据
+    buffer: Vec<u8>,
+    /// 缓冲区第一个字节对应的文件偏移
+    buffer_start: u64,
+    /// 每次 Range 请求的字节数
+    buffer_size: usize,
+}
+
+impl<'a> RemoteFileReader<'a> {
+    pub(crate) fn new(client: &'a BaiduPcsClient, url: String, size: u64) -> Self {
+        Self {
+            client,
+            url,
+            size,
+            pos: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            buffer_size: REMOTE_READ_BUFFER_SIZE,
+        }
+    }
+
+    /// 设置每次 Range 请求的字节数，默认 1MB
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
+    /// 远程文件总大小
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 当前读取位置是否落在缓冲区内
+    fn is_buffered(&self) -> bool {
+        self.pos >= self.buffer_start && self.pos < self.buffer_start + self.buffer.len() as u64
+    }
+
+    /// 从当前位置重新请求一段数据填充缓冲区
+    fn fill_buffer(&mut self) -> std::io::Result<()> {
+        let len = (self.size - self.pos).min(self.buffer_size as u64) as usize;
+        let mut bytes = self
+            .client
+            .fetch_range(self.url.as_str(), self.pos, len)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        if bytes.len() > len {
+            // 服务端忽略了 Range 返回完整内容，截取所需区间
+            let start = (self.pos as usize).min(bytes.len());
+            let end = (start + len).min(bytes.len());
+            bytes = bytes[start..end].to_vec();
+        }
+        self.buffer = bytes;
+        self.buffer_start = self.pos;
+        Ok(())
+    }
+}
+
+impl Read for RemoteFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.size {
+            return Ok(0);
+        }
+        if !self.is_buffered() {
+            self.fill_buffer()?;
+            if self.buffer.is_empty() {
+                return Ok(0);
+            }
+        }
+        let offset = (self.pos - self.buffer_start) as usize;
+        let n = (self.buffer.len() - offset).min(buf.len());
+        buf[..n].copy_from_slice(&self.buffer[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for RemoteFileReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        match target {
+            Some(p) => {
+                self.pos = p;
+                Ok(p)
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek 位置无效",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::baidu_pcs_sdk::pcs::PcsUploadPolicy::Overwrite;
+    use crate::baidu_pcs_sdk::pcs::{
+        appdata_path, clock_skew, decode_unicode_escapes, default_server_unreachable,
+        delete_async_mode, delete_in_batches, dir_size_with, download_dir_plan, download_part_path,
+        download_sidecar_path, download_with_link_refresh, format_tree, get_file_block_list,
+        guess_content_type, if_rest_ok_then_get_else_err, index_by_fs_id, list_dir_pages,
+        merge_attributes, paginate, parse_headers, pick_upload_path, poll_file_task,
+        precreate_attributes, resolve_remote_path, size_breakdown, suffixed_name, to_remote_path,
+        token_validity, upload_server_candidates, upload_slices, walk_dir, with_permit,
+        with_server_failover, BaiduPcsClient, DownloadSidecar, Md5Cache, PauseControl,
+        PcsDownloadPolicy, PcsExifInfo, PcsFileSliceInfo, PcsUploadPolicy, ProgressInfo,
+        RemoteFileReader, UploadOptions, APPDATA_ROOT, LIST_DIR_PAGE_SIZE,
+    };
+    use crate::baidu_pcs_sdk::{
+        timestamp_to_utc, token_refresh_threshold, token_refresh_threshold_for, AccountSummary,
+        AppError, AppErrorType, BackupPlan, BaiduPcsApp, CategoryCounts, PcsAccessToken,
+        PcsApiError, PcsDiskQuota, PcsError, PcsFileItem, PcsFileListResult, PcsFileMetaInfo,
+        PcsFileMetaResult, PcsFileSlicePrepareResult, PcsFileTaskOperationResult,
+        PcsFileTaskStatus, PcsFileUploadResult, PcsUserInfo, UploadServerResult,
+        TOKEN_REFRESH_JITTER_SECS, TOKEN_REFRESH_THRESHOLD_SECS,
+    };
+    use chrono::TimeZone;
+    use md5::{Digest, Md5};
+    use std::env;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+    const BAIDU_PCS_APP: BaiduPcsApp = BaiduPcsApp {
+        app_key: env!("BAIDU_PCS_APP_KEY"),
+        app_secret: env!("BAIDU_PCS_APP_SECRET"),
+        app_name: env!("BAIDU_PCS_APP_NAME"),
+        app_id: None,
+    };
+
+    /// 启动一个简易的本地 HTTP 服务模拟下载地址，支持 `Range: bytes=a-b` 请求
+    /// 返回可直接拼接 `&access_token=` 的地址
+    fn spawn_range_server(data: Vec<u8>) -> String {
+        spawn_server(data, true)
+    }
+
+    /// 启动本地 HTTP 服务，`support_range` 为 false 时忽略 Range 请求头，始终返回 200
+    fn spawn_server(data: Vec<u8>, support_range: bool) -> String {
+        spawn_handler(move |request, stream| {
+            let range = request_range(request, data.len()).filter(|_| support_range);
+            let (status, body) = match range {
+                Some((a, b)) => ("206 Partial Content", &data[a..b]),
+                None => ("200 OK", &data[..]),
+            };
+            let header = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(body);
+        })
+    }
+
+    /// 启动本地 HTTP 服务，每个连接由独立线程读完请求头后交给 `respond` 写回响应
+    /// `respond` 收到的请求头已转为小写；返回的下载地址同 `spawn_server`
+    fn spawn_handler<F>(respond: F) -> String
+    where
+        F: Fn(&str, &mut TcpStream) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let respond = Arc::new(respond);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let respond = respond.clone();
+                std::thread::spawn(move || {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request).to_lowercase();
+                    respond(request.as_str(), &mut stream);
+                });
+            }
+        });
+        format!("http://{}/file?fid=1", addr)
+    }
+
+    /// 请求头中 `range: bytes=a-b` 对应的 `[a, b + 1)` 区间，超出 `len` 的部分截掉
+    fn request_range(request: &str, len: usize) -> Option<(usize, usize)> {
+        let (a, b) = request
+            .lines()
+            .find_map(|l| l.strip_prefix("range: bytes="))?
+            .trim()
+            .split_once('-')?;
+        let a: usize = a.parse().ok()?;
+        let b: usize = b.parse().unwrap_or(len - 1);
+        Some((a.min(len), (b + 1).min(len)))
+    }
+
+    #[test]
+    fn test_download_buffered_identical() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(300 * 1024).collect();
+        let url = spawn_range_server(data.clone());
+        let local = env::temp_dir().join(format!("pcs-test-buffered-{}", std::process::id()));
+        let local = local.to_str().unwrap();
+        for size in [0usize, 7, 64 * 1024] {
+            let mut client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+            client.set_download_buffer_size(size);
+            client
+                .download(url.as_str(), local, None::<fn(u64, u64)>)
+                .unwrap();
+            assert_eq!(std::fs::read(local).unwrap(), data);
+        }
+        std::fs::remove_file(local).unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_after_download_keeps_file_complete() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(3 * 1024 * 1024 + 17).collect();
+        let url = spawn_range_server(data.clone());
+        let local = env::temp_dir().join(format!("pcs-test-shutdown-{}", std::process::id()));
+        let mut client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        // 缓冲区大于文件，全部内容在结束时一次写入
+        client.set_download_buffer_size(4 * 1024 * 1024);
+        let progress = Arc::new(AtomicU64::new(0));
+        let reported = progress.clone();
+        client
+            .download(
+                url.as_str(),
+                local.to_str().unwrap(),
+                Some(move |done: u64, _| reported.store(done, Ordering::SeqCst)),
+            )
+            .unwrap();
+        client.shutdown();
+
+        assert_eq!(progress.load(Ordering::SeqCst), data.len() as u64);
+        assert_eq!(std::fs::read(&local).unwrap(), data);
+        std::fs::remove_file(&local).unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_waits_for_in_flight_write() {
+        let local =
+            env::temp_dir().join(format!("pcs-test-shutdown-pending-{}", std::process::id()));
+        let _ = std::fs::remove_file(&local);
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        let started = Arc::new(std::sync::Barrier::new(2));
+        let (path, running) = (local.clone(), started.clone());
+        // 与 tokio::fs 相同，文件写入以阻塞任务在运行时中执行；调用 shutdown 时写入仍未完成
+        client.runtime.spawn_blocking(move || {
+            running.wait();
+            std::thread::sleep(Duration::from_millis(200));
+            std::fs::write(&path, b"in-flight").unwrap();
+        });
+        started.wait();
+        client.shutdown();
+
+        assert_eq!(std::fs::read(&local).unwrap(), b"in-flight");
+        std::fs::remove_file(&local).unwrap();
+    }
+
+    #[test]
+    fn test_download_no_clobber_keeps_existing() {
+        let url = spawn_range_server(b"remote content".to_vec());
+        let local = env::temp_dir().join(format!("pcs-test-noclobber-{}", std::process::id()));
+        let local = local.to_str().unwrap();
+        std::fs::write(local, b"local data").unwrap();
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+
+        client
+            .download_no_clobber(url.as_str(), local, None::<fn(u64, u64)>)
+            .unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), b"local data");
+
+        let res = client.download_with_policy(
+            url.as_str(),
+            local,
+            PcsDownloadPolicy::Fail,
+            None::<fn(u64, u64)>,
+        );
+        assert!(res.is_err());
+        assert_eq!(std::fs::read(local).unwrap(), b"local data");
+
+        std::fs::remove_file(local).unwrap();
+        client
+            .download_no_clobber(url.as_str(), local, None::<fn(u64, u64)>)
+            .unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), b"remote content");
+        std::fs::remove_file(local).unwrap();
+    }
+
+    #[test]
+    fn test_download_atomic_part_file() {
+        // 声明 1000 字节但只发送 100 字节后断开，模拟下载中断
+        let url = spawn_handler(|_, stream| {
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\nConnection: close\r\n\r\n");
+            let _ = stream.write_all(&[1u8; 100]);
+        });
+        let local = env::temp_dir().join(format!("pcs-test-atomic-{}", std::process::id()));
+        let local = local.to_str().unwrap();
+        let part = download_part_path(local);
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+
+        let res = client.download(url.as_str(), local, None::<fn(u64, u64)>);
+        assert!(res.is_err());
+        assert!(!Path::new(local).exists());
+        assert!(Path::new(part.as_str()).exists());
+
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let url = spawn_range_server(data.clone());
+        client
+            .download(url.as_str(), local, None::<fn(u64, u64)>)
+            .unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), data);
+        assert!(!Path::new(part.as_str()).exists());
+        std::fs::remove_file(local).unwrap();
+    }
+
+    #[test]
+    fn test_download_resume_checks_sidecar() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let url = spawn_range_server(data.clone());
+        let local = env::temp_dir().join(format!("pcs-test-resume-{}", std::process::id()));
+        let local = local.to_str().unwrap();
+        let part = download_part_path(local);
+        let sidecar = download_sidecar_path(local);
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        let expected = DownloadSidecar {
+            size: 1000,
+            md5: "new-md5".to_string(),
+        };
+
+        // 记录不一致：远程文件已变化，丢弃 .part 重新下载
+        std::fs::write(&part, [9u8; 300]).unwrap();
+        let stale = DownloadSidecar {
+            size: 1000,
+            md5: "old-md5".to_string(),
+        };
+        std::fs::write(&sidecar, serde_json::to_string(&stale).unwrap()).unwrap();
+        client
+            .download_resumable(
+                url.as_str(),
+                local,
+                PcsDownloadPolicy::Overwrite,
+                None::<fn(u64, u64)>,
+                Some(&expected),
+            )
+            .unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), data);
+        assert!(!Path::new(part.as_str()).exists());
+        assert!(!Path::new(sidecar.as_str()).exists());
+
+        // 记录一致：从 .part 末尾继续下载，已下载的部分原样保留
+        std::fs::write(&part, [9u8; 300]).unwrap();
+        std::fs::write(&sidecar, serde_json::to_string(&expected).unwrap()).unwrap();
+        client
+            .download_resumable(
+                url.as_str(),
+                local,
+                PcsDownloadPolicy::Overwrite,
+                None::<fn(u64, u64)>,
+                Some(&expected),
+            )
+            .unwrap();
+        let downloaded = std::fs::read(local).unwrap();
+        assert_eq!(&downloaded[..300], &[9u8; 300]);
+        assert_eq!(&downloaded[300..], &data[300..]);
+        assert!(!Path::new(sidecar.as_str()).exists());
+        std::fs::remove_file(local).unwrap();
+    }
+
+    #[test]
+    fn test_build_download_url() {
+        let client = BaiduPcsClient::new("token-abc", BAIDU_PCS_APP);
+        let url = client
+            .build_download_url("https://d.pcs.baidu.com/file/x?fid=1\\u0026rt=pr\\u0026sign=s");
+        assert_eq!(
+            url,
+            "https://d.pcs.baidu.com/file/x?fid=1&rt=pr&sign=s&access_token=token-abc"
+        );
+        assert!(url.contains("access_token=token-abc"));
+        assert_eq!(decode_unicode_escapes("a\\uzzzzb"), "a\\uzzzzb");
+    }
+
+    #[test]
+    fn test_download_segmented_reassembled() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(100_003).collect();
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        for support_range in [true, false] {
+            let url = spawn_server(data.clone(), support_range);
+            let local = env::temp_dir().join(format!(
+                "pcs-test-segmented-{}-{}",
+                support_range,
+                std::process::id()
+            ));
+            let local = local.to_str().unwrap();
+            for segments in [1usize, 4, 7] {
+                client
+                    .download_segmented_link(
+                        url.as_str(),
+                        data.len() as u64,
+                        local,
+                        segments,
+                        None::<fn(u64, u64)>,
+                    )
+                    .unwrap();
+                assert_eq!(std::fs::read(local).unwrap(), data);
+            }
+            std::fs::remove_file(local).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_download_segmented_respects_request_limit() {
+        // 每个连接由独立线程处理，最后一个字节发出前才减少计数；计数重叠说明有分段请求同时进行
+        let data: Vec<u8> = (0..=255u8).cycle().take(4000).collect();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let (server_data, server_max_seen) = (data.clone(), max_seen.clone());
+        let url = spawn_handler(move |request, stream| {
+            let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            server_max_seen.fetch_max(n, Ordering::SeqCst);
+            let (a, b) = request_range(request, server_data.len()).unwrap();
+            let body = &server_data[a..b];
+            let header = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body[..body.len() - 1]);
+            std::thread::sleep(Duration::from_millis(20));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream.write_all(&body[body.len() - 1..]);
+        });
+
+        let mut client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        client.set_max_concurrent_requests(1);
+        let local = env::temp_dir().join(format!("pcs-test-segment-limit-{}", std::process::id()));
+        let local = local.to_str().unwrap();
+        client
+            .download_segmented_link(
+                url.as_str(),
+                data.len() as u64,
+                local,
+                4,
+                None::<fn(u64, u64)>,
+            )
+            .unwrap();
+        assert_eq!(std::fs::read(local).unwrap(), data);
+        std::fs::remove_file(local).unwrap();
+        assert_eq!(max_seen.load(Ordering::SeqCst), 1);
+    }
+
+    /// 测试用的文件条目 JSON：文件名取路径最后一段，时间均为 0
+    fn pcs_file_item_json(fs_id: u64, path: &str, size: u64, is_dir: i32) -> serde_json::Value {
+        serde_json::json!({
+            "fs_id": fs_id, "path": path, "server_filename": path.rsplit('/').next().unwrap(),
+            "size": size, "server_mtime": 0, "server_ctime": 0, "local_mtime": 0,
+            "local_ctime": 0, "isdir": is_dir, "category": 6
+        })
+    }
+
+    /// 测试用的文件条目，见 `pcs_file_item_json`
+    fn pcs_file_item(fs_id: u64, path: &str, size: u64, is_dir: i32) -> PcsFileItem {
+        serde_json::from_value(pcs_file_item_json(fs_id, path, size, is_dir)).unwrap()
+    }
+
+    /// 测试用的普通用户（非会员，分片 4MB）
+    fn test_user() -> PcsUserInfo {
+        serde_json::from_value(serde_json::json!({
+            "baidu_name": "u", "netdisk_name": "u", "avatar_url": "", "vip_type": 0, "uk": 1
+        }))
+        .unwrap()
+    }
+
+    /// 测试用的分片信息：大小为 1，没有校验和，创建与修改时间为 1
+    fn slice_info(path: &str) -> PcsFileSliceInfo {
+        PcsFileSliceInfo {
+            path: path.to_string(),
+            size: 1,
+            content_md5: None,
+            slice_md5: None,
+            block_list: vec![],
+            ctime: 1,
+            mtime: 1,
+        }
+    }
+
+    /// 测试用的预上传结果：上传 ID 为 `up-1`，只需上传第 0 个分片
+    fn prepare_result(path: &str) -> PcsFileSlicePrepareResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path, "uploadid": "up-1", "return_type": 1, "block_list": [0]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_walk_dir_max_depth() {
+        let item = |path: &str, is_dir: i32| pcs_file_item(1, path, 0, is_dir);
+        let mut listed = Vec::new();
+        let mut list = |dir: &str| {
+            listed.push(dir.to_string());
+            Ok(match dir {
+                "/a" => vec![item("/a/f1", 0), item("/a/b", 1)],
+                "/a/b" => vec![item("/a/b/f2", 0), item("/a/b/c", 1)],
+                "/a/b/c" => vec![item("/a/b/c/f3", 0)],
+                _ => vec![],
+            })
+        };
+
+        let files = walk_dir("/a", Some(1), &mut list).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path().as_str()).collect();
+        assert_eq!(paths, vec!["/a/f1", "/a/b"]);
+
+        let files = walk_dir("/a", None, &mut list).unwrap();
+        assert_eq!(files.len(), 5);
+        assert!(files.iter().any(|f| f.path() == "/a/b/c/f3"));
+        // max_depth=1 时只列出了 /a 一次，未进入子目录
+        assert_eq!(listed, vec!["/a", "/a", "/a/b", "/a/b/c"]);
+    }
+
+    #[test]
+    fn test_walk_dir_lists_dirs_without_subdirs() {
+        let item = |path: &str, is_dir: i32, dir_empty: Option<i32>| -> PcsFileItem {
+            let mut json = pcs_file_item_json(1, path, 0, is_dir);
+            json["dir_empty"] = serde_json::json!(dir_empty);
+            serde_json::from_value(json).unwrap()
+        };
+        let mut listed = Vec::new();
+        let mut list = |dir: &str| {
+            listed.push(dir.to_string());
+            Ok(match dir {
+                "/a" => vec![item("/a/files", 1, Some(1)), item("/a/empty", 1, Some(1))],
+                "/a/files" => vec![item("/a/files/f1", 0, None)],
+                _ => vec![],
+            })
+        };
+
+        let files = walk_dir("/a", None, &mut list).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path().as_str()).collect();
+        // dir_empty 为 1 的目录没有子目录，但其中的文件不能遗漏
+        assert_eq!(paths, vec!["/a/files", "/a/files/f1", "/a/empty"]);
+        assert_eq!(listed, vec!["/a", "/a/files", "/a/empty"]);
+    }
+
+    #[test]
+    fn test_size_breakdown() {
+        let item = |path: &str, is_dir: i32, size: u64| pcs_file_item(1, path, size, is_dir);
+        let mut list = |dir: &str| {
+            Ok(match dir {
+                "/r" => vec![
+                    item("/r/a", 1, 0),
+                    item("/r/b", 1, 0),
+                    item("/r/f.txt", 0, 7),
+                ],
+                "/r/a" => vec![item("/r/a/x", 0, 10), item("/r/a/s", 1, 0)],
+                "/r/a/s" => vec![item("/r/a/s/y", 0, 5)],
+                "/r/b" => vec![item("/r/b/z", 0, 100)],
+                _ => vec![],
+            })
+        };
+        assert_eq!(dir_size_with("/r", &mut list).unwrap(), 122);
+        assert_eq!(
+            size_breakdown("/r", &mut list).unwrap(),
+            vec![("/r/b".to_string(), 100), ("/r/a".to_string(), 15)]
+        );
+    }
+
+    #[test]
+    fn test_download_dir_plan() {
+        let item = |fs_id: u64, path: &str, is_dir: i32| pcs_file_item(fs_id, path, 0, is_dir);
+        let items = vec![
+            item(1, "/r/a.txt", 0),
+            item(2, "/r/sub", 1),
+            item(3, "/r/sub/b.txt", 0),
+            item(4, "/r/empty", 1),
+            item(5, "/rx/other.txt", 0),
+        ];
+        let local = Path::new("local");
+        let (dirs, files) = download_dir_plan("/r/", "local", items.as_slice());
+        assert_eq!(
+            dirs,
+            vec![local.to_path_buf(), local.join("sub"), local.join("empty")]
+        );
+        assert_eq!(
+            files,
+            vec![
+                (
+                    1,
+                    "/r/a.txt".to_string(),
+                    local.join("a.txt").to_string_lossy().to_string()
+                ),
+                (
+                    3,
+                    "/r/sub/b.txt".to_string(),
+                    local.join("sub/b.txt").to_string_lossy().to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upload_as_names() {
+        assert_eq!(suffixed_name("a.txt", 2), "a_2.txt");
+        assert_eq!(suffixed_name("a.tar.gz", 3), "a.tar_3.gz");
+        assert_eq!(suffixed_name("README", 2), "README_2");
+        assert_eq!(suffixed_name(".env", 2), ".env_2");
+
+        let existing: std::collections::HashSet<String> =
+            ["/apps/demo/a.txt", "/apps/demo/a_2.txt"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        assert_eq!(
+            pick_upload_path("/apps/demo/b.txt", &existing, suffixed_name).as_deref(),
+            Some("/apps/demo/b.txt")
+        );
+        assert_eq!(
+            pick_upload_path("/apps/demo/a.txt", &existing, suffixed_name).as_deref(),
+            Some("/apps/demo/a_3.txt")
+        );
+        assert_eq!(
+            pick_upload_path("/apps/demo/a.txt", &existing, |name, n| format!(
+                "{} ({})",
+                name, n
+            ))
+            .as_deref(),
+            Some("/apps/demo/a.txt (2)")
+        );
+        // 所有候选名称均被占用
+        assert_eq!(
+            pick_upload_path("/apps/demo/a.txt", &existing, |_, _| "a.txt".to_string()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_upload_result_is_renamed_from() {
+        let result: PcsFileUploadResult = serde_json::from_value(serde_json::json!({
+            "path": "/apps/demo/text(1).txt", "size": 4, "ctime": 0, "mtime": 0,
+            "md5": "abcd", "fs_id": 1
+        }))
+        .unwrap();
+        assert!(result.is_renamed_from("/apps/demo/text.txt"));
+        assert!(!result.is_renamed_from("/apps/demo/text(1).txt"));
+    }
+
+    #[test]
+    fn test_get_file_block_list_uses_md5_cache() {
+        let dir = env::temp_dir().join(format!("pcs-md5-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        let path_str = path.to_str().unwrap();
+        let cache_path = dir.join("md5-cache.jsonl");
+        let user = test_user();
+        let md5 = |cache: Option<&Md5Cache>, reports: &mut usize| {
+            get_file_block_list(&user, path_str, true, cache, &mut |_, _| *reports += 1)
+                .unwrap()
+                .content_md5
+                .unwrap()
+        };
+
+        std::fs::write(&path, b"hello world").unwrap();
+        let mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let cache = Md5Cache::load(&cache_path);
+        let mut reports = 0;
+        let original = md5(Some(&cache), &mut reports);
+        assert_eq!(cache.len(), 1);
+
+        // 内容改变但大小与修改时间不变：命中缓存，返回的仍是缓存中的校验和
+        std::fs::write(&path, b"HELLO WORLD").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        reports = 0;
+        assert_eq!(md5(Some(&cache), &mut reports), original);
+        assert_eq!(reports, 1);
+        assert_ne!(md5(None, &mut reports), original);
+        // 缓存已写入文件，重新加载后同样命中
+        assert_eq!(
+            md5(Some(&Md5Cache::load(&cache_path)), &mut reports),
+            original
+        );
+
+        // 大小变化后重新计算
+        std::fs::write(&path, b"hello world!").unwrap();
+        let changed = md5(Some(&cache), &mut reports);
+        assert_ne!(changed, original);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(Md5Cache::load(&cache_path).len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_file_block_list_hash_progress() {
+        let path = env::temp_dir().join(format!("pcs-hash-progress-{}.bin", std::process::id()));
+        // 普通用户分片 4MB，10MB 文件共 3 个分片
+        let size = 10 * 1024 * 1024u64;
+        std::fs::write(&path, vec![7u8; size as usize]).unwrap();
+        let user = test_user();
+        let mut reports = Vec::new();
+        let info = get_file_block_list(&user, path.to_str().unwrap(), true, None, &mut |h, t| {
+            reports.push((h, t))
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(info.block_list.len(), 3);
+        assert_eq!(reports.len(), 3);
+        assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(reports.last(), Some(&(size, size)));
+    }
+
+    #[test]
+    fn test_upload_options_times_in_precreate_and_merge() {
+        let path = env::temp_dir().join(format!("pcs-upload-options-{}.bin", std::process::id()));
+        std::fs::write(&path, b"archive").unwrap();
+        let user = test_user();
+        let fs_meta =
+            get_file_block_list(&user, path.to_str().unwrap(), true, None, &mut |_, _| {}).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let task = prepare_result("/apps/a.bin");
+        let options = UploadOptions {
+            policy: PcsUploadPolicy::Rename,
+            local_ctime: Some(1_500_000_000),
+            local_mtime: Some(1_600_000_000),
+            mode: 3,
+            is_revision: false,
+            zip_quality: None,
+            zip_sign: None,
+            exif_info: None,
+            detect_exif: false,
+            content_type: None,
+            pause: None,
+        };
+
+        let precreate =
+            serde_json::to_value(precreate_attributes("/apps/a.bin", &fs_meta, &options).unwrap())
+                .unwrap();
+        assert_eq!(precreate["local_ctime"], 1_500_000_000);
+        assert_eq!(precreate["local_mtime"], 1_600_000_000);
+        assert_eq!(precreate["rtype"], 1);
+
+        let merge =
+            serde_json::to_value(merge_attributes(&task, &fs_meta, "[]", &options).unwrap())
+                .unwrap();
+        assert_eq!(merge["local_ctime"], 1_500_000_000);
+        assert_eq!(merge["local_mtime"], 1_600_000_000);
+        assert_eq!(merge["rtype"], 1);
+        assert_eq!(merge["mode"], 3);
+        assert!(merge.get("is_revision").is_none());
+
+        // 未指定时间时取本地文件元数据
+        let merge = serde_json::to_value(
+            merge_attributes(&task, &fs_meta, "[]", &UploadOptions::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(merge["local_mtime"], fs_meta.mtime);
+        assert_eq!(merge["mode"], 2);
+        assert_eq!(merge["is_revision"], 1);
+    }
+
+    #[test]
+    #[cfg(feature = "exif")]
+    fn test_read_jpeg_exif() {
+        use super::{is_jpeg_file, read_jpeg_exif};
+        // SOI + APP1(Exif, 大端 TIFF, IFD0 含 ImageWidth=640、ImageLength=480、Orientation=6) + SOS
+        let entry = |tag: u16, value: u16| {
+            [
+                &tag.to_be_bytes()[..],
+                &[0x00, 0x03, 0x00, 0x00, 0x00, 0x01],
+                &value.to_be_bytes(),
+                &[0x00, 0x00],
+            ]
+            .concat()
+        };
+        let tiff: Vec<u8> = [
+            &b"MM\0\x2a\0\0\0\x08"[..],
+            &[0x00, 0x03],
+            &entry(0x0100, 640),
+            &entry(0x0101, 480),
+            &entry(0x0112, 6),
+            &[0x00, 0x00, 0x00, 0x00],
+        ]
+        .concat();
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02]);
+
+        let path = env::temp_dir().join(format!("pcs-exif-{}.JPG", std::process::id()));
+        std::fs::write(&path, &jpeg).unwrap();
+        let local = path.to_str().unwrap();
+        assert!(is_jpeg_file(local));
+        let exif = read_jpeg_exif(local);
+        std::fs::write(&path, b"not a jpeg").unwrap();
+        let invalid = read_jpeg_exif(local);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(exif.unwrap()).unwrap(),
+            serde_json::json!({"orientation": 6, "width": 640, "height": 480, "recovery": 0})
+        );
+        assert!(invalid.is_none());
+    }
+
+    #[test]
+    fn test_exif_info_attribute() {
+        let task = prepare_result("/apps/a.jpg");
+        let fs_meta = slice_info("a.jpg");
+        let options = UploadOptions {
+            exif_info: Some(PcsExifInfo {
+                orientation: 6,
+                width: 640,
+                height: 480,
+                recovery: 0,
+            }),
+            ..Default::default()
+        };
+        let merge = merge_attributes(&task, &fs_meta, "[]", &options).unwrap();
+        assert_eq!(
+            merge.exif_info.as_deref(),
+            Some(r#"{"orientation":6,"width":640,"height":480,"recovery":0}"#)
+        );
+    }
+
+    #[test]
+    fn test_upload_zip_options() {
+        let task = prepare_result("/apps/a.jpg");
+        let mut fs_meta = slice_info("a.jpg");
+        // 只设置压缩程度而无法得到 zip_sign：拒绝
+        let options = UploadOptions {
+            zip_quality: Some(70),
+            ..Default::default()
+        };
+        assert!(merge_attributes(&task, &fs_meta, "[]", &options).is_err());
+        // 只设置 zip_sign：拒绝
+        let sign_only = UploadOptions {
+            zip_sign: Some("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+            ..Default::default()
+        };
+        assert!(merge_attributes(&task, &fs_meta, "[]", &sign_only).is_err());
+        // 非法的压缩程度：拒绝
+        let invalid = UploadOptions {
+            zip_quality: Some(80),
+            zip_sign: Some("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+            ..Default::default()
+        };
+        assert!(merge_attributes(&task, &fs_meta, "[]", &invalid).is_err());
+
+        // 自动使用整文件 MD5 作为 zip_sign
+        fs_meta.content_md5 = Some("0cc175b9c0f1b6a831c399e269772661".to_string());
+        let merge =
+            serde_json::to_value(merge_attributes(&task, &fs_meta, "[]", &options).unwrap())
+                .unwrap();
+        assert_eq!(merge["zip_quality"], 70);
+        assert_eq!(merge["zip_sign"], "0cc175b9c0f1b6a831c399e269772661");
+        // 显式指定的 zip_sign 优先
+        let explicit = UploadOptions {
+            zip_quality: Some(100),
+            zip_sign: Some("d41d8cd98f00b204e9800998ecf8427e".to_string()),
+            ..Default::default()
+        };
+        let merge =
+            serde_json::to_value(merge_attributes(&task, &fs_meta, "[]", &explicit).unwrap())
+                .unwrap();
+        assert_eq!(merge["zip_quality"], 100);
+        assert_eq!(merge["zip_sign"], "d41d8cd98f00b204e9800998ecf8427e");
+    }
+
+    #[test]
+    fn test_merge_r_type_matches_precreate() {
+        let fs_meta = slice_info("a.txt");
+        for (policy, expected) in [
+            (PcsUploadPolicy::Fail, 0),
+            (PcsUploadPolicy::Rename, 1),
+            (PcsUploadPolicy::NewCopy, 2),
+            (PcsUploadPolicy::Overwrite, 3),
+        ] {
+            let options = UploadOptions::from(policy);
+            let precreate = precreate_attributes("/apps/a.txt", &fs_meta, &options).unwrap();
+            assert_eq!(precreate.r_type, Some(expected));
+            // 与 precreate 中记录的 rtype 相同
+            let mut task = prepare_result("/apps/a.txt");
+            assert_eq!(task.r_type, None);
+            task.r_type = precreate.r_type;
+            let merge = merge_attributes(&task, &fs_meta, "[]", &options).unwrap();
+            assert_eq!(merge.r_type, precreate.r_type);
+        }
+    }
+
+    #[test]
+    fn test_rename_policy_not_overridden_by_revision() {
+        let task = prepare_result("/apps/a.txt");
+        let fs_meta = slice_info("a.txt");
+        for (policy, r_type) in [(PcsUploadPolicy::Rename, 1), (PcsUploadPolicy::NewCopy, 2)] {
+            let options = UploadOptions::from(policy);
+            assert!(!options.is_revision);
+            let merge =
+                serde_json::to_value(merge_attributes(&task, &fs_meta, "[]", &options).unwrap())
+                    .unwrap();
+            assert_eq!(merge["rtype"], r_type);
+            assert!(merge.get("is_revision").is_none());
+        }
+        // 覆盖上传默认保留历史版本
+        let options = UploadOptions::from(Overwrite);
+        assert!(options.is_revision);
+        let merge =
+            serde_json::to_value(merge_attributes(&task, &fs_meta, "[]", &options).unwrap())
+                .unwrap();
+        assert_eq!(merge["rtype"], 3);
+        assert_eq!(merge["is_revision"], 1);
+    }
+
+    #[test]
+    fn test_precreate_fast_mode_omits_checksums() {
+        let path = env::temp_dir().join(format!("pcs-fast-upload-{}.bin", std::process::id()));
+        std::fs::write(&path, vec![1u8; 300 * 1024]).unwrap();
+        let user = test_user();
+        let local = path.to_str().unwrap();
+        let full = get_file_block_list(&user, local, true, None, &mut |_, _| {}).unwrap();
+        let fast = get_file_block_list(&user, local, false, None, &mut |_, _| {}).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(full.block_list, fast.block_list);
+        let full_body = serde_json::to_value(
+            precreate_attributes("/apps/a.bin", &full, &UploadOptions::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(full_body["content_md5"], full.block_list[0].as_str());
+        assert_eq!(
+            full_body["slice_md5"],
+            hex::encode(Md5::digest(vec![1u8; 256 * 1024]))
+        );
+
+        let fast_body = serde_json::to_value(
+            precreate_attributes("/apps/a.bin", &fast, &UploadOptions::default()).unwrap(),
+        )
+        .unwrap();
+        assert!(fast_body.get("content_md5").is_none());
+        assert!(fast_body.get("slice_md5").is_none());
+        assert_eq!(fast_body["block_list"], full_body["block_list"]);
+    }
+
+    #[test]
+    fn test_datetime_accessors() {
+        let item: PcsFileItem = serde_json::from_value(serde_json::json!({
+            "fs_id": 1, "path": "/a.txt", "server_filename": "a.txt", "size": 1,
+            "server_mtime": 1700000000, "server_ctime": 1600000000,
+            "local_mtime": 0, "local_ctime": -1, "isdir": 0, "category": 4
+        }))
+        .unwrap();
+        let expected = chrono::Utc
+            .with_ymd_and_hms(2023, 11, 14, 22, 13, 20)
+            .unwrap();
+        assert_eq!(item.server_mtime_dt(), expected);
+        assert_eq!(item.server_mtime_local(), expected);
+        assert_eq!(item.server_ctime_dt().timestamp(), 1600000000);
+        assert_eq!(item.local_mtime_dt(), chrono::DateTime::UNIX_EPOCH);
+        assert_eq!(item.local_ctime_dt().timestamp(), -1);
+        assert_eq!(timestamp_to_utc(i64::MAX), chrono::DateTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_format_tree() {
+        let item = |path: &str, is_dir: i32| pcs_file_item(1, path, 0, is_dir);
+        let items = vec![
+            item("/apps/b", 1),
+            item("/apps/b/x.txt", 0),
+            item("/apps/b/sub", 1),
+            item("/apps/b/sub/y.txt", 0),
+            item("/apps/a.txt", 0),
+            item("/apps/c", 1),
+        ];
+        assert_eq!(
+            format_tree(&items),
+            [
+                "/apps",
+                "├── 📄 a.txt",
+                "├── 📁 b",
+                "│   ├── 📁 sub",
+                "│   │   └── 📄 y.txt",
+                "│   └── 📄 x.txt",
+                "└── 📁 c",
+            ]
+            .join("\n")
+        );
+        assert_eq!(format_tree(&[]), "");
+    }
+
+    #[test]
+    fn test_result_display() {
+        let upload: PcsFileUploadResult = serde_json::from_value(serde_json::json!({
+            "path": "/apps/a.bin", "size": 1536, "ctime": 0, "mtime": 0,
+            "md5": "abc", "fs_id": 42, "isdir": 0
+        }))
+        .unwrap();
+        assert_eq!(upload.to_string(), "/apps/a.bin（1.500 KiB，fs_id: 42）");
+
+        let quota: PcsDiskQuota = serde_json::from_value(serde_json::json!({
+            "total": 2199023255552u64, "expire": false, "used": 1073741824, "free": 0
+        }))
+        .unwrap();
+        assert_eq!(quota.to_string(), "已用 1.000 GiB / 总空间 2.000 TiB");
+        let quota: PcsDiskQuota = serde_json::from_value(serde_json::json!({
+            "total": 1024, "expire": true, "used": 0, "free": 0
+        }))
+        .unwrap();
+        assert_eq!(
+            quota.to_string(),
+            "已用 0.000 B / 总空间 1.000 KiB（7 天内有容量到期）"
+        );
+
+        let user: PcsUserInfo = serde_json::from_value(serde_json::json!({
+            "baidu_name": "baidu", "netdisk_name": "pan", "avatar_url": "", "vip_type": 2, "uk": 1
+        }))
+        .unwrap();
+        assert_eq!(user.to_string(), "baidu pan (超级会员)");
+    }
+
+    #[test]
+    fn test_download_with_link_refresh() {
+        let not_found = || AppError::new(AppErrorType::Network, "http error 404", Some(404));
+        let mut used = Vec::new();
+        let mut refreshed = 0;
+        let result = download_with_link_refresh(
+            "stale",
+            |link| {
+                used.push(link.to_string());
+                if link == "stale" {
+                    Err(not_found())
+                } else {
+                    Ok(())
+                }
+            },
+            || {
+                refreshed += 1;
+                Ok("fresh".to_string())
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(used, vec!["stale", "fresh"]);
+        assert_eq!(refreshed, 1);
+
+        // 只重试一次
+        let mut attempts = 0;
+        let result = download_with_link_refresh(
+            "stale",
+            |_| {
+                attempts += 1;
+                Err(not_found())
+            },
+            || Ok("still-stale".to_string()),
+        );
+        assert_eq!(result.unwrap_err().errno, Some(404));
+        assert_eq!(attempts, 2);
+
+        // 其他错误不重新获取下载地址
+        let result = download_with_link_refresh(
+            "link",
+            |_| Err(AppError::new(AppErrorType::Network, "timeout", None)),
+            || panic!("不应重新获取下载地址"),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_guess_content_type() {
+        assert_eq!(guess_content_type("/data/photo.jpg"), "image/jpeg");
+        assert_eq!(guess_content_type("/data/PHOTO.JPEG"), "image/jpeg");
+        assert_eq!(
+            guess_content_type("/data/a.unknown-ext"),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            guess_content_type("/data/noext"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test_delete_async_mode() {
+        // trash 等同于 delete(paths, None)：自适应模式，文件进入回收站
+        assert_eq!(delete_async_mode(None), 1);
+        assert_eq!(delete_async_mode(Some(true)), 0);
+        assert_eq!(delete_async_mode(Some(false)), 2);
+    }
+
+    #[test]
+    fn test_delete_in_batches() {
+        let paths: Vec<String> = (0..2500).map(|i| format!("/tmp/{}.txt", i)).collect();
+        let mut sizes = Vec::new();
+        let result = delete_in_batches(&paths, 1000, |batch| {
+            sizes.push(batch.len());
+            let info: Vec<_> = batch
+                .iter()
+                .map(|p| serde_json::json!({"errno": 0, "path": p}))
+                .collect();
+            Ok(serde_json::from_value(serde_json::json!({ "info": info })).unwrap())
         })
+        .unwrap();
+        assert_eq!(sizes, vec![1000, 1000, 500]);
+        assert_eq!(result.info().len(), 2500);
+        assert_eq!(result.info()[2499].path(), "/tmp/2499.txt");
     }
 
-    /// 自定义功能： 备份指定文件到应用目录下
-    /// 机制说明： 1. 如果文件小于 `FILE_MAX_SIZE` ，使用小文件上传接口，否则使用大文件上传接口
-    /// # Arguments
-    /// * `local_file` - 本地文件路径(待上传文件的绝对路径)
-    /// * `pcs_path` - 上传后使用的文件绝对路径，云盘的存储路径，需要注意的是有限制只能上传到 /apps/{app-name}/目录下，其他目录会返回 31064
-    /// # Returns
-    /// * `FileUpload` - 文件上传结果
-    /// # Errors
-    /// * `AppError` - 文件上传失败
-    pub fn backup_file(
-        &mut self,
-        local_file: &str,
-        pcs_path: &str,
-    ) -> Result<Vec<PcsFileUploadResult>, AppError> {
-        let file = File::open(local_file)?;
-        let mut rs: Vec<PcsFileUploadResult> = Vec::new();
-        if file.metadata()?.is_file() {
-            rs.push(self.upload_large_file(
-                local_file,
-                pcs_path,
-                PcsUploadPolicy::Overwrite,
-                |_| {},
-            )?)
-        } else if file.metadata()?.is_dir() {
-            let prefix = PathBuf::from(pcs_path);
-            for entry in std::fs::read_dir(local_file)? {
-                let entry = entry?;
-                if entry.file_type()?.is_file() {
-                    let mut this_file = prefix.clone();
-                    this_file.push(entry.path().strip_prefix(local_file).unwrap());
-                    rs.push(self.upload_large_file(
-                        entry.path().to_str().unwrap(),
-                        this_file.as_path().to_str().unwrap(),
-                        PcsUploadPolicy::Overwrite,
-                        |_| {},
-                    )?)
-                }
-            }
-        }
-        Ok(rs)
+    #[test]
+    fn test_poll_file_task_until_done() {
+        let status = |s: &str| -> PcsFileTaskStatus {
+            serde_json::from_value(serde_json::json!({
+                "status": s, "list": [{"from": "/a.txt", "errno": 0}]
+            }))
+            .unwrap()
+        };
+        let mut calls = 0;
+        let result = poll_file_task(
+            || {
+                calls += 1;
+                Ok(status(if calls < 3 { "running" } else { "success" }))
+            },
+            Duration::from_secs(5),
+            Duration::from_millis(1),
+        )
+        .unwrap();
+        assert_eq!(calls, 3);
+        assert!(result.is_success());
+        assert_eq!(result.list()[0].from().as_deref(), Some("/a.txt"));
+
+        let result = poll_file_task(
+            || Ok(status("pending")),
+            Duration::from_millis(20),
+            Duration::from_millis(5),
+        );
+        assert!(result.is_err());
+
+        let submitted: PcsFileTaskOperationResult =
+            serde_json::from_value(serde_json::json!({"errno": 0, "info": [], "taskid": 123}))
+                .unwrap();
+        assert_eq!(submitted.task_id().as_deref(), Some("123"));
     }
-}
 
-/// 进度回调类型别名
-pub type ProgressCallback = Arc<Mutex<dyn FnMut(ProgressInfo) + Send>>;
+    #[test]
+    fn test_file_task_status_from_item_errno() {
+        // 任务整体为 success，但其中一个文件失败
+        let result = poll_file_task(
+            || {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "status": "success",
+                    "list": [{"from": "/a.txt", "errno": 0}, {"from": "/b.txt", "errno": -9}]
+                }))
+                .unwrap())
+            },
+            Duration::from_secs(1),
+            Duration::from_millis(1),
+        )
+        .unwrap();
+        assert!(!result.is_success());
+        assert_eq!(result.status(), "failed");
+        let failures = result.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].from().as_deref(), Some("/b.txt"));
 
-#[cfg(test)]
-mod test {
-    use crate::baidu_pcs_sdk::pcs::PcsUploadPolicy::Overwrite;
-    use crate::baidu_pcs_sdk::pcs::{get_file_block_list, BaiduPcsClient, ProgressInfo};
-    use crate::baidu_pcs_sdk::{BaiduPcsApp, PcsFileSlicePrepareResult};
-    use std::env;
-    const BAIDU_PCS_APP: BaiduPcsApp = BaiduPcsApp {
-        app_key: env!("BAIDU_PCS_APP_KEY"),
-        app_secret: env!("BAIDU_PCS_APP_SECRET"),
-        app_name: env!("BAIDU_PCS_APP_NAME"),
-        app_id: None,
-    };
+        // 同步完成（未返回任务ID）时以提交结果中的 errno 为准
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        let submitted: PcsFileTaskOperationResult = serde_json::from_value(serde_json::json!({
+            "info": [{"errno": 0, "path": "/a.txt"}, {"errno": 12, "path": "/b.txt"}]
+        }))
+        .unwrap();
+        let result = client
+            .wait_file_task(submitted, Duration::from_secs(1))
+            .unwrap();
+        assert!(!result.is_success());
+        assert_eq!(result.failures()[0].from().as_deref(), Some("/b.txt"));
+
+        let submitted: PcsFileTaskOperationResult =
+            serde_json::from_value(serde_json::json!({"info": [{"errno": 0, "path": "/a.txt"}]}))
+                .unwrap();
+        let result = client
+            .wait_file_task(submitted, Duration::from_secs(1))
+            .unwrap();
+        assert!(result.is_success());
+    }
+
+    #[test]
+    fn test_remote_file_reader_range() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let url = spawn_range_server(data.clone());
+        let client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        let mut reader =
+            RemoteFileReader::new(&client, format!("{}&access_token=token", url), 1000)
+                .with_buffer_size(64);
+
+        let mut head = [0u8; 100];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(&head[..], &data[..100]);
+
+        reader.seek(SeekFrom::Start(900)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, &data[900..]);
+
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        let mut last = [0u8; 10];
+        reader.read_exact(&mut last).unwrap();
+        assert_eq!(&last[..], &data[990..]);
+    }
 
     #[test]
     fn test_get_user_info() {
@@ -1686,7 +5676,7 @@ mod test {
         );
         let result = client
             .delete(
-                &vec![
+                &[
                     "/apps/stock-trunk/testsa".to_string(),
                     "/apps/stock-trunk/testsa2".to_string(),
                 ],
@@ -1711,6 +5701,597 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_list_dir_web_thumbs_deserialize() {
+        let text = r#"{"errno":0,"guid":0,"list":[
+            {"fs_id":1,"path":"/a.jpg","server_filename":"a.jpg","size":10,"server_mtime":1,"server_ctime":1,
+             "local_mtime":1,"local_ctime":1,"isdir":0,"category":3,"md5":"x",
+             "thumbs":{"icon":"i","url1":"u1","url2":"u2","url3":"u3"}},
+            {"fs_id":2,"path":"/b.jpg","server_filename":"b.jpg","size":10,"server_mtime":1,"server_ctime":1,
+             "local_mtime":1,"local_ctime":1,"isdir":0,"category":3,"thumbs":["t1","t2","t3"]},
+            {"fs_id":3,"path":"/c","server_filename":"c","size":0,"server_mtime":1,"server_ctime":1,
+             "local_mtime":1,"local_ctime":1,"isdir":1,"category":6,"dir_empty":1}
+        ]}"#;
+        let result: PcsFileListResult = serde_json::from_str(text).unwrap();
+        let list = result.list();
+        assert_eq!(
+            list[0].thumbs().as_deref(),
+            Some(&["u1".to_string(), "u2".to_string(), "u3".to_string()][..])
+        );
+        assert_eq!(list[1].thumbs().as_ref().map(|t| t.len()), Some(3));
+        assert!(list[2].thumbs().is_none());
+        assert_eq!(*list[2].dir_empty(), Some(1));
+    }
+
+    #[test]
+    fn test_resolve_remote_path() {
+        let cwd = "/apps/demo/photos";
+        assert_eq!(resolve_remote_path(cwd, "/abs/x.txt"), "/abs/x.txt");
+        assert_eq!(resolve_remote_path(cwd, "a.txt"), "/apps/demo/photos/a.txt");
+        assert_eq!(resolve_remote_path(cwd, "./y"), "/apps/demo/photos/y");
+        assert_eq!(resolve_remote_path(cwd, "../x"), "/apps/demo/x");
+        assert_eq!(
+            resolve_remote_path(cwd, "a/./b/../c/"),
+            "/apps/demo/photos/a/c"
+        );
+        assert_eq!(resolve_remote_path(cwd, ""), cwd);
+        assert_eq!(resolve_remote_path(cwd, "../../../../.."), "/");
+        assert_eq!(resolve_remote_path("/", "x"), "/x");
+
+        let mut client = BaiduPcsClient::new("token", BAIDU_PCS_APP);
+        assert_eq!(client.cwd(), "/");
+        client.set_cwd("/apps/demo");
+        client.set_cwd("photos/2024");
+        assert_eq!(client.cwd(), "/apps/demo/photos/2024");
+        client.set_cwd("..");
+        assert_eq!(client.cwd(), "/apps/demo/photos");
+        assert_eq!(client.resolve_path("../a.txt"), "/apps/demo/a.txt");
+    }
+
+    #[test]
+    fn test_to_remote_path() {
+        let joined = Path::new("/backup").join("photos").join("a.jpg");
+        assert_eq!(to_remote_path(&joined), "/backup/photos/a.jpg");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_to_remote_path_uses_forward_slashes_on_windows() {
+        let joined = Path::new("/backup").join("photos\\2024").join("a.jpg");
+        let remote = to_remote_path(&joined);
+        assert_eq!(remote, "/backup/photos/2024/a.jpg");
+        assert!(!remote.contains('\\'));
+    }
+
+    #[test]
+    fn test_appdata_path_and_listing_deserialize() {
+        assert_eq!(appdata_path(""), "/_pcs_.appdata/xpan/");
+        assert_eq!(appdata_path("/"), "/_pcs_.appdata/xpan/");
+        assert_eq!(appdata_path("state"), "/_pcs_.appdata/xpan/state");
+        assert_eq!(appdata_path("/state/db/"), "/_pcs_.appdata/xpan/state/db");
+        assert_eq!(
+            appdata_path("/_pcs_.appdata/xpan/state"),
+            "/_pcs_.appdata/xpan/state"
+        );
+
+        let text = r#"{"errno":0,"guid":0,"guid_info":"","request_id":8889,"list":[
+            {"fs_id":101,"path":"/_pcs_.appdata/xpan/state","server_filename":"state","size":0,
+             "server_mtime":1700000000,"server_ctime":1700000000,"local_mtime":1700000000,
+             "local_ctime":1700000000,"isdir":1,"category":6,"privacy":0,"unlist":0,
+             "oper_id":0,"share":0,"empty":0},
+            {"fs_id":102,"path":"/_pcs_.appdata/xpan/config.json","server_filename":"config.json",
+             "size":128,"server_mtime":1700000001,"server_ctime":1700000001,
+             "local_mtime":1700000001,"local_ctime":1700000001,"isdir":0,"category":6,
+             "md5":"0b2f7b5e3c1e4d0f8a9b6c7d5e4f3a2b","privacy":0,"unlist":0,"oper_id":0,"share":0}
+        ]}"#;
+        let result: PcsFileListResult = serde_json::from_str(text).unwrap();
+        let list = result.list();
+        assert_eq!(list.len(), 2);
+        assert!(list
+            .iter()
+            .all(|item| item.path().starts_with(&format!("{APPDATA_ROOT}/"))));
+        assert_eq!(*list[0].is_dir(), 1);
+        assert_eq!(list[1].server_filename(), "config.json");
+        assert_eq!(*list[1].size(), 128);
+    }
+
+    #[test]
+    fn test_api_error_errno_as_string() {
+        let e: PcsApiError = serde_json::from_str(r#"{"errno":"31034"}"#).unwrap();
+        assert_eq!(e.errno, 31034);
+        let e: PcsApiError = serde_json::from_str(r#"{"error_code":-6,"error_msg":"x"}"#).unwrap();
+        assert_eq!(e.errno, -6);
+        assert!(serde_json::from_str::<PcsApiError>(r#"{"errno":"abc"}"#).is_err());
+    }
+
+    #[test]
+    fn test_upload_slices_returns_md5_per_slice() {
+        let mut parts = Vec::new();
+        let md5s = upload_slices(3, 10, 4, None, |p| {
+            parts.push((p.current_part, p.uploaded_bytes, p.current_part_bytes));
+            Ok(format!("md5-{}", p.current_part))
+        })
+        .unwrap();
+        assert_eq!(md5s.len(), 3);
+        assert_eq!(md5s, vec!["md5-0", "md5-1", "md5-2"]);
+        assert_eq!(parts, vec![(0, 0, 4), (1, 4, 4), (2, 8, 2)]);
+
+        let result = upload_slices(3, 10, 4, None, |p| {
+            if p.current_part == 1 {
+                Err(AppError::new(AppErrorType::Network, "mock", None))
+            } else {
+                Ok(String::new())
+            }
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_custom_headers_sent() {
+        assert!(parse_headers(&[("bad name", "v")]).is_err());
+        assert!(parse_headers(&[("X-Token", "line\nbreak")]).is_err());
+        let headers = parse_headers(&[("X-Proxy-Token", "secret"), ("Cookie", "a=1")]).unwrap();
+        assert_eq!(headers.len(), 2);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let url = spawn_handler(move |request, stream| {
+            let _ = tx.lock().unwrap().send(request.to_string());
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok");
+        });
+
+        let client = BaiduPcsClient::new_with_headers("token", BAIDU_PCS_APP, None, headers);
+        let local = env::temp_dir().join(format!("pcs-test-headers-{}", std::process::id()));
+        client
+            .download(url.as_str(), local.to_str().unwrap(), None::<fn(u64, u64)>)
+            .unwrap();
+        let request = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(request.contains("x-proxy-token: secret"));
+        assert!(request.contains("cookie: a=1"));
+        assert!(request.contains("user-agent: pan.baidu.com"));
+        std::fs::remove_file(&local).unwrap();
+    }
+
+    #[test]
+    fn test_pause_control_blocks_until_resumed() {
+        let pause = PauseControl::new();
+        assert!(!pause.is_paused());
+        pause.pause();
+        let passed = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (pause, passed) = (pause.clone(), passed.clone());
+            std::thread::spawn(move || {
+                pause.wait_while_paused();
+                passed.store(true, Ordering::SeqCst);
+            })
+        };
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(!passed.load(Ordering::SeqCst));
+        pause.resume();
+        handle.join().unwrap();
+        assert!(passed.load(Ordering::SeqCst));
+        assert_eq!(pause, pause.clone());
+        assert_ne!(pause, PauseControl::new());
+    }
+
+    #[test]
+    fn test_remap_app_path() {
+        let client = BaiduPcsClient::new("", BAIDU_PCS_APP);
+        let current = client.get_apps_path().to_string_lossy().to_string();
+        assert_eq!(
+            client.remap_app_path("/apps/old/a/b.txt", "old"),
+            format!("{}/a/b.txt", current)
+        );
+        assert_eq!(client.remap_app_path("/apps/old", "old"), current);
+        // 仅前缀相同的其他应用目录、非应用目录不做映射
+        assert_eq!(
+            client.remap_app_path("/apps/older/a.txt", "old"),
+            "/apps/older/a.txt"
+        );
+        assert_eq!(client.remap_app_path("/data/a.txt", "old"), "/data/a.txt");
+    }
+
+    #[test]
+    fn test_with_permit_limits_concurrency() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let limiter = Arc::new(Semaphore::new(1));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let (limiter, in_flight, max_seen) =
+                    (limiter.clone(), in_flight.clone(), max_seen.clone());
+                runtime.spawn(async move {
+                    with_permit(&limiter, async {
+                        let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_seen.fetch_max(n, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                    })
+                    .await
+                })
+            })
+            .collect();
+        runtime.block_on(async {
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        });
+        assert_eq!(max_seen.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_list_dir_pages() {
+        let item = |id: u64| pcs_file_item(id, format!("/d/{}", id).as_str(), 1, 0);
+        // 条目数恰好是整页时，需要再请求一页才能确认已取完
+        let mut starts = Vec::new();
+        let items = list_dir_pages(|start| {
+            starts.push(start);
+            let end = (start + LIST_DIR_PAGE_SIZE).min(2000);
+            Ok((start..end).map(item).collect())
+        })
+        .unwrap();
+        assert_eq!(items.len(), 2000);
+        assert_eq!(starts, vec![0, 1000, 2000]);
+
+        let err = list_dir_pages(|start| match start {
+            0 => Ok((0..LIST_DIR_PAGE_SIZE).map(item).collect()),
+            _ => Err(AppError::new(AppErrorType::Network, "timeout", None)),
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_paginate_stops_after_error() {
+        let mut pages = Vec::new();
+        let items: Vec<Result<i32, AppError>> = paginate(|page| {
+            pages.push(page);
+            match page {
+                1 => Ok((vec![1, 2], true)),
+                _ => Err(AppError::new(AppErrorType::Network, "timeout", None)),
+            }
+        })
+        .collect();
+        assert_eq!(items.len(), 3);
+        assert!(items[2].is_err());
+        assert_eq!(pages, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_token_refresh_threshold() {
+        let lower = TOKEN_REFRESH_THRESHOLD_SECS - TOKEN_REFRESH_JITTER_SECS;
+        let upper = TOKEN_REFRESH_THRESHOLD_SECS + TOKEN_REFRESH_JITTER_SECS;
+        // 同一进程内多次调用结果一致，不会出现前一次判断无需刷新、后一次又需要刷新
+        let threshold = token_refresh_threshold();
+        assert!((lower..=upper).contains(&threshold));
+        assert!((0..100).all(|_| token_refresh_threshold() == threshold));
+        // 同一种子取值固定，不同种子（主机名）的取值分散在抖动范围内
+        assert_eq!(
+            token_refresh_threshold_for(b"host-a"),
+            token_refresh_threshold_for(b"host-a")
+        );
+        let samples: Vec<i64> = (0..1000)
+            .map(|i| token_refresh_threshold_for(format!("host-{}", i).as_bytes()))
+            .collect();
+        assert!(samples.iter().all(|t| (lower..=upper).contains(t)));
+        assert!(samples.iter().any(|t| *t != samples[0]));
+
+        // 剩余 30 天无需刷新，剩余 1 天或超出抖动范围的 6 天则需要刷新
+        let token = PcsAccessToken::new("a", 30 * 24 * 3600, "r", "basic");
+        assert!(!token.is_need_refresh());
+        let token = PcsAccessToken::new("a", 24 * 3600, "r", "basic");
+        assert!(token.is_need_refresh());
+        let token = PcsAccessToken::new("a", (lower - 3600) as u32, "r", "basic");
+        assert!(token.is_need_refresh());
+    }
+
+    #[test]
+    fn test_backup_plan() {
+        const MB: u64 = 1024 * 1024;
+        // 4MB 分片：0B -> 0 片，1B -> 1 片，4MB -> 1 片，4MB+1 -> 2 片，10MB -> 3 片
+        let sizes = [0, 1, 4 * MB, 4 * MB + 1, 10 * MB];
+        let plan = BackupPlan::new(4 * MB, sizes);
+        assert_eq!(*plan.files(), 5);
+        assert_eq!(*plan.total_bytes(), sizes.iter().sum::<u64>());
+        assert_eq!(*plan.precreate_calls(), 5);
+        assert_eq!(*plan.locate_calls(), 5);
+        assert_eq!(*plan.merge_calls(), 5);
+        assert_eq!(*plan.slice_calls(), 7);
+        assert_eq!(plan.total_calls(), 5 * 3 + 7);
+
+        // 分片越大，分片上传次数越少
+        let plan = BackupPlan::new(32 * MB, sizes);
+        assert_eq!(*plan.slice_calls(), 4);
+        assert_eq!(BackupPlan::new(4 * MB, []).total_calls(), 0);
+    }
+
+    #[test]
+    fn test_account_summary() {
+        let user: PcsUserInfo = serde_json::from_str(
+            r#"{"baidu_name":"b","netdisk_name":"n","avatar_url":"","vip_type":2,"uk":42}"#,
+        )
+        .unwrap();
+        let quota: PcsDiskQuota =
+            serde_json::from_str(r#"{"total":1000,"expire":false,"used":250,"free":0}"#).unwrap();
+        let summary = AccountSummary::new(user.clone(), &quota);
+        assert_eq!(*summary.user().uk(), 42);
+        assert_eq!(summary.vip_type_name(), "超级会员");
+        assert_eq!(*summary.available(), 750);
+        assert_eq!(*summary.used_percent(), 25.0);
+        assert_eq!(*summary.available_percent(), 75.0);
+
+        // 总空间为 0 或超额使用时百分比不越界
+        let empty: PcsDiskQuota =
+            serde_json::from_str(r#"{"total":0,"expire":false,"used":0,"free":0}"#).unwrap();
+        let summary = AccountSummary::new(user.clone(), &empty);
+        assert_eq!(*summary.used_percent(), 0.0);
+        assert_eq!(*summary.available_percent(), 0.0);
+        let over: PcsDiskQuota =
+            serde_json::from_str(r#"{"total":100,"expire":true,"used":120,"free":0}"#).unwrap();
+        let summary = AccountSummary::new(user, &over);
+        assert_eq!(*summary.available(), 0);
+        assert_eq!(*summary.used_percent(), 100.0);
+        assert!(*summary.expire());
+    }
+
+    #[test]
+    fn test_server_time_offset() {
+        let server: UploadServerResult = serde_json::from_str(
+            r#"{"client_ip":"1.2.3.4","host":"d.pcs.baidu.com","server_time":1700000600,
+            "servers":[],"bak_servers":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(server.server_time_offset_at(1700000000), 600);
+        assert_eq!(server.server_time_offset_at(1700000900), -300);
+        assert_eq!(
+            clock_skew(server.server_time_offset_at(1700000000)),
+            Some(600)
+        );
+        assert_eq!(clock_skew(server.server_time_offset_at(1700000900)), None);
+        assert_eq!(clock_skew(-301), Some(-301));
+    }
+
+    #[test]
+    fn test_upload_server_failover() {
+        let server: UploadServerResult = serde_json::from_str(
+            r#"{"client_ip":"1.2.3.4","host":"d.pcs.baidu.com","server_time":1,
+            "servers":[{"server":"https://c1.pcs.baidu.com"},{"server":"https://c2.pcs.baidu.com"}],
+            "bak_servers":[{"server":"https://c2.pcs.baidu.com"},{"server":"https://bak.pcs.baidu.com"}]}"#,
+        )
+        .unwrap();
+        let servers = upload_server_candidates(&server, "https://d.pcs.baidu.com");
+        assert_eq!(
+            servers,
+            vec![
+                "https://c1.pcs.baidu.com",
+                "https://c2.pcs.baidu.com",
+                "https://bak.pcs.baidu.com"
+            ]
+        );
+
+        // 第一个服务器网络失败，第二个成功
+        let mut tried = Vec::new();
+        let result = with_server_failover(&servers, |s| {
+            tried.push(s.to_string());
+            if s.contains("c1") {
+                Err(AppError::new(
+                    AppErrorType::Network,
+                    "connection refused",
+                    None,
+                ))
+            } else {
+                Ok(format!("md5 from {}", s))
+            }
+        });
+        assert_eq!(result.unwrap(), "md5 from https://c2.pcs.baidu.com");
+        assert_eq!(tried.len(), 2);
+
+        // 服务端返回的错误不切换服务器
+        let mut calls = 0;
+        let result: Result<String, AppError> = with_server_failover(&servers, |_| {
+            calls += 1;
+            Err(AppError::new(AppErrorType::Server, "31363", Some(31363)))
+        });
+        assert_eq!(result.unwrap_err().error_type, AppErrorType::Server);
+        assert_eq!(calls, 1);
+
+        // 全部失败时返回最后一个网络错误
+        let result: Result<String, AppError> = with_server_failover(&servers, |s| {
+            Err(AppError::new(AppErrorType::Network, s, None))
+        });
+        assert!(String::from(result.unwrap_err()).contains("bak.pcs.baidu.com"));
+    }
+
+    /// 记录 warn 级别日志，用于断言回退行为已输出日志
+    struct WarnCapture(Mutex<Vec<String>>);
+
+    impl log::Log for WarnCapture {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static WARN_CAPTURE: WarnCapture = WarnCapture(Mutex::new(Vec::new()));
+
+    #[test]
+    fn test_upload_server_empty_fallback() {
+        let _ = log::set_logger(&WARN_CAPTURE);
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let server: UploadServerResult = serde_json::from_str(
+            r#"{"client_ip":"1.2.3.4","host":"d.pcs.baidu.com","server_time":1,
+            "servers":[],"bak_servers":[]}"#,
+        )
+        .unwrap();
+        let servers = upload_server_candidates(&server, "https://d.pcs.baidu.com");
+        assert_eq!(servers, vec!["https://d.pcs.baidu.com"]);
+        assert!(WARN_CAPTURE
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|m| m.contains("改用默认上传服务器 https://d.pcs.baidu.com")));
+
+        // 默认服务器也不可达时给出明确的错误
+        let e = with_server_failover::<String, _>(&servers, |_| {
+            Err(AppError::new(AppErrorType::Network, "dns error", None))
+        })
+        .map_err(|e| default_server_unreachable(e, "https://d.pcs.baidu.com"))
+        .unwrap_err();
+        assert_eq!(e.error_type, AppErrorType::Network);
+        assert!(String::from(e).contains("默认上传服务器 https://d.pcs.baidu.com 不可达"));
+    }
+
+    #[test]
+    fn test_token_validity() {
+        let expired: AppError = serde_json::from_str::<PcsApiError>(r#"{"errno":111}"#)
+            .unwrap()
+            .into();
+        assert!(!token_validity::<()>(Err(expired)).unwrap());
+        assert!(token_validity(Ok(())).unwrap());
+        let network = AppError::new(AppErrorType::Network, "connection refused", None);
+        let e = token_validity::<()>(Err(network)).unwrap_err();
+        assert_eq!(e.error_type, AppErrorType::Network);
+    }
+
+    #[test]
+    fn test_api_error_unauthorized() {
+        for (text, errno) in [
+            (r#"{"errno":111}"#, 111),
+            (r#"{"errno":-6}"#, -6),
+            (r#"{"errno":6}"#, 6),
+        ] {
+            let e: AppError = serde_json::from_str::<PcsApiError>(text).unwrap().into();
+            assert_eq!(e.error_type, AppErrorType::Unauthorized);
+            assert_eq!(e.errno, Some(errno));
+        }
+        let e: AppError = serde_json::from_str::<PcsApiError>(r#"{"errno":-9}"#)
+            .unwrap()
+            .into();
+        assert_eq!(e.error_type, AppErrorType::Server);
+    }
+
+    #[test]
+    fn test_oauth_error_mapping() {
+        let oauth_error = |error: &str| -> AppError {
+            serde_json::from_value::<PcsError>(serde_json::json!({
+                "error": error, "error_description": "desc"
+            }))
+            .unwrap()
+            .into()
+        };
+        let e = oauth_error("invalid_grant");
+        assert_eq!(e.error_type, AppErrorType::Unauthorized);
+        assert_eq!(
+            e.message,
+            "授权凭据无效或已过期，请重新认证（invalid_grant: desc）"
+        );
+        assert_eq!(e.to_string(), e.message);
+        let e = oauth_error("authorization_pending");
+        assert_eq!(e.error_type, AppErrorType::Client);
+        assert!(e.message.contains("用户尚未完成授权"));
+        assert_eq!(
+            oauth_error("expired_token").error_type,
+            AppErrorType::Unauthorized
+        );
+        // 未知错误保持原有格式
+        let e = oauth_error("invalid_client");
+        assert_eq!(e.error_type, AppErrorType::Server);
+        assert_eq!(e.message, "invalid_client:desc");
+    }
+
+    #[test]
+    fn test_from_str_or_int_scalars() {
+        let task_id = |raw: &str| {
+            let text = format!(r#"{{"info":[],"task_id":{raw}}}"#);
+            serde_json::from_str::<PcsFileTaskOperationResult>(&text)
+                .unwrap()
+                .task_id()
+                .clone()
+        };
+        assert_eq!(task_id(r#""abc""#).as_deref(), Some("abc"));
+        assert_eq!(task_id("123456789012").as_deref(), Some("123456789012"));
+        assert_eq!(task_id("1.5").as_deref(), Some("1.5"));
+        assert_eq!(task_id("true").as_deref(), Some("true"));
+        assert_eq!(task_id("false").as_deref(), Some("false"));
+        assert_eq!(task_id("null"), None);
+        assert_eq!(task_id(r#"{"id":"1"}"#), None);
+        assert_eq!(task_id("[1,2]"), None);
+    }
+
+    #[test]
+    fn test_category_counts_deserialize() {
+        let text = r#"{"errno":0,"request_id":8542510413411418911,
+            "info":{"3":{"total":120,"size":536870912,"count":120}}}"#;
+        let mut counts: CategoryCounts = if_rest_ok_then_get_else_err(text.to_string()).unwrap();
+        let images = counts.category(3).unwrap();
+        assert_eq!((*images.count(), *images.size()), (120, 536870912));
+        assert!(counts.category(1).is_none());
+
+        let videos: CategoryCounts =
+            serde_json::from_str(r#"{"errno":0,"info":{"1":{"total":2,"size":10,"count":2}}}"#)
+                .unwrap();
+        counts.merge(videos);
+        assert_eq!(
+            counts.info().keys().copied().collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn test_file_meta_info_fs_id_deserialize() {
+        let info: PcsFileMetaInfo = serde_json::from_str(
+            r#"{"fs_id":414244021542671,"category":3,"filename":"a.jpg","isdir":0,
+            "path":"/a.jpg","size":10,"server_ctime":1,"server_mtime":1,
+            "local_ctime":1,"local_mtime":1,"md5":"x"}"#,
+        )
+        .unwrap();
+        assert_eq!(*info.fs_id(), 414244021542671);
+    }
+
+    #[test]
+    fn test_index_by_fs_id() {
+        let text = r#"{"errno":0,"request_id":"1","list":[
+            {"fs_id":11,"category":4,"filename":"a.txt","isdir":0,"path":"/a.txt","size":3,
+             "server_ctime":1,"server_mtime":1,"local_ctime":1,"local_mtime":1,"md5":"x"},
+            {"fs_id":22,"category":6,"filename":"b","isdir":1,"path":"/b","size":0,
+             "server_ctime":1,"server_mtime":1,"local_ctime":1,"local_mtime":1}
+        ]}"#;
+        let result: PcsFileMetaResult = serde_json::from_str(text).unwrap();
+        let map = index_by_fs_id(result.list);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&11].path().as_deref(), Some("/a.txt"));
+        assert_eq!(map[&22].path().as_deref(), Some("/b"));
+        // 请求了但服务端未返回的 ID 不在结果中
+        assert!(!map.contains_key(&33));
+    }
+
+    #[test]
+    fn test_download_thumbnail_invalid_index() {
+        let client = BaiduPcsClient::new("", BAIDU_PCS_APP);
+        let item: PcsFileItem = serde_json::from_str(
+            r#"{"fs_id":1,"path":"/a.jpg","server_filename":"a.jpg","size":10,"server_mtime":1,
+            "server_ctime":1,"local_mtime":1,"local_ctime":1,"isdir":0,"category":3,
+            "thumbs":["t1","t2","t3"]}"#,
+        )
+        .unwrap();
+        let err = client
+            .download_thumbnail(&item, 3, "/tmp/never-written.jpg")
+            .unwrap_err();
+        assert_eq!(err.error_type, AppErrorType::Client);
+        assert!(err.message.contains("0..3"));
+        assert_eq!(BaiduPcsClient::select_thumbnail(&item, 2).unwrap(), "t3");
+    }
+
     #[test]
     fn test_upload_single_file() {
         let client = BaiduPcsClient::new(
@@ -1726,6 +6307,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_resolve_single_upload_path_enforce_apps_dir() {
+        let mut client = BaiduPcsClient::new("", BAIDU_PCS_APP);
+        assert!(client.is_enforce_apps_dir());
+        assert_eq!(
+            client.resolve_single_upload_path("/backup/x.txt"),
+            format!("/apps/{}/backup/x.txt", BAIDU_PCS_APP.app_name)
+        );
+        let in_apps = format!("/apps/{}/x.txt", BAIDU_PCS_APP.app_name);
+        assert_eq!(client.resolve_single_upload_path(&in_apps), in_apps);
+
+        client.set_enforce_apps_dir(false);
+        assert_eq!(
+            client.resolve_single_upload_path("/backup/x.txt"),
+            "/backup/x.txt"
+        );
+    }
+
     #[test]
     fn test_prepare_file_upload() {
         let client = BaiduPcsClient::new(
@@ -1735,7 +6334,8 @@ mod test {
         let result = client.file_slice_prepare(
             format!("{}/back.tar.gz", env::var("HOME").unwrap()).as_str(),
             "/apps/stock-trunk/backup/text.rar",
-            &Overwrite,
+            &UploadOptions::from(Overwrite),
+            &mut |_, _| {},
         );
         if result.is_err() {
             println!("error: {:?}", result.err().unwrap());
@@ -1754,6 +6354,9 @@ mod test {
         let task_file_meta = get_file_block_list(
             &client.get_user_info().unwrap(),
             format!("{}/back.tar.gz", env::var("HOME").unwrap()).as_str(),
+            true,
+            None,
+            &mut |_, _| {},
         )
         .unwrap();
         let upload_task = PcsFileSlicePrepareResult {
@@ -1761,6 +6364,7 @@ mod test {
             upload_id: "P1-MTAuNDEuMTUuMTU6MTcwNjcyMjczNjo4NzkxMDIxNjg3NzQ1ODUyNTY5".to_string(),
             return_type: 1,
             block_list: vec![0, 1, 2],
+            r_type: Some(3),
         };
         let s = client
             .get_upload_server(&upload_task)
@@ -1774,7 +6378,8 @@ mod test {
                 current_part: 1,
                 current_part_bytes: client.get_user_info().unwrap().get_user_block_slice_size(),
             },
-            &s,
+            &upload_server_candidates(&s, client.file_server_url()),
+            "application/octet-stream",
             None,
         );
         if result.is_err() {
@@ -1809,6 +6414,9 @@ mod test {
         let result = get_file_block_list(
             &client.get_user_info().unwrap(),
             "test/uploadtestdata/a.txt",
+            true,
+            None,
+            &mut |_, _| {},
         );
         if result.is_err() {
             println!("error: {:?}", result.err().unwrap());
@@ -1818,7 +6426,7 @@ mod test {
             println!("result: {:?}", pcs_file_slice_info);
             assert_eq!(
                 "d05f84cf5340d1ef0c5f6d6eb8ce13b8",
-                pcs_file_slice_info.content_md5.as_str()
+                pcs_file_slice_info.content_md5.as_deref().unwrap()
             );
             assert_eq!(271, pcs_file_slice_info.size);
         }
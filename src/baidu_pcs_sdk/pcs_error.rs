@@ -1,4 +1,4 @@
-use crate::baidu_pcs_sdk::AppErrorType::{Client, Network, Server};
+use crate::baidu_pcs_sdk::AppErrorType::{Client, Network, Server, Unauthorized};
 use crate::baidu_pcs_sdk::{AppError, AppErrorType, PcsApiError, PcsError};
 use std::fmt::{Display, Error};
 
@@ -24,7 +24,7 @@ use std::fmt::{Display, Error};
 // -1	权益已过期	权益已过期
 
 pub(crate) fn translate_error_to_string(error: AppError) -> String {
-    if error.error_type == Server {
+    if matches!(error.error_type, Server | Unauthorized) {
         if let Some(errno) = error.errno {
             return try_translate_errno(&error.message, errno);
         }
@@ -54,13 +54,34 @@ impl From<Error> for AppError {
     }
 }
 
+/// 授权接口（OAuth）常见错误码对应的错误类型与提示
+/// `authorization_pending`、`slow_down` 表示设备码授权仍在等待用户操作，归为可重试的 `Client`；
+/// 凭据无效、过期或用户拒绝授权时需要重新认证，归为 `Unauthorized`
+fn translate_oauth_error(error: &str) -> Option<(AppErrorType, &'static str)> {
+    match error {
+        "authorization_pending" => Some((Client, "用户尚未完成授权")),
+        "slow_down" => Some((Client, "查询授权结果过于频繁")),
+        "expired_token" => Some((Unauthorized, "设备码已过期，请重新获取验证码")),
+        "invalid_grant" => Some((Unauthorized, "授权凭据无效或已过期，请重新认证")),
+        "access_denied" => Some((Unauthorized, "用户拒绝了授权")),
+        _ => None,
+    }
+}
+
 impl From<PcsError> for AppError {
     fn from(e: PcsError) -> Self {
-        AppError::new(
-            Server,
-            format!("{}:{}", e.error, e.error_description).as_str(),
-            None,
-        )
+        match translate_oauth_error(e.error.as_str()) {
+            Some((error_type, hint)) => AppError::new(
+                error_type,
+                format!("{}（{}: {}）", hint, e.error, e.error_description).as_str(),
+                None,
+            ),
+            None => AppError::new(
+                Server,
+                format!("{}:{}", e.error, e.error_description).as_str(),
+                None,
+            ),
+        }
     }
 }
 
@@ -69,7 +90,9 @@ impl Display for AppError {
         match self.error_type {
             Client => write!(f, "Client Error: {}", self.message),
             Network => write!(f, "Network Error: {}", self.message),
-            Server => try_translate_errno(&self.message, self.errno.unwrap_or(0)).fmt(f),
+            Server | Unauthorized => {
+                try_translate_errno(&self.message, self.errno.unwrap_or(0)).fmt(f)
+            }
             _ => write!(f, "Unknown Error: {}", self.message),
         }
     }
@@ -114,8 +137,13 @@ impl From<PcsApiError> for AppError {
         if e.errno == i32::MIN {
             AppError::new(Server, e.raw.as_str(), None)
         } else {
+            // 111 access token 失效，-6 身份验证失败，6 不允许接入用户数据
+            let error_type = match e.errno {
+                111 | -6 | 6 => Unauthorized,
+                _ => Server,
+            };
             AppError::new(
-                Server,
+                error_type,
                 e.err_msg.unwrap_or(e.raw).to_string().as_str(),
                 Some(e.errno as i64),
             )
@@ -1,4 +1,4 @@
-use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(name = "baidu-pan-cli-rs")]
@@ -14,13 +14,59 @@ pub struct CommandLineArgs {
     /// 指定用于解析域名的 DNS 服务器地址（支持逗号分隔多个，格式如 8.8.8.8 或 8.8.8.8:53）
     #[arg(long, default_value = None)]
     pub dns: Option<String>,
+
+    /// 日志、备份断点等运行状态文件的存放目录（默认为配置文件所在目录）
+    #[arg(long = "state-dir", default_value = None)]
+    pub state_dir: Option<String>,
+
+    /// 日志输出目标：file 写入状态目录下的日志文件，stderr 输出到标准错误，both 同时输出
+    #[arg(long = "log", value_enum, default_value_t = LogTarget::File)]
+    pub log: LogTarget,
+
+    /// 静默模式：不显示进度条，仅输出每个操作的结果（非终端环境下自动启用）
+    #[arg(short = 'q', long = "quiet", global = true, action = ArgAction::SetTrue)]
+    pub quiet: bool,
+
+    /// 非交互模式：不读取标准输入，缺少凭据时直接报错退出（适合 CI/容器，可配合 BAIDU_PCS_ACCESS_TOKEN 使用）
+    #[arg(long = "non-interactive", global = true, action = ArgAction::SetTrue)]
+    pub non_interactive: bool,
+
+    /// 日志文件保留天数，启动时自动删除更早的日志文件
+    #[arg(long = "log-keep-days", default_value_t = 7)]
+    pub log_keep_days: u64,
+
+    /// 另一个进程正在使用同一状态目录时最多等待的秒数，默认 0 即立即退出
+    #[arg(long = "lock-timeout", value_name = "SECONDS", default_value_t = 0)]
+    pub lock_timeout: u64,
+}
+
+/// 日志输出目标
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogTarget {
+    /// 写入状态目录下的日志文件（默认）
+    File,
+    /// 输出到标准错误
+    Stderr,
+    /// 同时写入日志文件与标准错误
+    Both,
+}
+
+impl LogTarget {
+    /// 是否写入日志文件
+    pub fn writes_file(&self) -> bool {
+        matches!(self, LogTarget::File | LogTarget::Both)
+    }
+    /// 是否输出到标准错误
+    pub fn writes_stderr(&self) -> bool {
+        matches!(self, LogTarget::Stderr | LogTarget::Both)
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// 认证授权
     #[command(alias = "login")]
-    Auth,
+    Auth(AuthArgs),
     /// 列出远程目录
     #[command(alias = "list")]
     Ls(LsArgs),
@@ -49,6 +95,14 @@ pub enum Commands {
     /// 创建远程目录
     #[command(alias = "md")]
     Mkdir(MkdirArgs),
+    /// 显示远程文件元信息
+    #[command(alias = "info")]
+    Stat(StatArgs),
+    /// 按关键字搜索远程文件
+    #[command(alias = "find")]
+    Search(SearchArgs),
+    /// 显示当前登录的用户信息与容量概况
+    Whoami,
     /// 显示版本信息
     #[command(alias = "ver")]
     Version,
@@ -56,9 +110,21 @@ pub enum Commands {
     #[command(alias = "self")]
     AppSelf(SelfArgs),
     /// 生成 shell 补全脚本
+    #[command(alias = "completions")]
     Completion(CompletionArgs),
 }
 
+/// auth [--device-code <code> | --status]
+#[derive(Args)]
+pub struct AuthArgs {
+    /// 使用此前已显示的设备码轮询授权结果（验证码的展示与授权可在不同机器上进行）
+    #[arg(long = "device-code", conflicts_with = "status")]
+    pub device_code: Option<String>,
+    /// 显示当前登录凭据的过期时间、剩余有效期及是否需要刷新，不进行认证
+    #[arg(long = "status", action = ArgAction::SetTrue)]
+    pub status: bool,
+}
+
 /// app self 子命令
 #[derive(Args)]
 pub struct SelfArgs {
@@ -87,7 +153,7 @@ pub struct UpdateArgs {
     pub download: Option<Option<String>>,
 }
 
-/// ls <remote> [-r]
+/// ls <remote> [-r] [--max-depth N]
 #[derive(Args)]
 pub struct LsArgs {
     /// 远程路径
@@ -95,6 +161,9 @@ pub struct LsArgs {
     /// 递归列出子目录
     #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
     pub recursive: bool,
+    /// 递归时的最大深度，1 表示仅列出当前目录（默认不限制）
+    #[arg(long = "max-depth", requires = "recursive")]
+    pub max_depth: Option<usize>,
 }
 
 /// cp <src> <dest>  （远程 → 远程）
@@ -115,19 +184,25 @@ pub struct MvArgs {
     pub dest: String,
 }
 
-/// rm <remote>... [-r]
+/// rm <remote>... [-r] [-y] [--force-root]
 #[derive(Args)]
 pub struct RmArgs {
     /// 远程路径（支持多个）
     #[arg(required = true)]
     pub remote: Vec<String>,
-    /// 递归删除子目录
+    /// 递归删除目录（删除目录时必须指定）
     #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
     pub recursive: bool,
+    /// 跳过删除确认（非交互）
+    #[arg(short = 'y', long = "yes", action = ArgAction::SetTrue)]
+    pub yes: bool,
+    /// 允许删除网盘根目录 `/`、`/apps` 或应用根目录 `/apps/{app-name}`（默认拒绝）
+    #[arg(long = "force-root", action = ArgAction::SetTrue)]
+    pub force_root: bool,
 }
 
-/// tx <local> <remote> [-r] [--remove-source]
-#[derive(Args)]
+/// tx <local> <remote> [-r] [--remove-source] [--from-app NAME]
+#[derive(Args, Clone)]
 pub struct TxArgs {
     /// 本地源路径
     pub local: String,
@@ -139,10 +214,26 @@ pub struct TxArgs {
     /// 上传完成后删除本地源文件
     #[arg(long = "remove-source", action = ArgAction::SetTrue)]
     pub remove_source: bool,
+    /// 远程路径位于其他应用目录 /apps/NAME 下时映射到当前应用目录（恢复其他应用创建的备份）
+    #[arg(long = "from-app", value_name = "NAME")]
+    pub from_app: Option<String>,
+    /// 本地路径（以源路径自身的名称开头）去掉前 N 级后拼接到远程目录下，默认 0 即保留源目录名
+    #[arg(long = "strip-components", value_name = "N", conflicts_with = "base")]
+    pub strip_components: Option<usize>,
+    /// 以该本地目录为基准计算相对路径并拼接到远程目录下，如 `--base /` 保留完整本地路径
+    #[arg(long = "base", value_name = "PATH")]
+    pub base: Option<String>,
+    /// 只上传修改时间不早于该时间的文件：时长如 `90m`、`1h`、`7d`（单位 s/m/h/d/w），
+    /// Unix 时间戳，或 `2024-01-02`、`2024-01-02 03:04:05`（本地时间）、RFC 3339 时间；默认 `all` 即不限制
+    #[arg(long = "since", value_name = "DURATION|TIMESTAMP")]
+    pub since: Option<String>,
+    /// 允许上传到网盘根目录 `/` 或 `/apps`（默认拒绝）
+    #[arg(long = "force-root", action = ArgAction::SetTrue)]
+    pub force_root: bool,
 }
 
-/// rx <remote> [local] [-r]
-#[derive(Args)]
+/// rx <remote> [local] [-r] [--max-depth N] [--no-clobber] [--from-app NAME]
+#[derive(Args, Clone)]
 pub struct RxArgs {
     /// 远程源路径
     pub remote: String,
@@ -151,25 +242,51 @@ pub struct RxArgs {
     /// 递归下载目录
     #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
     pub recursive: bool,
+    /// 递归时的最大深度，1 表示仅下载当前目录下的文件（默认不限制）
+    #[arg(long = "max-depth", requires = "recursive")]
+    pub max_depth: Option<usize>,
+    /// 本地文件已存在时跳过，不覆盖
+    #[arg(short = 'n', long = "no-clobber", action = ArgAction::SetTrue)]
+    pub no_clobber: bool,
+    /// 远程路径位于其他应用目录 /apps/NAME 下时映射到当前应用目录
+    #[arg(long = "from-app", value_name = "NAME")]
+    pub from_app: Option<String>,
 }
 
-/// backup [local] [remote] [--daemon] [--rm]
+/// backup [local] [remote] [--daemon] [--rm] [--resume | --fresh]
 #[derive(Args)]
 pub struct BackupArgs {
     /// 本地源目录/文件（可选，未提供时从配置文件读取）
     pub local: Option<String>,
     /// 远程目标目录（可选，未提供时从配置文件读取）
     pub remote: Option<String>,
+    /// 依次执行配置文件中 `[[backup_jobs]]` 定义的全部备份任务
+    #[arg(long = "all", action = ArgAction::SetTrue, conflicts_with_all = ["local", "remote"])]
+    pub all: bool,
     /// 守护模式：持续监控本地变更并自动备份
     #[arg(short = 'd', long = "daemon", action = ArgAction::SetTrue)]
     pub daemon: bool,
     /// 备份成功后删除本地源文件
     #[arg(long = "rm", action = ArgAction::SetTrue)]
     pub remove_source: bool,
+    /// 从上次中断的断点继续，跳过已完成且此后未修改的文件
+    #[arg(long = "resume", action = ArgAction::SetTrue, conflicts_with = "fresh")]
+    pub resume: bool,
+    /// 清除上次的断点，重新检查所有文件
+    #[arg(long = "fresh", action = ArgAction::SetTrue)]
+    pub fresh: bool,
+    /// 本地路径（以源路径自身的名称开头）去掉前 N 级后拼接到远程目录下，默认备份目录时为 1 即只备份目录内容
+    #[arg(long = "strip-components", value_name = "N", conflicts_with = "base")]
+    pub strip_components: Option<usize>,
+    /// 以该本地目录为基准计算相对路径并拼接到远程目录下，如 `--base /` 保留完整本地路径
+    #[arg(long = "base", value_name = "PATH")]
+    pub base: Option<String>,
 }
 
 #[derive(Args)]
 pub struct DiskQuotaArgs {
+    /// 远程目录：指定时列出其各子目录的递归大小（按大小降序），而不是显示容量配额
+    pub path: Option<String>,
     /// 是否显示详细信息
     #[arg(short = 'v', long = "verbose", default_value = "false", action = ArgAction::SetTrue)]
     pub verbose: bool,
@@ -208,12 +325,79 @@ pub struct MkdirArgs {
     pub parents: bool,
 }
 
+/// stat <remote> | --fs-id <id> [--dlink]
+#[derive(Args)]
+pub struct StatArgs {
+    /// 远程文件路径
+    #[arg(required_unless_present = "fs_id", conflicts_with = "fs_id")]
+    pub remote: Option<String>,
+    /// 按文件ID查询（可从 `ls` 输出的最后一列获取）
+    #[arg(long = "fs-id")]
+    pub fs_id: Option<u64>,
+    /// 同时显示下载地址
+    #[arg(long = "dlink", action = ArgAction::SetTrue)]
+    pub dlink: bool,
+}
+
+/// 文件类型，对应接口的 category 参数
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FileCategory {
+    /// 视频
+    Video,
+    /// 音频
+    Audio,
+    /// 图片
+    Image,
+    /// 文档
+    Doc,
+    /// 应用
+    App,
+    /// 其他
+    Other,
+    /// 种子
+    Torrent,
+}
+
+impl FileCategory {
+    /// 接口使用的分类编号：1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
+    pub fn code(&self) -> i32 {
+        match self {
+            FileCategory::Video => 1,
+            FileCategory::Audio => 2,
+            FileCategory::Image => 3,
+            FileCategory::Doc => 4,
+            FileCategory::App => 5,
+            FileCategory::Other => 6,
+            FileCategory::Torrent => 7,
+        }
+    }
+}
+
+/// search <key> [--dir DIR] [--category TYPE] [-r]
+#[derive(Args)]
+pub struct SearchArgs {
+    /// 搜索关键字，最大 30 个字符
+    pub key: String,
+    /// 搜索目录（默认根目录）
+    #[arg(long = "dir")]
+    pub dir: Option<String>,
+    /// 只搜索指定类型的文件
+    #[arg(long = "category", value_enum)]
+    pub category: Option<FileCategory>,
+    /// 递归搜索子目录
+    #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
+    pub recursive: bool,
+}
+
 /// completion 子命令参数
 #[derive(Args)]
 pub struct CompletionArgs {
     /// Shell 类型 (默认: 当前 shell)
     #[arg(short = 's', long = "shell")]
     pub shell: Option<String>,
+    /// Shell 类型，与 --shell 相同，如 `completions bash`
+    #[arg(value_name = "SHELL", conflicts_with = "shell")]
+    pub shell_arg: Option<String>,
     /// 将补全脚本安装到 shell 配置文件
     #[arg(short = 'i', long = "install", action = ArgAction::SetTrue)]
     pub install: bool,
@@ -221,3 +405,133 @@ pub struct CompletionArgs {
     #[arg(short = 'y', long = "yes", action = ArgAction::SetTrue)]
     pub yes: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{CommandLineArgs, Commands, FileCategory};
+    use clap::{Parser, ValueEnum};
+
+    #[test]
+    fn test_parse_search_args() {
+        let cli = CommandLineArgs::try_parse_from([
+            "app",
+            "search",
+            "report",
+            "--dir",
+            "/apps/demo",
+            "--category",
+            "doc",
+            "-r",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Search(args)) => {
+                assert_eq!(args.key, "report");
+                assert_eq!(args.dir.as_deref(), Some("/apps/demo"));
+                assert_eq!(args.category, Some(FileCategory::Doc));
+                assert_eq!(args.category.map(|c| c.code()), Some(4));
+                assert!(args.recursive);
+            }
+            _ => panic!("expected search command"),
+        }
+        let codes: Vec<i32> = ["video", "audio", "image", "doc", "app", "other", "torrent"]
+            .iter()
+            .map(|name| FileCategory::from_str(name, false).unwrap().code())
+            .collect();
+        assert_eq!(codes, vec![1, 2, 3, 4, 5, 6, 7]);
+        assert!(
+            CommandLineArgs::try_parse_from(["app", "search", "x", "--category", "pdf"]).is_err()
+        );
+
+        let cli = CommandLineArgs::try_parse_from(["app", "search", "x"]).unwrap();
+        match cli.command {
+            Some(Commands::Search(args)) => {
+                assert!(args.dir.is_none() && args.category.is_none() && !args.recursive)
+            }
+            _ => panic!("expected search command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_completions_positional_shell() {
+        let cli = CommandLineArgs::try_parse_from(["app", "completions", "bash"]).unwrap();
+        match cli.command {
+            Some(Commands::Completion(args)) => {
+                assert_eq!(args.shell_arg.as_deref(), Some("bash"));
+                assert!(args.shell.is_none());
+            }
+            _ => panic!("expected completion command"),
+        }
+        assert!(
+            CommandLineArgs::try_parse_from(["app", "completion", "bash", "-s", "zsh"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_auth_args() {
+        let cli = CommandLineArgs::try_parse_from(["app", "auth", "--device-code", "abc"]).unwrap();
+        match cli.command {
+            Some(Commands::Auth(args)) => assert_eq!(args.device_code.as_deref(), Some("abc")),
+            _ => panic!("expected auth command"),
+        }
+        let cli = CommandLineArgs::try_parse_from(["app", "login"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Auth(args)) if args.device_code.is_none()));
+        let cli = CommandLineArgs::try_parse_from(["app", "auth", "--status"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Auth(args)) if args.status));
+        assert!(CommandLineArgs::try_parse_from([
+            "app",
+            "auth",
+            "--status",
+            "--device-code",
+            "abc"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_stat_args() {
+        let cli =
+            CommandLineArgs::try_parse_from(["app", "stat", "/apps/a.txt", "--dlink"]).unwrap();
+        match cli.command {
+            Some(Commands::Stat(args)) => {
+                assert_eq!(args.remote.as_deref(), Some("/apps/a.txt"));
+                assert_eq!(args.fs_id, None);
+                assert!(args.dlink);
+            }
+            _ => panic!("expected stat command"),
+        }
+
+        let cli = CommandLineArgs::try_parse_from(["app", "info", "--fs-id", "123"]).unwrap();
+        match cli.command {
+            Some(Commands::Stat(args)) => {
+                assert_eq!(args.remote, None);
+                assert_eq!(args.fs_id, Some(123));
+                assert!(!args.dlink);
+            }
+            _ => panic!("expected stat command"),
+        }
+
+        assert!(CommandLineArgs::try_parse_from(["app", "stat"]).is_err());
+        assert!(CommandLineArgs::try_parse_from(["app", "stat", "/a", "--fs-id", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_from_app() {
+        let cli = CommandLineArgs::try_parse_from([
+            "app",
+            "tx",
+            "./backup",
+            "/apps/old/backup",
+            "-r",
+            "--from-app",
+            "old",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Tx(args)) => assert_eq!(args.from_app.as_deref(), Some("old")),
+            _ => panic!("expected tx command"),
+        }
+        let cli = CommandLineArgs::try_parse_from(["app", "rx", "/apps/old/a.txt"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Rx(args)) if args.from_app.is_none()));
+    }
+}
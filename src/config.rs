@@ -1,19 +1,53 @@
 use crate::auth::device_auth_with_dns;
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::PcsAccessToken;
-use log::{debug, info};
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::{token_refresh_threshold, PcsAccessToken};
+use log::{debug, info, warn};
+use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 覆盖 `baidu_pan.access_token` 的环境变量
+pub const ENV_ACCESS_TOKEN: &str = "BAIDU_PCS_ACCESS_TOKEN";
+/// 覆盖 `baidu_pan.refresh_token` 的环境变量
+pub const ENV_REFRESH_TOKEN: &str = "BAIDU_PCS_REFRESH_TOKEN";
+/// 覆盖 `baidu_pan.root_path` 的环境变量
+pub const ENV_ROOT_PATH: &str = "BAIDU_PCS_ROOT_PATH";
+
+/// 配置升级步骤，第 i 项将版本 i+1 的配置升级为版本 i+2；配置格式变更时在末尾追加
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[];
+/// 当前配置文件格式版本
+pub const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
+    /// 配置文件格式版本，缺省视为 1
+    #[serde(default = "default_config_version")]
+    pub version: u32,
     pub baidu_pan: BaiduPan,
     pub local_pan: LocalConfig,
     /// 自定义 DNS 服务器，逗号分隔，例如："8.8.8.8,1.1.1.1"（可为空，空则使用系统默认）
     pub dns: Option<String>,
     /// 备份任务配置（本地与远程路径）
     pub backup: Option<BackupConfig>,
+    /// 批量备份任务列表（`[[backup_jobs]]`），由 `backup --all` 依次执行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backup_jobs: Vec<BackupJob>,
+    /// 环境变量覆盖，仅在本次运行中生效，不写入配置文件
+    #[serde(skip)]
+    pub env: EnvOverrides,
+    /// 配置文件不存在、仅凭环境变量中的凭据运行，此时不创建配置文件
+    #[serde(skip)]
+    pub env_only: bool,
+}
+
+/// 环境变量提供的配置项（见 `ENV_ACCESS_TOKEN` 等），优先于配置文件
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvOverrides {
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub root_path: Option<String>,
 }
 
 /// 备份任务路径配置
@@ -25,6 +59,38 @@ pub struct BackupConfig {
     pub remote_path: String,
 }
 
+/// 批量备份中的单个任务
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct BackupJob {
+    /// 本地备份源目录/文件，支持 `~` 与环境变量
+    pub local_path: String,
+    /// 远程备份目标目录
+    pub remote_path: String,
+    /// 只备份匹配任一模式的文件（为空时备份全部）
+    /// 模式支持 `*`、`?` 与 `**`，不含 `/` 时匹配文件名，否则匹配相对源目录的路径
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// 跳过匹配任一模式的文件，优先于 `include`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// 远程已存在同名文件时的处理策略
+    #[serde(default)]
+    pub policy: BackupPolicy,
+}
+
+/// 备份策略
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupPolicy {
+    /// 跳过远程已存在的文件（默认）
+    #[default]
+    Skip,
+    /// 重新上传并覆盖远程已存在的文件
+    Overwrite,
+    /// 同 `skip`，上传成功后删除本地源文件
+    Move,
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct BaiduPan {
     pub access_token: String,
@@ -39,21 +105,92 @@ pub struct LocalConfig {
     pub include_prefix: Option<bool>,
 }
 
+fn default_config_version() -> u32 {
+    1
+}
+
 impl Config {
+    /// 使用新获取的凭据构造默认配置
+    pub fn from_token(
+        token: PcsAccessToken,
+        local_root: &str,
+        remote_root: &str,
+        dns: Option<&str>,
+    ) -> Self {
+        let mut config = Config {
+            version: CONFIG_VERSION,
+            baidu_pan: BaiduPan {
+                access_token: String::new(),
+                refresh_token: String::new(),
+                expires_at: 0,
+                root_path: remote_root.to_string(),
+            },
+            local_pan: LocalConfig {
+                root_path: local_root.to_string(),
+                include_prefix: Some(false),
+            },
+            dns: dns.map(|s| s.to_string()),
+            backup: None,
+            backup_jobs: Vec::new(),
+            env: EnvOverrides::default(),
+            env_only: false,
+        };
+        config.update_token(token);
+        config
+    }
     pub fn update_token(&mut self, ticket: PcsAccessToken) {
         self.baidu_pan.access_token = ticket.get_access_token().to_string();
         self.baidu_pan.refresh_token = ticket.get_refresh_token().to_string();
         self.baidu_pan.expires_at = ticket.get_born_at() + *ticket.get_expires_in() as i64;
     }
+    /// 本次运行实际使用的凭据：配置文件中的 `baidu_pan` 叠加环境变量覆盖
+    pub fn runtime_pan(&self) -> BaiduPan {
+        self.env.apply(&self.baidu_pan)
+    }
     pub fn is_need_refresh_token(&self) -> bool {
-        self.baidu_pan.is_need_refresh_token()
+        self.runtime_pan().is_need_refresh_token()
+    }
+}
+
+impl EnvOverrides {
+    /// 读取进程环境变量
+    pub fn from_env() -> Self {
+        Self::from_var(env_var)
+    }
+
+    fn from_var<F>(var: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        EnvOverrides {
+            access_token: var(ENV_ACCESS_TOKEN),
+            refresh_token: var(ENV_REFRESH_TOKEN),
+            root_path: var(ENV_ROOT_PATH),
+        }
+    }
+
+    /// 在 `pan` 上叠加环境变量，返回新的副本，`pan` 本身保持不变
+    /// 通过环境变量提供 access_token 时视为由外部负责刷新，不再触发自动刷新
+    pub fn apply(&self, pan: &BaiduPan) -> BaiduPan {
+        let mut pan = pan.clone();
+        if let Some(token) = &self.access_token {
+            pan.access_token = token.clone();
+            pan.expires_at = i64::MAX;
+        }
+        if let Some(token) = &self.refresh_token {
+            pan.refresh_token = token.clone();
+        }
+        if let Some(root) = &self.root_path {
+            pan.root_path = root.clone();
+        }
+        pan
     }
 }
 
 impl BaiduPan {
-    /// 凭据是否需要刷新
+    /// 凭据是否需要刷新（距过期不足 7 天，阈值带按主机固定的抖动，见 `token_refresh_threshold`）
     pub fn is_need_refresh_token(&self) -> bool {
-        chrono::Utc::now().timestamp() + 7 * 24 * 3600 > self.expires_at
+        chrono::Utc::now().timestamp() + token_refresh_threshold() > self.expires_at
     }
 }
 
@@ -74,21 +211,237 @@ pub fn get_config_file_path(custom_config: Option<&String>) -> PathBuf {
     path
 }
 
-pub fn save_or_update_config(config: &mut Config, custom_config: Option<&String>) {
-    use std::io::prelude::*;
-    let path = get_config_file_path(custom_config);
-    fs::create_dir_all(path.parent().unwrap()).unwrap();
+/// 运行状态目录（日志、备份断点等）：`--state-dir` 指定的目录，默认为配置文件所在目录
+pub fn get_state_dir(custom_config: Option<&String>, state_dir: Option<&String>) -> PathBuf {
+    match state_dir {
+        Some(dir) if !dir.trim().is_empty() => PathBuf::from(dir.trim()),
+        _ => get_config_file_path(custom_config)
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default(),
+    }
+}
+
+/// 状态目录下的进程锁文件名
+const LOCK_FILE_NAME: &str = "baidu-pcs-rs.lock";
+
+/// 状态目录下的本地文件校验和缓存文件名
+pub const MD5_CACHE_FILE_NAME: &str = "md5-cache.jsonl";
+
+/// 进程锁：持有期间其他使用同一状态目录的进程无法获取，释放（drop）或进程退出时自动解锁
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// 获取状态目录下的进程锁（操作系统提供的建议锁），避免多个进程同时读写配置、凭据与断点文件
+/// 锁被占用时每隔一段时间重试，超过 `timeout` 仍未获取则返回错误（`timeout` 为 0 时不等待）
+pub fn acquire_instance_lock(state_dir: &Path, timeout: Duration) -> Result<InstanceLock, String> {
+    use fs2::FileExt;
+    use std::io::{Read, Seek, Write};
+    let path = state_dir.join(LOCK_FILE_NAME);
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| format!("无法打开锁文件 {}: {}", path.display(), e))?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock_exclusive() {
+            Ok(()) => break,
+            Err(e) if is_lock_contended(&e) && started.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Err(e) if is_lock_contended(&e) => {
+                // Windows 的文件锁是强制锁，被锁定时读不到持有者记录的进程号
+                let mut holder = String::new();
+                let holder = match file.read_to_string(&mut holder) {
+                    Ok(_) => describe_lock_holder(holder.as_str()),
+                    Err(_) => String::new(),
+                };
+                return Err(format!(
+                    "另一个进程{}正在使用状态目录 {}，请等待其结束，或通过 --lock-timeout 指定等待秒数",
+                    holder,
+                    state_dir.display()
+                ));
+            }
+            Err(e) => {
+                return Err(format!("无法锁定 {}: {}", path.display(), e));
+            }
+        }
+    }
+    // 记录持有锁的进程号，便于排查
+    let _ = file
+        .set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| write!(file, "{}", std::process::id()));
+    Ok(InstanceLock { _file: file })
+}
+
+/// 锁是否已被其他进程持有（区别于打开或加锁本身失败）
+fn is_lock_contended(e: &std::io::Error) -> bool {
+    e.kind() == fs2::lock_contended_error().kind()
+}
+
+/// 锁文件中记录的持有者描述：读到进程号时为 `（PID 进程号）`，否则为空
+fn describe_lock_holder(content: &str) -> String {
+    match content.trim() {
+        "" => String::new(),
+        pid => format!("（PID {}）", pid),
+    }
+}
+
+/// 确认目录存在且可写：不存在时创建，并尝试写入一个临时文件
+pub fn ensure_writable_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("无法创建目录 {}: {}", dir.display(), e))?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    File::create(&probe)
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("目录 {} 不可写: {}", dir.display(), e))
+}
+
+/// 备份断点文件路径：状态目录下的 `checkpoints/{md5(本地目录 + 远程目录)}.txt`
+pub fn get_checkpoint_file_path(state_dir: &Path, local_root: &str, remote_root: &str) -> PathBuf {
+    let mut hasher = Md5::new();
+    hasher.update(local_root.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(remote_root.as_bytes());
+    let key = hex::encode(hasher.finalize());
+    state_dir.join("checkpoints").join(format!("{}.txt", key))
+}
+
+/// 读取非空的环境变量
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// 是否通过环境变量提供了 access_token
+pub fn has_env_access_token() -> bool {
+    env_var(ENV_ACCESS_TOKEN).is_some()
+}
+
+/// 配置文件不存在时，仅凭环境变量构造配置（需提供 access_token），不写入磁盘
+fn config_from_env<F>(var: F, dns: Option<&str>) -> Option<Config>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let env = EnvOverrides::from_var(var);
+    env.access_token.as_ref()?;
+    Some(Config {
+        version: CONFIG_VERSION,
+        baidu_pan: BaiduPan {
+            access_token: String::new(),
+            refresh_token: String::new(),
+            expires_at: 0,
+            root_path: "/".to_string(),
+        },
+        local_pan: LocalConfig {
+            root_path: "/data/backup/".to_string(),
+            include_prefix: Some(false),
+        },
+        dns: dns.map(|s| s.to_string()),
+        backup: None,
+        backup_jobs: Vec::new(),
+        env,
+        env_only: true,
+    })
+}
+
+/// 解析配置文件内容，并将旧版本配置逐步升级到当前版本
+/// 升级在 TOML 层面进行，以便处理字段改名、结构调整等无法直接反序列化的变更
+/// 返回升级后的配置，以及文件内容是否需要回写（缺少版本号或版本较旧）
+fn migrate_config(contents: &str) -> Result<(Config, bool), String> {
+    let mut table: toml::Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+    let found = match table.get("version") {
+        None => None,
+        Some(v) => Some(
+            v.as_integer()
+                .and_then(|v| u32::try_from(v).ok())
+                .filter(|v| *v >= 1)
+                .ok_or_else(|| format!("无效的配置文件版本: {}", v))?,
+        ),
+    };
+    let version = found.unwrap_or(1);
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "配置文件版本 {} 高于当前程序支持的版本 {}，请升级程序",
+            version, CONFIG_VERSION
+        ));
+    }
+    for step in &MIGRATIONS[(version - 1) as usize..] {
+        step(&mut table);
+    }
+    table.insert(
+        "version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION as i64),
+    );
+    let config = toml::Value::Table(table)
+        .try_into::<Config>()
+        .map_err(|e| e.to_string())?;
+    Ok((config, found != Some(CONFIG_VERSION)))
+}
+
+/// 在 `path` 文件名后追加后缀，如 `config.toml` -> `config.toml.bak`
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 原子替换文件内容：由 `write` 写入同目录下的临时文件（保证与目标位于同一文件系统），
+/// 落盘后再重命名覆盖 `path`；写入失败时删除临时文件，原文件保持不变
+/// Unix 下临时文件权限为 0600，替换后目标文件沿用该权限
+/// 替换前将原文件复制为 `{文件名}.bak`，仅保留最近一份
+fn replace_file_atomically<F>(path: &Path, write: F) -> std::io::Result<()>
+where
+    F: FnOnce(&mut File) -> std::io::Result<()>,
+{
+    let tmp_path = path_with_suffix(path, ".tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     {
-        use std::os::unix::fs::PermissionsExt;
-        let config_file = File::create(path.as_path()).unwrap();
-        config_file
-            .set_permissions(fs::Permissions::from_mode(0o600))
-            .unwrap();
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = options.open(&tmp_path).and_then(|mut file| {
+        // 临时文件可能是上次中断时遗留的，重新设置权限
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
     }
-    let mut file = File::create(path.as_path()).unwrap();
+    if path.exists() {
+        if let Err(e) = fs::copy(path, path_with_suffix(path, ".bak")) {
+            warn!("备份配置文件 {} 失败: {}", path.display(), e);
+        }
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// 保存配置：写入临时文件后原子替换，避免写入中断导致配置损坏、丢失刷新凭据
+/// 替换前将原配置复制为 `config.toml.bak`；环境变量覆盖不会写入文件
+/// 仅凭环境变量运行（`env_only`）时不创建配置文件，返回 false
+pub fn save_or_update_config(config: &mut Config, custom_config: Option<&String>) -> bool {
+    use std::io::prelude::*;
+    if config.env_only {
+        info!("仅使用环境变量中的凭据运行，配置未写入文件");
+        return false;
+    }
+    let path = get_config_file_path(custom_config);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
     let config_str = toml::to_string(&config).unwrap();
-    file.write_all(config_str.as_bytes()).unwrap();
+    replace_file_atomically(&path, |file| file.write_all(config_str.as_bytes()))
+        .unwrap_or_else(|e| panic!("保存配置文件 {} 失败: {}", path.display(), e));
+    true
 }
 
 pub fn config_load_or_init(
@@ -101,6 +454,10 @@ pub fn config_load_or_init(
     let path = get_config_file_path(custom_config);
     // 如果配置文件不存在则创建
     if !path.exists() {
+        if let Some(config) = config_from_env(env_var, dns) {
+            info!("配置文件 {} 不存在，使用环境变量中的凭据", path.display());
+            return config;
+        }
         info!(
             "配置文件 {} 不存在，正在创建默认配置文件并进行认证...",
             path.to_str().unwrap()
@@ -108,34 +465,292 @@ pub fn config_load_or_init(
         let local_root = local.unwrap_or_else(|| "/data/backup/".to_string());
         let remote_root = remote.unwrap_or_else(|| "/".to_string());
         let pcs_token: PcsAccessToken = device_auth_with_dns(dns);
-        let mut config: Config = Config {
-            baidu_pan: BaiduPan {
-                access_token: pcs_token.get_access_token().to_string(),
-                refresh_token: pcs_token.get_refresh_token().to_string(),
-                expires_at: *pcs_token.get_born_at(),
-                root_path: remote_root.to_string(),
-            },
-            local_pan: LocalConfig {
-                root_path: local_root.to_string(),
-                include_prefix: Some(false),
-            },
-            dns: dns.map(|s| s.to_string()),
-            backup: None,
-        };
+        let mut config = Config::from_token(pcs_token, &local_root, &remote_root, dns);
         save_or_update_config(&mut config, custom_config);
     }
     let mut file = File::open(path.clone()).unwrap();
     let mut contents = String::new();
     file.read_to_string(&mut contents).unwrap();
     debug!("config => {}", contents);
-    let config_a = toml::from_str::<Config>(&contents);
-    config_a.expect("config file is not valid")
+    let (mut config, upgraded) =
+        migrate_config(&contents).unwrap_or_else(|e| panic!("config file is not valid: {}", e));
+    if upgraded {
+        info!(
+            "配置文件 {} 已升级到版本 {}",
+            path.display(),
+            CONFIG_VERSION
+        );
+        save_or_update_config(&mut config, custom_config);
+    }
+    config.env = EnvOverrides::from_env();
+    config
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::get_config_file_path;
+    use crate::config::{
+        acquire_instance_lock, config_from_env, config_load_or_init, describe_lock_holder,
+        ensure_writable_dir, get_config_file_path, get_state_dir, migrate_config,
+        replace_file_atomically, save_or_update_config, BackupJob, BackupPolicy, Config,
+        EnvOverrides, CONFIG_VERSION, ENV_ACCESS_TOKEN, ENV_REFRESH_TOKEN, ENV_ROOT_PATH,
+    };
+    use std::collections::HashMap;
     use std::env;
+    use std::time::Duration;
+
+    #[test]
+    fn test_apply_env_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [baidu_pan]
+            access_token = "file-access"
+            refresh_token = "file-refresh"
+            expires_at = 100
+            root_path = "/apps/file"
+
+            [local_pan]
+            root_path = "/data"
+            "#,
+        )
+        .unwrap();
+        let vars = HashMap::from([
+            (ENV_ACCESS_TOKEN, "env-access"),
+            (ENV_ROOT_PATH, "/apps/env"),
+        ]);
+        let var = |key: &str| vars.get(key).map(|v| v.to_string());
+        config.env = EnvOverrides::from_var(var);
+        let pan = config.runtime_pan();
+        assert_eq!(pan.access_token, "env-access");
+        assert_eq!(pan.refresh_token, "file-refresh");
+        assert_eq!(pan.root_path, "/apps/env");
+        assert!(!config.is_need_refresh_token());
+        // 环境变量覆盖不进入持久化的配置
+        assert_eq!(config.baidu_pan.access_token, "file-access");
+        assert_eq!(config.baidu_pan.expires_at, 100);
+        let saved = toml::to_string(&config).unwrap();
+        assert!(!saved.contains("env-access") && !saved.contains("/apps/env"));
+
+        // 仅凭环境变量运行时不创建配置文件
+        let mut config = config_from_env(var, Some("8.8.8.8")).unwrap();
+        assert_eq!(config.runtime_pan().access_token, "env-access");
+        assert_eq!(config.dns.as_deref(), Some("8.8.8.8"));
+        let path = env::temp_dir().join(format!("pcs-test-env-only-{}.toml", std::process::id()));
+        let custom = path.to_string_lossy().to_string();
+        assert!(!save_or_update_config(&mut config, Some(&custom)));
+        assert!(!path.exists());
+        assert!(config_from_env(
+            |key: &str| (key == ENV_REFRESH_TOKEN).then(|| "r".to_string()),
+            None
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_get_state_dir() {
+        let config = "/etc/pcs/config.toml".to_string();
+        assert_eq!(
+            get_state_dir(Some(&config), None).to_str().unwrap(),
+            "/etc/pcs"
+        );
+        let custom = "/var/lib/pcs".to_string();
+        assert_eq!(
+            get_state_dir(Some(&config), Some(&custom))
+                .to_str()
+                .unwrap(),
+            "/var/lib/pcs"
+        );
+
+        let dir = env::temp_dir().join(format!("pcs-test-state-{}", std::process::id()));
+        assert!(ensure_writable_dir(&dir.join("nested")).is_ok());
+        assert_eq!(std::fs::read_dir(dir.join("nested")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_acquire_instance_lock() {
+        let dir = env::temp_dir().join(format!("pcs-test-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = acquire_instance_lock(&dir, Duration::ZERO).unwrap();
+        let err = acquire_instance_lock(&dir, Duration::from_millis(300))
+            .err()
+            .unwrap();
+        if cfg!(unix) {
+            assert!(err.contains(&std::process::id().to_string()), "{}", err);
+        }
+        drop(first);
+        assert!(acquire_instance_lock(&dir, Duration::ZERO).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(describe_lock_holder("1234\n"), "（PID 1234）");
+        assert_eq!(describe_lock_holder(""), "");
+    }
+
+    #[test]
+    fn test_save_or_update_config_keeps_backup() {
+        let dir = env::temp_dir().join(format!("pcs-test-save-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let custom = path.to_string_lossy().to_string();
+        let (mut config, _) = migrate_config(
+            r#"
+            [baidu_pan]
+            access_token = "old"
+            refresh_token = "r"
+            expires_at = 100
+            root_path = "/apps/file"
+
+            [local_pan]
+            root_path = "/data"
+            "#,
+        )
+        .unwrap();
+        save_or_update_config(&mut config, Some(&custom));
+        assert!(!dir.join("config.toml.bak").exists());
+        let previous = std::fs::read_to_string(&path).unwrap();
+
+        config.baidu_pan.access_token = "new".to_string();
+        save_or_update_config(&mut config, Some(&custom));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            previous
+        );
+        let saved: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.baidu_pan.access_token, "new");
+        assert!(!dir.join("config.toml.tmp").exists());
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_file_atomically_keeps_original_on_failure() {
+        use std::io::Write;
+        let dir = env::temp_dir().join(format!("pcs-test-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "refresh_token = \"keep\"\n").unwrap();
+
+        // 模拟写入到一半失败
+        let err = replace_file_atomically(&path, |file| {
+            file.write_all(b"refresh_to")?;
+            Err(std::io::Error::other("disk full"))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "refresh_token = \"keep\"\n"
+        );
+        assert!(!dir.join("config.toml.tmp").exists());
+        assert!(!dir.join("config.toml.bak").exists());
+
+        replace_file_atomically(&path, |file| file.write_all(b"refresh_token = \"new\"\n"))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "refresh_token = \"new\"\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_config() {
+        let legacy = r#"
+            [baidu_pan]
+            access_token = "a"
+            refresh_token = "r"
+            expires_at = 100
+            root_path = "/apps/file"
+
+            [local_pan]
+            root_path = "/data"
+            "#;
+        let (config, upgraded) = migrate_config(legacy).unwrap();
+        assert!(upgraded);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.baidu_pan.root_path, "/apps/file");
+
+        let current = toml::to_string(&config).unwrap();
+        let (_, upgraded) = migrate_config(&current).unwrap();
+        assert!(!upgraded);
+        let future = format!("version = {}\n{}", CONFIG_VERSION + 1, legacy);
+        assert!(migrate_config(&future).is_err());
+        assert!(migrate_config(&format!("version = 0\n{}", legacy)).is_err());
+
+        // 加载时回写升级后的配置
+        let dir = env::temp_dir().join(format!("pcs-test-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, legacy).unwrap();
+        let custom = path.to_string_lossy().to_string();
+        let config = config_load_or_init(Some(&custom), None, None, None);
+        assert_eq!(config.version, CONFIG_VERSION);
+        let saved: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.version, CONFIG_VERSION);
+        assert_eq!(saved.baidu_pan.access_token, "a");
+        // 回写前的旧配置保留为 .bak
+        assert_eq!(
+            std::fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            legacy
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_backup_jobs() {
+        let contents = r#"
+            [baidu_pan]
+            access_token = "a"
+            refresh_token = "r"
+            expires_at = 100
+            root_path = "/apps/file"
+
+            [local_pan]
+            root_path = "/data"
+
+            [[backup_jobs]]
+            local_path = "~/photos"
+            remote_path = "/backup/photos"
+            include = ["*.jpg", "*.png"]
+            exclude = ["tmp/**"]
+            policy = "overwrite"
+
+            [[backup_jobs]]
+            local_path = "/var/log/app"
+            remote_path = "/backup/logs"
+            "#;
+        let (config, _) = migrate_config(contents).unwrap();
+        assert_eq!(
+            config.backup_jobs,
+            vec![
+                BackupJob {
+                    local_path: "~/photos".to_string(),
+                    remote_path: "/backup/photos".to_string(),
+                    include: vec!["*.jpg".to_string(), "*.png".to_string()],
+                    exclude: vec!["tmp/**".to_string()],
+                    policy: BackupPolicy::Overwrite,
+                },
+                BackupJob {
+                    local_path: "/var/log/app".to_string(),
+                    remote_path: "/backup/logs".to_string(),
+                    include: vec![],
+                    exclude: vec![],
+                    policy: BackupPolicy::Skip,
+                },
+            ]
+        );
+
+        // 回写后任务列表保持不变，未配置任务时不写出该字段
+        let saved: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.backup_jobs, config.backup_jobs);
+        let mut config = config;
+        config.backup_jobs.clear();
+        assert!(!toml::to_string(&config).unwrap().contains("backup_jobs"));
+        assert!(migrate_config(&contents.replace("overwrite", "unknown")).is_err());
+    }
 
     #[test]
     fn test_get_config_file_path() {
@@ -2,7 +2,9 @@ pub mod baidu_pcs_sdk {
     use getset::Getters;
     use serde::{Deserialize, Deserializer, Serialize};
     use serde_json::Value;
+    use std::collections::BTreeMap;
     use std::error::Error;
+    use std::fmt::{Display, Formatter};
 
     pub mod pcs;
 
@@ -38,7 +40,8 @@ pub mod baidu_pcs_sdk {
     pub struct PcsApiError {
         /// 表示具体错误码。 0 表示成功
         // 返回的json为 number， rust反序列化时，会报错，所以改为 i32
-        #[serde(alias = "error_code")]
+        // 部分接口返回的是带引号的字符串，如 "31034"
+        #[serde(alias = "error_code", deserialize_with = "from_int_or_str")]
         errno: i32,
         /// 有关该错误的描述。
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -54,7 +57,7 @@ pub mod baidu_pcs_sdk {
         pub(crate) raw: String,
     }
 
-    #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
     pub enum AppErrorType {
         /// 未知错误
         Unknown,
@@ -64,6 +67,8 @@ pub mod baidu_pcs_sdk {
         Server,
         /// 客户端错误
         Client,
+        /// 授权失效（access token 过期或身份验证失败），需要刷新凭据或重新认证
+        Unauthorized,
     }
 
     #[derive(Debug, Serialize, Deserialize)]
@@ -123,6 +128,85 @@ pub mod baidu_pcs_sdk {
         free: u64,
     }
 
+    /// 账号概况：用户信息与容量使用情况，由 `BaiduPcsClient::account_summary` 获取
+    #[derive(Serialize, Debug, Getters, Clone)]
+    #[getset(get = "pub")]
+    pub struct AccountSummary {
+        /// 用户信息
+        user: PcsUserInfo,
+        /// 会员类型名称，如 `超级会员`
+        vip_type_name: String,
+        /// 总空间大小，单位B
+        total: u64,
+        /// 已使用大小，单位B
+        used: u64,
+        /// 剩余可用大小（总空间 - 已使用），单位B
+        available: u64,
+        /// 已使用百分比（0 ~ 100），总空间为 0 时为 0
+        used_percent: f64,
+        /// 剩余可用百分比（0 ~ 100），总空间为 0 时为 0
+        available_percent: f64,
+        /// 7天内是否有容量到期
+        expire: bool,
+    }
+
+    /// 备份前估算的接口调用次数，由 `BaiduPcsClient::plan` 生成，用于评估是否会触发频控
+    /// 按 `backup_file` 的分片上传流程计算：每个文件依次调用预上传、获取上传服务器、逐个上传分片、创建文件；
+    /// 秒传成功的文件实际不会上传分片，因此分片上传次数是上限
+    #[derive(Serialize, Debug, Getters, Clone, Default, PartialEq, Eq)]
+    #[getset(get = "pub")]
+    pub struct BackupPlan {
+        /// 待备份的文件数量
+        files: u64,
+        /// 待备份的文件总大小，单位B
+        total_bytes: u64,
+        /// 估算所用的分片大小，单位B
+        slice_size: u64,
+        /// 预上传（precreate）调用次数
+        precreate_calls: u64,
+        /// 获取上传服务器（locateupload）调用次数
+        locate_calls: u64,
+        /// 分片上传调用次数
+        slice_calls: u64,
+        /// 创建文件（合并分片）调用次数
+        merge_calls: u64,
+    }
+
+    /// 单个分类的文件统计
+    #[derive(Serialize, Deserialize, Debug, Getters, Clone, Default, PartialEq, Eq)]
+    #[getset(get = "pub")]
+    pub struct CategoryStat {
+        /// `total`    int    文件总数
+        #[serde(default)]
+        total: u64,
+        /// `size`    int    文件总大小，单位B
+        #[serde(default)]
+        size: u64,
+        /// `count`    int    文件个数
+        #[serde(default)]
+        count: u64,
+    }
+
+    /// 分类文件统计，键为分类：1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
+    #[derive(Serialize, Deserialize, Debug, Getters, Clone, Default)]
+    #[getset(get = "pub")]
+    pub struct CategoryCounts {
+        #[serde(default)]
+        info: BTreeMap<i32, CategoryStat>,
+    }
+
+    impl CategoryCounts {
+        /// 指定分类的统计
+        pub fn category(&self, category: i32) -> Option<&CategoryStat> {
+            self.info.get(&category)
+        }
+
+        /// 合并其他分类的统计结果
+        pub fn merge(&mut self, other: CategoryCounts) {
+            self.info.extend(other.info);
+        }
+    }
+
     #[derive(Serialize, Deserialize, Debug, Getters)]
     #[getset(get = "pub")]
     pub struct PcsCreateFolderResult {
@@ -141,19 +225,49 @@ pub mod baidu_pcs_sdk {
         is_dir: i32,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Getters)]
+    #[getset(get = "pub")]
     pub struct PcsFileTask {
         errno: i32,
         path: String,
         task_id: Option<String>,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Getters)]
+    #[getset(get = "pub")]
     pub struct PcsFileTaskOperationResult {
+        #[serde(default)]
         info: Vec<PcsFileTask>,
+        /// 异步任务ID，仅异步（async=2）或自适应转为异步时返回
+        #[serde(alias = "taskid", deserialize_with = "from_str_or_int", default)]
         task_id: Option<String>,
     }
 
+    /// 文件管理异步任务的查询结果
+    #[derive(Serialize, Deserialize, Debug, Getters)]
+    #[getset(get = "pub")]
+    pub struct PcsFileTaskStatus {
+        /// `status` string 任务状态：pending 排队中、running 执行中、success 成功、failed 失败
+        status: String,
+        /// `list` array 每个文件的处理结果
+        #[serde(default)]
+        list: Vec<PcsFileTaskStatusItem>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Getters, Clone)]
+    #[getset(get = "pub")]
+    pub struct PcsFileTaskStatusItem {
+        /// `from` string 源文件路径
+        #[serde(default)]
+        from: Option<String>,
+        /// `to` string 目标文件路径（删除操作时为空）
+        #[serde(default)]
+        to: Option<String>,
+        /// `errno` int 该文件的错误码，0 表示成功
+        #[serde(default)]
+        errno: i32,
+    }
+
     #[derive(Serialize, Deserialize, Debug, Getters, Clone)]
     #[getset(get = "pub")]
     pub struct PcsFileItem {
@@ -183,6 +297,7 @@ pub mod baidu_pcs_sdk {
         /// `dir_empty` int 该目录是否存在子目录，0为存在，1为不存在
         dir_empty: Option<i32>,
         /// `thumbs` array 包含三个尺寸的缩略图URL，仅当只有请求参数web=1且该条目分类为图片时存在
+        #[serde(deserialize_with = "from_thumbs", default)]
         thumbs: Option<Vec<String>>,
     }
 
@@ -244,16 +359,28 @@ pub mod baidu_pcs_sdk {
         return_type: i32,
         /// `block_list`    string    需要上传的分片序号列表，索引从0开始
         block_list: Vec<i32>,
+        /// 自行记录的参数，预上传时提交的文件命名策略 rtype，创建文件时须保持一致
+        #[serde(skip)]
+        r_type: Option<i32>,
     }
     #[derive(Serialize, Deserialize, Debug, Getters)]
     #[getset(get = "pub")]
     pub struct PcsFileMetaInfo {
+        /// fs_id 文件在云端的唯一标识ID
+        #[serde(default)]
+        fs_id: u64,
         /// category 文件类型，1 视频、2 音频、3 图片、4 文档、5 应用、6 其他、7 种子
         category: i32,
         /// dlink 文件下载地址，参考下载文档进行下载操作。注意unicode解码处理。
         dlink: Option<String>,
         /// filename 文件名
         filename: String,
+        /// path 文件的绝对路径
+        #[serde(default)]
+        path: Option<String>,
+        /// md5 云端哈希（非文件真实MD5），目录无此字段
+        #[serde(default)]
+        md5: Option<String>,
         /// isdir 是否是目录，为1表示目录，为0表示非目录
         #[serde(alias = "isdir", rename = "isdir")]
         is_dir: i32,
@@ -287,6 +414,13 @@ pub mod baidu_pcs_sdk {
     pub struct PcsFileSearchInfo {
         category: i32,
         fs_id: u64,
+        /// `path` string 文件的绝对路径
+        #[serde(default)]
+        path: String,
+        /// `server_filename` string 文件名称
+        #[serde(default)]
+        server_filename: String,
+        #[serde(alias = "isdir")]
         is_dir: i32,
         local_ctime: i64,
         local_mtime: i64,
@@ -294,6 +428,7 @@ pub mod baidu_pcs_sdk {
         server_mtime: i64,
         md5: Option<String>,
         size: u64,
+        #[serde(deserialize_with = "from_thumbs", default)]
         thumbs: Option<Vec<String>>,
     }
     #[derive(Serialize, Deserialize, Debug, Getters)]
@@ -333,13 +468,47 @@ pub mod baidu_pcs_sdk {
         }
 
         pub fn is_need_refresh(&self) -> bool {
-            // 一般有效期是30天， 小于 7 天 则刷新
-            (chrono::Utc::now().timestamp() + 7 * 24 * 3600)
-                < (self.born_at + self.expires_in as i64)
+            // 一般有效期是30天， 小于 7 天（加随机抖动）则刷新
+            (chrono::Utc::now().timestamp() + token_refresh_threshold())
+                > (self.born_at + self.expires_in as i64)
         }
     }
 
-    /// 反序列化时，支持 string 和 number 或者空，避免服务器返回的数据不规范导致反序列化失败
+    /// 凭据距过期不足该时长（秒）时刷新
+    pub const TOKEN_REFRESH_THRESHOLD_SECS: i64 = 7 * 24 * 3600;
+    /// 刷新阈值的随机抖动幅度（秒），避免共用同一应用的大量机器在同一时刻集中刷新
+    pub const TOKEN_REFRESH_JITTER_SECS: i64 = 12 * 3600;
+
+    /// 本机的刷新阈值（秒），在 `[阈值 - 抖动, 阈值 + 抖动]` 范围内
+    /// 抖动由主机名决定：同一台机器每次运行、每次调用取值相同，不同机器的刷新时间相互错开
+    pub fn token_refresh_threshold() -> i64 {
+        static THRESHOLD: std::sync::OnceLock<i64> = std::sync::OnceLock::new();
+        *THRESHOLD.get_or_init(|| token_refresh_threshold_for(host_name().as_bytes()))
+    }
+
+    /// 由种子确定的刷新阈值（秒），取种子 MD5 的前 8 字节映射到抖动范围内
+    pub(crate) fn token_refresh_threshold_for(seed: &[u8]) -> i64 {
+        use md5::{Digest, Md5};
+        let digest = Md5::digest(seed);
+        let value = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        TOKEN_REFRESH_THRESHOLD_SECS + (value % (2 * TOKEN_REFRESH_JITTER_SECS as u64 + 1)) as i64
+            - TOKEN_REFRESH_JITTER_SECS
+    }
+
+    /// 主机名：Windows 取 `COMPUTERNAME`，其他系统依次取 `HOSTNAME` 环境变量、`/etc/hostname`、
+    /// `/proc/sys/kernel/hostname`，均取不到时为空字符串
+    fn host_name() -> String {
+        std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// 反序列化时，支持 string、number、bool 或者空，避免服务器返回的数据不规范导致反序列化失败
+    /// 标量统一转为字符串（bool 为 `"true"`/`"false"`），数组、对象及 null 返回 None
     fn from_str_or_int<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
     where
         D: Deserializer<'de>,
@@ -352,6 +521,53 @@ pub mod baidu_pcs_sdk {
         match value? {
             Value::String(s) => Ok(Some(s)),
             Value::Number(n) => Ok(Some(n.to_string())),
+            Value::Bool(b) => Ok(Some(b.to_string())),
+            Value::Null | Value::Array(_) | Value::Object(_) => Ok(None),
+        }
+    }
+
+    /// 反序列化错误码，支持 number 和内容为整数的 string
+    fn from_int_or_str<'de, D>(deserializer: D) -> Result<i32, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match Value::deserialize(deserializer)? {
+            Value::Number(n) => n
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .ok_or_else(|| D::Error::custom(format!("错误码超出范围: {}", n))),
+            Value::String(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| D::Error::custom(format!("无效的错误码: {}", s))),
+            other => Err(D::Error::custom(format!("无效的错误码: {}", other))),
+        }
+    }
+
+    /// 反序列化缩略图地址，支持数组或 `{"url1": .., "url2": .., "url3": ..}` 对象两种格式
+    // 接口文档描述为数组，但实际 web=1 时返回的是对象（另含 icon 字段，忽略）
+    fn from_thumbs<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer);
+        if value.is_err() {
+            return Ok(None);
+        }
+        match value? {
+            Value::Array(list) => Ok(Some(
+                list.into_iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect(),
+            )),
+            Value::Object(map) => Ok(Some(
+                ["url1", "url2", "url3"]
+                    .iter()
+                    .filter_map(|k| map.get(*k).and_then(|v| v.as_str()))
+                    .map(|s| s.to_string())
+                    .collect(),
+            )),
             _ => Ok(None),
         }
     }
@@ -422,7 +638,205 @@ pub mod baidu_pcs_sdk {
         dlink: Option<String>,
     }
 
+    /// 将 Unix 时间戳（秒）转换为 UTC 时间，超出范围时返回 Unix 纪元
+    pub fn timestamp_to_utc(timestamp: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default()
+    }
+
+    /// 为结构体中以 Unix 时间戳（秒）表示的字段生成 `{字段}_dt()`（UTC）与 `{字段}_local()`（本地时区）访问方法
+    macro_rules! datetime_accessors {
+        ($ty:ty { $($field:ident => $dt:ident, $local:ident;)+ }) => {
+            impl $ty {
+                $(
+                    #[doc = concat!("`", stringify!($field), "` 对应的 UTC 时间")]
+                    pub fn $dt(&self) -> chrono::DateTime<chrono::Utc> {
+                        timestamp_to_utc(self.$field)
+                    }
+                    #[doc = concat!("`", stringify!($field), "` 对应的本地时间")]
+                    pub fn $local(&self) -> chrono::DateTime<chrono::Local> {
+                        timestamp_to_utc(self.$field).with_timezone(&chrono::Local)
+                    }
+                )+
+            }
+        };
+    }
+
+    datetime_accessors!(PcsCreateFolderResult {
+        ctime => ctime_dt, ctime_local;
+        mtime => mtime_dt, mtime_local;
+    });
+    datetime_accessors!(PcsFileItem {
+        server_mtime => server_mtime_dt, server_mtime_local;
+        server_ctime => server_ctime_dt, server_ctime_local;
+        local_mtime => local_mtime_dt, local_mtime_local;
+        local_ctime => local_ctime_dt, local_ctime_local;
+    });
+    datetime_accessors!(PcsFileUploadResult {
+        ctime => ctime_dt, ctime_local;
+        mtime => mtime_dt, mtime_local;
+    });
+    datetime_accessors!(PcsFileMetaInfo {
+        server_ctime => server_ctime_dt, server_ctime_local;
+        server_mtime => server_mtime_dt, server_mtime_local;
+    });
+    datetime_accessors!(PcsFileSearchInfo {
+        server_mtime => server_mtime_dt, server_mtime_local;
+        server_ctime => server_ctime_dt, server_ctime_local;
+        local_mtime => local_mtime_dt, local_mtime_local;
+        local_ctime => local_ctime_dt, local_ctime_local;
+    });
+    datetime_accessors!(UploadServerResult {
+        server_time => server_time_dt, server_time_local;
+    });
+
+    /// 以二进制单位（KiB/MiB/GiB…）格式化字节数
+    fn human_bytes(bytes: u64) -> String {
+        let adj =
+            byte_unit::Byte::from_u64(bytes).get_appropriate_unit(byte_unit::UnitType::Binary);
+        format!("{:.3} {}", adj.get_value(), adj.get_unit())
+    }
+
+    impl Display for PcsFileUploadResult {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{}（{}，fs_id: {}）",
+                self.path,
+                human_bytes(self.size),
+                self.fs_id
+            )
+        }
+    }
+
+    impl Display for PcsDiskQuota {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "已用 {} / 总空间 {}",
+                human_bytes(self.used),
+                human_bytes(self.total)
+            )?;
+            if self.expire {
+                write!(f, "（7 天内有容量到期）")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Display for PcsUserInfo {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} {} ({})",
+                self.baidu_name,
+                self.netdisk_name,
+                self.vip_type_name()
+            )
+        }
+    }
+
+    impl PcsFileTaskStatus {
+        /// 任务是否已结束（成功或失败）
+        pub fn is_finished(&self) -> bool {
+            matches!(self.status.as_str(), "success" | "failed")
+        }
+        /// 任务是否成功
+        pub fn is_success(&self) -> bool {
+            self.status == "success"
+        }
+        /// 处理失败（errno 不为 0）的文件
+        pub fn failures(&self) -> Vec<&PcsFileTaskStatusItem> {
+            self.list.iter().filter(|item| item.errno != 0).collect()
+        }
+    }
+
+    impl PcsFileTaskOperationResult {
+        /// 处理失败（errno 不为 0）的条目
+        pub fn failures(&self) -> Vec<&PcsFileTask> {
+            self.info.iter().filter(|task| task.errno != 0).collect()
+        }
+    }
+
+    impl UploadServerResult {
+        /// 服务端时间与本机时间之差（秒），正数表示本机时钟偏慢
+        pub fn server_time_offset(&self) -> i64 {
+            self.server_time_offset_at(chrono::Utc::now().timestamp())
+        }
+
+        /// 以 `local_time`（Unix 时间戳，秒）作为本机时间计算时间差
+        pub fn server_time_offset_at(&self, local_time: i64) -> i64 {
+            self.server_time - local_time
+        }
+    }
+
+    impl PcsFileUploadResult {
+        /// 服务端实际保存的路径是否与请求的路径不同（重命名策略下同名冲突时会自动重命名）
+        pub fn is_renamed_from(&self, requested: &str) -> bool {
+            self.path != requested
+        }
+    }
+
+    impl AccountSummary {
+        /// 由用户信息与容量信息汇总
+        pub fn new(user: PcsUserInfo, quota: &PcsDiskQuota) -> Self {
+            let available = quota.total.saturating_sub(quota.used);
+            let percent = |bytes: u64| {
+                if quota.total == 0 {
+                    0.0
+                } else {
+                    bytes as f64 * 100.0 / quota.total as f64
+                }
+            };
+            Self {
+                vip_type_name: user.vip_type_name().to_string(),
+                user,
+                total: quota.total,
+                used: quota.used,
+                available,
+                used_percent: percent(quota.used.min(quota.total)),
+                available_percent: percent(available),
+                expire: quota.expire,
+            }
+        }
+    }
+
+    impl BackupPlan {
+        /// 由分片大小与各文件大小估算，每个文件的分片数为 `ceil(大小 / 分片大小)`
+        pub fn new<I: IntoIterator<Item = u64>>(slice_size: u64, file_sizes: I) -> Self {
+            let mut plan = Self {
+                slice_size,
+                ..Self::default()
+            };
+            for size in file_sizes {
+                plan.files += 1;
+                plan.total_bytes += size;
+                if slice_size > 0 {
+                    plan.slice_calls += size.div_ceil(slice_size);
+                }
+            }
+            plan.precreate_calls = plan.files;
+            plan.locate_calls = plan.files;
+            plan.merge_calls = plan.files;
+            plan
+        }
+
+        /// 接口调用总次数
+        pub fn total_calls(&self) -> u64 {
+            self.precreate_calls + self.locate_calls + self.slice_calls + self.merge_calls
+        }
+    }
+
     impl PcsUserInfo {
+        /// 会员类型名称
+        pub fn vip_type_name(&self) -> &'static str {
+            match self.vip_type {
+                0 => "普通用户",
+                1 => "普通会员",
+                2 => "超级会员",
+                _ => "未知会员类型",
+            }
+        }
+
         /// 返回当前用户账号允许上次文件的分片大小，用于上传大文件时的文件切片
         // https://pan.baidu.com/union/doc/nksg0s9vi
         // 如果文件大小小于等于4MB，无需切片，直接上传即可
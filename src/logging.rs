@@ -0,0 +1,249 @@
+use crate::cli::LogTarget;
+use chrono::Local;
+use log::{debug, warn};
+use simplelog::{
+    ColorChoice, CombinedLogger, Config as LogConfig, LevelFilter, SharedLogger, TermLogger,
+    TerminalMode, WriteLogger,
+};
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// 日志目录中最多保留的日志文件数
+const MAX_LOG_FILES: usize = 100;
+
+/// 警告日志是否会输出到标准错误，由 `init_logger` 设置
+static WARNINGS_ON_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// 日志目录：{状态目录}/logs
+pub fn get_log_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("logs")
+}
+
+/// 按输出目标构建日志记录器
+/// `log_file` 仅在目标包含文件时使用
+pub(crate) fn build_loggers(
+    target: LogTarget,
+    level: LevelFilter,
+    log_file: Option<File>,
+) -> Vec<Box<dyn SharedLogger>> {
+    let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
+    if target.writes_file() {
+        if let Some(file) = log_file {
+            loggers.push(WriteLogger::new(level, LogConfig::default(), file));
+        }
+    }
+    if target.writes_stderr() {
+        loggers.push(TermLogger::new(
+            level,
+            LogConfig::default(),
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        ));
+    }
+    loggers
+}
+
+/// 按输出目标与日志级别判断警告日志是否会输出到标准错误
+fn warnings_reach_stderr(target: LogTarget, level: LevelFilter) -> bool {
+    target.writes_stderr() && level >= LevelFilter::Warn
+}
+
+/// 警告日志已输出到标准错误时返回 true，此时无需再用 `eprintln!` 重复提示用户
+pub(crate) fn warnings_on_stderr() -> bool {
+    WARNINGS_ON_STDERR.load(Ordering::Relaxed)
+}
+
+/// 清理日志目录中的旧日志
+/// 删除修改时间早于 `max_age` 的 `.log` 文件，并在剩余文件超过 `max_files` 时按时间从旧到新删除多余的文件
+/// # Returns
+/// * `usize` - 删除的文件数
+pub(crate) fn cleanup_old_logs(dir: &Path, max_age: Duration, max_files: usize) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    let mut logs: Vec<(PathBuf, SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    // 从新到旧排序，超出数量上限或过期的都删除
+    logs.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    let mut removed = 0;
+    for (index, (path, modified)) in logs.iter().enumerate() {
+        let expired = now.duration_since(*modified).is_ok_and(|age| age > max_age);
+        if expired || index >= max_files {
+            match fs::remove_file(path) {
+                Ok(_) => removed += 1,
+                Err(e) => warn!("删除旧日志失败: {} - {}", path.display(), e),
+            }
+        }
+    }
+    removed
+}
+
+/// 日志保留时长，`keep_days` 过大时取可表示的最大值而不是溢出
+fn log_max_age(keep_days: u64) -> Duration {
+    Duration::from_secs(keep_days.saturating_mul(24 * 3600))
+}
+
+/// 在日志目录下新建本次运行的日志文件 `{时间}-{pid}.log`
+/// # Returns
+/// * `(PathBuf, File)` - 日志文件路径及文件句柄
+pub(crate) fn create_log_file(log_dir: &Path) -> std::io::Result<(PathBuf, File)> {
+    if !log_dir.exists() {
+        fs::create_dir_all(log_dir)?;
+    }
+    let now = Local::now();
+    let pid = std::process::id();
+    let path = log_dir.join(format!("{}-{}.log", now.format("%Y%m%dT%H%M%S"), pid));
+    let file = File::create(&path)?;
+    Ok((path, file))
+}
+
+/// 初始化全局日志
+/// 写入文件时日志位于 `{state_dir}/logs`，见 `create_log_file`；
+/// 日志初始化后再清理超过 `keep_days` 天的旧日志，清理中的警告才能写入日志。
+/// 本次运行的日志文件最新，计入 `MAX_LOG_FILES` 的保留数量
+pub(crate) fn init_logger(target: LogTarget, level: LevelFilter, keep_days: u64, state_dir: &Path) {
+    let log_dir = get_log_dir(state_dir);
+    let log_file = if target.writes_file() {
+        let (_, file) = create_log_file(&log_dir).expect("无法创建日志文件");
+        Some(file)
+    } else {
+        None
+    };
+    CombinedLogger::init(build_loggers(target, level, log_file)).expect("日志初始化失败");
+    WARNINGS_ON_STDERR.store(warnings_reach_stderr(target, level), Ordering::Relaxed);
+    if target.writes_file() {
+        let removed = cleanup_old_logs(&log_dir, log_max_age(keep_days), MAX_LOG_FILES);
+        if removed > 0 {
+            debug!("已清理 {} 个旧日志文件", removed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::LogTarget;
+    use crate::logging::{
+        build_loggers, cleanup_old_logs, create_log_file, get_log_dir, log_max_age,
+        warnings_reach_stderr,
+    };
+    use simplelog::LevelFilter;
+    use std::env;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_build_loggers_match_target() {
+        let path = env::temp_dir().join(format!("pcs-test-logger-{}.log", std::process::id()));
+        let open = || Some(File::create(&path).unwrap());
+
+        assert!(LogTarget::File.writes_file() && !LogTarget::File.writes_stderr());
+        assert!(!LogTarget::Stderr.writes_file() && LogTarget::Stderr.writes_stderr());
+        assert!(LogTarget::Both.writes_file() && LogTarget::Both.writes_stderr());
+
+        assert_eq!(
+            build_loggers(LogTarget::File, LevelFilter::Info, open()).len(),
+            1
+        );
+        assert_eq!(
+            build_loggers(LogTarget::Stderr, LevelFilter::Info, None).len(),
+            1
+        );
+        assert_eq!(
+            build_loggers(LogTarget::Stderr, LevelFilter::Info, open()).len(),
+            1
+        );
+        let both = build_loggers(LogTarget::Both, LevelFilter::Debug, open());
+        assert_eq!(both.len(), 2);
+        assert!(both.iter().all(|l| l.level() == LevelFilter::Debug));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_warnings_reach_stderr() {
+        assert!(!warnings_reach_stderr(LogTarget::File, LevelFilter::Info));
+        assert!(warnings_reach_stderr(LogTarget::Stderr, LevelFilter::Warn));
+        assert!(warnings_reach_stderr(LogTarget::Both, LevelFilter::Debug));
+        assert!(!warnings_reach_stderr(LogTarget::Both, LevelFilter::Error));
+    }
+
+    #[test]
+    fn test_create_log_file_in_state_dir() {
+        let state_dir = env::temp_dir().join(format!("pcs-test-state-logs-{}", std::process::id()));
+        let (path, _) = create_log_file(&get_log_dir(&state_dir)).unwrap();
+        assert!(path.exists());
+        assert_eq!(path.parent().unwrap(), state_dir.join("logs"));
+        assert_eq!(path.extension().unwrap(), "log");
+        std::fs::remove_dir_all(&state_dir).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_old_logs() {
+        let dir = env::temp_dir().join(format!("pcs-test-logs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let day = Duration::from_secs(24 * 3600);
+        let touch = |name: &str, age: Duration| {
+            let file = File::create(dir.join(name)).unwrap();
+            file.set_modified(SystemTime::now() - age).unwrap();
+        };
+        touch("old-1.log", day * 10);
+        touch("old-2.log", day * 8);
+        touch("new-1.log", Duration::ZERO);
+        touch("new-2.log", day);
+        touch("other.txt", day * 30);
+
+        assert_eq!(cleanup_old_logs(&dir, day * 7, 100), 2);
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["new-1.log", "new-2.log", "other.txt"]);
+
+        // 超出数量上限时删除最旧的
+        assert_eq!(cleanup_old_logs(&dir, day * 7, 1), 1);
+        assert!(dir.join("new-1.log").exists());
+        assert!(!dir.join("new-2.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_old_logs_keeps_exactly_max_files() {
+        let dir = env::temp_dir().join(format!("pcs-test-logs-max-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let count = || std::fs::read_dir(&dir).unwrap().count();
+        let touch = |name: &str, age_secs: u64| {
+            let file = File::create(dir.join(name)).unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+                .unwrap();
+        };
+        let max_age = log_max_age(7);
+        for i in 0..3 {
+            touch(format!("{}.log", i).as_str(), 10 + i);
+        }
+        // 恰好等于上限时不删除
+        assert_eq!(cleanup_old_logs(&dir, max_age, 3), 0);
+        assert_eq!(count(), 3);
+
+        // 新建本次运行的日志后超出上限一个，只删除最旧的一个
+        let (current, _) = create_log_file(&dir).unwrap();
+        assert_eq!(cleanup_old_logs(&dir, max_age, 3), 1);
+        assert_eq!(count(), 3);
+        assert!(current.exists());
+        assert!(!dir.join("2.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 保留天数很大时不溢出
+        assert_eq!(log_max_age(u64::MAX), Duration::from_secs(u64::MAX));
+    }
+}
@@ -6,21 +6,37 @@
 mod auth;
 mod cli;
 mod config;
+mod logging;
 mod sync;
 
-use crate::auth::{device_auth_with_dns, first_app_use, renew_token};
-use crate::cli::{CommandLineArgs, Commands, CompletionArgs, SelfCommand};
-use crate::config::{config_load_or_init, get_config_file_path, save_or_update_config, BackupConfig, Config};
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::BaiduPcsClient;
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::BaiduPcsApp;
+use crate::auth::{
+    check_first_use, device_auth_with_code, device_auth_with_dns, first_app_use,
+    non_interactive_auth_error, renew_token,
+};
+use crate::cli::{
+    AuthArgs, BackupArgs, CommandLineArgs, Commands, CompletionArgs, CpArgs, DiskQuotaArgs,
+    LogTarget, LsArgs, MkdirArgs, MvArgs, RmArgs, RxArgs, SearchArgs, SelfCommand, StatArgs,
+    TxArgs, WgetArgs,
+};
+use crate::config::{
+    acquire_instance_lock, config_load_or_init, ensure_writable_dir, get_checkpoint_file_path,
+    get_config_file_path, get_state_dir, has_env_access_token, save_or_update_config, BackupConfig,
+    Config, ENV_ACCESS_TOKEN, MD5_CACHE_FILE_NAME,
+};
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::{
+    format_tree, to_remote_path, BaiduPcsClient, Md5Cache, SearchOptions,
+};
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::{
+    timestamp_to_utc, AppError, AppErrorType, BaiduPcsApp, PcsDiskQuota, PcsFileItem, PcsUserInfo,
+};
 use byte_unit::UnitType;
-use chrono::Local;
 use clap::{CommandFactory, Parser};
 use clap_complete::Shell;
 use log::info;
-use simplelog::{Config as LogConfig, LevelFilter, WriteLogger};
+use simplelog::LevelFilter;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::time::Duration;
 use std::{env, fs};
 
 pub(crate) const BAIDU_PCS_APP: BaiduPcsApp = BaiduPcsApp {
@@ -259,30 +275,46 @@ fn check_for_update(dry_run: bool, download: Option<Option<String>>) {
 }
 
 fn run_completion(args: &CompletionArgs) {
-    let shell_name = args.shell.as_deref().unwrap_or_else(|| detect_shell());
-    let shell: Shell = match shell_name.to_lowercase().as_str() {
-        "bash" => Shell::Bash,
-        "zsh" => Shell::Zsh,
-        "fish" => Shell::Fish,
-        "powershell" | "pwsh" => Shell::PowerShell,
-        other => {
-            eprintln!("不支持的 shell: {}\n支持的 shell: bash, zsh, fish, powershell", other);
-            return;
-        }
+    let shell_name = args
+        .shell
+        .as_deref()
+        .or(args.shell_arg.as_deref())
+        .unwrap_or_else(|| detect_shell());
+    let Some(shell) = parse_shell(shell_name) else {
+        eprintln!(
+            "不支持的 shell: {}\n支持的 shell: bash, zsh, fish, powershell",
+            shell_name
+        );
+        return;
     };
 
     if args.install {
         install_completion(shell, args.yes);
     } else {
-        clap_complete::generate(
-            shell,
-            &mut CommandLineArgs::command(),
-            "baidu-pcs-cli-rs",
-            &mut std::io::stdout(),
-        );
+        write_completion(shell, &mut std::io::stdout());
+    }
+}
+
+fn parse_shell(name: &str) -> Option<Shell> {
+    match name.to_lowercase().as_str() {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "powershell" | "pwsh" => Some(Shell::PowerShell),
+        _ => None,
     }
 }
 
+/// 基于 `CommandLineArgs` 的定义生成补全脚本
+fn write_completion(shell: Shell, out: &mut dyn Write) {
+    clap_complete::generate(
+        shell,
+        &mut CommandLineArgs::command(),
+        "baidu-pcs-cli-rs",
+        out,
+    );
+}
+
 fn detect_shell() -> &'static str {
     // 优先从 SHELL 环境变量检测
     if let Ok(shell) = env::var("SHELL") {
@@ -341,12 +373,7 @@ fn install_completion_zsh(skip_confirm: bool) {
 
     // 写入补全文件
     let mut buf = Vec::new();
-    clap_complete::generate(
-        Shell::Zsh,
-        &mut CommandLineArgs::command(),
-        "baidu-pcs-cli-rs",
-        &mut buf,
-    );
+    write_completion(Shell::Zsh, &mut buf);
     if let Err(e) = fs::write(&comp_file, &buf) {
         eprintln!("写入 {} 失败: {}", comp_file.display(), e);
         return;
@@ -490,18 +517,16 @@ fn dirs_home() -> std::path::PathBuf {
         .unwrap_or_else(|| std::path::PathBuf::from("."))
 }
 
+/// 无需配置、认证与状态文件的子命令：version、completion 与 self
+fn is_standalone_command(command: Option<&Commands>) -> bool {
+    matches!(
+        command,
+        Some(Commands::Version | Commands::Completion(_) | Commands::AppSelf(_))
+    )
+}
+
 fn main() {
     let cli = CommandLineArgs::parse();
-    // 日志文件初始化
-    let mut log_dir = env::temp_dir();
-    log_dir.push("baidu-pcs-rs/logs");
-    if !log_dir.exists() {
-        fs::create_dir_all(&log_dir).expect("无法创建日志目录");
-    }
-    let now = Local::now();
-    let pid = std::process::id();
-    let log_file_name = format!("{}-{}.log", now.format("%Y%m%dT%H%M%S"), pid);
-    let log_file_path = log_dir.join(log_file_name);
     // 初始化日志级别：默认根据编译模式决定（debug 构建为 Debug），命令行 --debug 或 -v 系列参数可覆盖
     let mut log_level = if cfg!(debug_assertions) {
         LevelFilter::Debug
@@ -521,8 +546,14 @@ fn main() {
             _ => LevelFilter::Trace, // -vvv 及以上视为最详尽的 Trace
         };
     }
-    let log_file = File::create(&log_file_path).expect("无法创建日志文件");
-    WriteLogger::init(log_level, LogConfig::default(), log_file).expect("日志初始化失败");
+    // 日志、断点等状态文件目录
+    let state_dir = get_state_dir(cli.config.as_ref(), cli.state_dir.as_ref());
+
+    // version、completion 与 self 子命令先于状态目录检查处理，状态目录不可写时也能运行
+    // 它们不写日志文件，--log stderr/both 时日志仍输出到标准错误
+    if is_standalone_command(cli.command.as_ref()) && cli.log.writes_stderr() {
+        logging::init_logger(LogTarget::Stderr, log_level, cli.log_keep_days, &state_dir);
+    }
 
     // version 子命令无需配置和认证，直接输出版本信息
     if matches!(cli.command, Some(Commands::Version)) {
@@ -549,11 +580,63 @@ fn main() {
         return;
     }
 
+    // 以下命令会读写配置、凭据与断点文件，启动时确认状态目录可写
+    if let Err(e) = ensure_writable_dir(&state_dir) {
+        eprintln!("状态目录不可用: {}，可通过 --state-dir 指定其他目录", e);
+        std::process::exit(1);
+    }
+    logging::init_logger(cli.log, log_level, cli.log_keep_days, &state_dir);
+
+    // 同一状态目录同时只允许一个进程运行
+    let _lock = match acquire_instance_lock(&state_dir, Duration::from_secs(cli.lock_timeout)) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     // 检查配置文件是否存在，如果不存在说明是第一次使用， 提示用户
     let path = get_config_file_path(cli.config.as_ref());
-    if !path.exists() {
-        if !first_app_use() {
-            return;
+
+    // auth --device-code 无需交互：直接轮询此前设备码的授权结果并保存凭据
+    if let Some(Commands::Auth(AuthArgs {
+        device_code: Some(device_code),
+        ..
+    })) = &cli.command
+    {
+        match device_auth_with_code(device_code, cli.dns.as_deref()) {
+            Ok(token) => {
+                let mut config = if path.exists() {
+                    let mut config =
+                        config_load_or_init(cli.config.as_ref(), None, None, cli.dns.as_deref());
+                    config.update_token(token);
+                    config
+                } else {
+                    Config::from_token(token, "/data/backup/", "/", cli.dns.as_deref())
+                };
+                save_or_update_config(&mut config, cli.config.as_ref());
+                println!("授权成功，凭据已保存到 {}", path.display());
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match check_first_use(
+        path.exists(),
+        has_env_access_token(),
+        cli.non_interactive,
+        first_app_use,
+    ) {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
     }
 
@@ -561,18 +644,41 @@ fn main() {
     let mut config: Config =
         config_load_or_init(cli.config.as_ref(), None, None, cli.dns.as_deref());
 
+    // auth --status 只显示凭据有效期，需在自动刷新之前处理
+    if let Some(Commands::Auth(AuthArgs { status: true, .. })) = &cli.command {
+        println!(
+            "{}",
+            render_token_status(
+                config.runtime_pan().expires_at,
+                chrono::Utc::now().timestamp(),
+                config.is_need_refresh_token()
+            )
+        );
+        return;
+    }
+
     if config.is_need_refresh_token() {
         info!("Access token (即将)过期，正在刷新...");
         // Clone DNS options first to avoid borrowing from `config` while passing `&mut config`.
         let dns_opt_owned: Option<String> = config.dns.clone().or(cli.dns.clone());
-        renew_token(&mut config, cli.config.as_ref(), dns_opt_owned.as_deref());
+        if let Err(e) = renew_token(
+            &mut config,
+            cli.config.as_ref(),
+            dns_opt_owned.as_deref(),
+            cli.non_interactive,
+        ) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
         info!("Access token 刷新成功");
     }
     let mut client: BaiduPcsClient = BaiduPcsClient::new_with_dns(
-        config.baidu_pan.access_token.as_str(),
+        config.runtime_pan().access_token.as_str(),
         BAIDU_PCS_APP,
         config.dns.as_deref().or(cli.dns.as_deref()),
     );
+    // 重复上传、备份未变化的文件时复用已计算的校验和
+    client.set_md5_cache(Some(Md5Cache::load(state_dir.join(MD5_CACHE_FILE_NAME))));
     match client.ware() {
         Ok(()) => {}
         Err(e) => {
@@ -580,242 +686,822 @@ fn main() {
             return;
         }
     }
-    match &cli.command {
-        Some(Commands::Auth) => {
-            if !config.baidu_pan.access_token.is_empty() && !config.is_need_refresh_token() {
-                let client = BaiduPcsClient::new_with_dns(
-                    config.baidu_pan.access_token.as_str(),
-                    BAIDU_PCS_APP,
-                    config.dns.as_deref().or(cli.dns.as_deref()),
-                );
-                if let Ok(info) = client.get_user_info() {
-                    println!("当前登录凭证 {} {} ({})仍然有效，无需重新认证。如需切换账号可另外指定 --config 参数切换账号配置", info.baidu_name() ,info.netdisk_name(), match info.vip_type() {
-                        0 => "普通用户".to_string(),
-                        1 => "普通会员".to_string(),
-                        2 => "超级会员".to_string(),
-                        _ => "未知会员类型".to_string(),
-                    });
-                    return;
-                }
-            }
-            println!("执行认证授权...");
-            let token = device_auth_with_dns(config.dns.as_deref().or(cli.dns.as_deref()));
-            config.update_token(token);
-            save_or_update_config(&mut config, None);
-        }
-        Some(Commands::Rx(args)) => {
-            println!(
-                "下载: {} -> {}",
-                args.remote,
-                args.local.as_deref().unwrap_or(".")
-            );
-            sync::run_download_task(args, &config, &client);
-        }
-        Some(Commands::Tx(args)) => {
-            println!("上传: {} -> {}", args.local, args.remote);
-            sync::run_upload_task(args, &config, &client);
-        }
-        Some(Commands::Ls(args)) => {
-            println!("列出网盘文件: {:?} 递归: {}", args.remote, args.recursive);
-            let list = client.list_dir(args.remote.as_str());
-            match list {
-                Ok(files) => {
-                    if files.list().is_empty() {
-                        println!("目录为空");
-                        return;
-                    }
-                    for file in files.list() {
-                        println!(
-                            "{}\t{}\t{}\t{} \t {}",
-                            if *file.is_dir() == 1 { "d" } else { "-" },
-                            file.size(),
-                            file.server_filename(),
-                            file.path(),
-                            file.fs_id()
-                        );
-                    }
-                }
-                Err(e) => {
-                    eprintln!("列出文件失败: {}", e);
-                }
-            }
+    let result = match &cli.command {
+        Some(Commands::Auth(_)) => handle_auth(&cli, &mut config),
+        Some(Commands::Rx(args)) => handle_rx(args, &config, &client, cli.quiet),
+        Some(Commands::Tx(args)) => handle_tx(args, &config, &client, cli.quiet),
+        Some(Commands::Ls(args)) => handle_ls(args, &client),
+        Some(Commands::Rm(args)) => handle_rm(args, &client),
+        Some(Commands::Stat(args)) => handle_stat(args, &client),
+        Some(Commands::Search(args)) => handle_search(args, &client),
+        Some(Commands::Whoami) => handle_whoami(&client),
+        Some(Commands::Cp(args)) => handle_cp(args, &client),
+        Some(Commands::Mv(args)) => handle_mv(args, &client),
+        Some(Commands::Backup(args)) => handle_backup(args, &cli, &mut config, &client),
+        Some(Commands::Wget(args)) => handle_wget(args, &client, cli.quiet),
+        Some(Commands::Mkdir(args)) => handle_mkdir(args, &client),
+        Some(Commands::Version) => unreachable!("已在前面提前处理"),
+        Some(Commands::AppSelf(_)) => unreachable!("已在前面提前处理"),
+        Some(Commands::Completion(_)) => unreachable!("已在前面提前处理"),
+        Some(Commands::Quota(args)) => handle_quota(args, &client),
+        None => {
+            //TODO 进入 shell 交互 可以 ls mv rename rm upload download
+            Ok(())
         }
-        Some(Commands::Rm(args)) => {
-            println!("即将删除网盘文件: {:?}", args.remote);
-            let result = client.delete(&args.remote, Some(false));
-            match result {
-                Ok(res) => {
-                    println!("删除成功: {:?}", res);
-                }
-                Err(e) => {
-                    eprintln!("删除失败: {}", e);
-                }
-            }
+    };
+    // process::exit 不会执行析构，先关闭客户端运行时，确保下载等文件写入完成
+    client.shutdown();
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        if e.error_type == AppErrorType::Unauthorized {
+            eprintln!("登录凭据已失效，请执行 auth 重新认证");
         }
-        Some(Commands::Cp(args)) => {
-            println!("复制: {} -> {}", args.src, args.dest);
-            match client.copy_file(&args.src, &args.dest) {
-                Ok(res) => println!("复制成功: {:?}", res),
-                Err(e) => eprintln!("复制失败: {}", e),
+        std::process::exit(1);
+    }
+}
+
+/// 为错误信息附加操作说明，保留原有的错误类型与错误码
+fn with_context(action: &str) -> impl FnOnce(AppError) -> AppError + '_ {
+    move |e| {
+        let error_type = e.error_type;
+        let errno = e.errno;
+        let message = format!("{}: {}", action, String::from(e));
+        AppError::new(error_type, message.as_str(), errno)
+    }
+}
+
+fn handle_auth(cli: &CommandLineArgs, config: &mut Config) -> Result<(), AppError> {
+    let access_token = config.runtime_pan().access_token;
+    if !access_token.is_empty() && !config.is_need_refresh_token() {
+        let client = BaiduPcsClient::new_with_dns(
+            access_token.as_str(),
+            BAIDU_PCS_APP,
+            config.dns.as_deref().or(cli.dns.as_deref()),
+        );
+        match client.check_token() {
+            Ok(true) => {
+                println!("当前登录凭证仍然有效，无需重新认证（可执行 whoami 查看当前账号）。如需切换账号可另外指定 --config 参数切换账号配置");
+                return Ok(());
             }
+            Ok(false) => println!("当前登录凭证已失效"),
+            Err(e) => return Err(with_context("检查登录凭证")(e)),
         }
-        Some(Commands::Mv(args)) => {
-            println!("移动: {} -> {}", args.src, args.dest);
-            match client.move_file(&args.src, &args.dest) {
-                Ok(res) => println!("移动成功: {:?}", res),
-                Err(e) => eprintln!("移动失败: {}", e),
-            }
+    }
+    if cli.non_interactive {
+        return Err(non_interactive_auth_error());
+    }
+    println!("执行认证授权...");
+    let token = device_auth_with_dns(config.dns.as_deref().or(cli.dns.as_deref()));
+    config.update_token(token);
+    // 主动认证得到的凭据写入配置文件，即使此前仅凭环境变量运行
+    config.env_only = false;
+    save_or_update_config(config, cli.config.as_ref());
+    Ok(())
+}
+
+fn handle_rx(
+    args: &RxArgs,
+    config: &Config,
+    client: &BaiduPcsClient,
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mut args = args.clone();
+    args.remote = remap_from_app(client, &args.remote, args.from_app.as_deref());
+    args.local = args.local.as_deref().map(sync::expand_local_path);
+    let args = &args;
+    println!(
+        "下载: {} -> {}",
+        args.remote,
+        args.local.as_deref().unwrap_or(".")
+    );
+    sync::run_download_task(args, config, client, quiet)
+}
+
+fn handle_tx(
+    args: &TxArgs,
+    config: &Config,
+    client: &BaiduPcsClient,
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mut args = args.clone();
+    args.remote = remap_from_app(client, &args.remote, args.from_app.as_deref());
+    args.local = sync::expand_local_path(args.local.as_str());
+    let args = &args;
+    let since = match args.since.as_deref() {
+        Some(since) => sync::parse_since(since, chrono::Local::now())
+            .map_err(|e| AppError::new(AppErrorType::Client, e.as_str(), None))?,
+        None => None,
+    };
+    sync::refuse_root_paths(
+        [client.resolve_path(&args.remote).as_str()],
+        args.force_root,
+        sync::is_disk_root,
+        "上传到",
+    )?;
+    println!("上传: {} -> {}", args.local, args.remote);
+    sync::run_upload_task(args, config, client, since, quiet)
+}
+
+/// 指定 --from-app 时，将其他应用目录下的远程路径映射到当前应用目录
+fn remap_from_app(client: &BaiduPcsClient, remote: &str, from_app: Option<&str>) -> String {
+    let Some(from_app) = from_app else {
+        return remote.to_string();
+    };
+    let mapped = client.remap_app_path(remote, from_app);
+    if mapped != remote {
+        println!("远程路径映射: {} -> {}", remote, mapped);
+    }
+    mapped
+}
+
+fn handle_ls(args: &LsArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    println!("列出网盘文件: {:?} 递归: {}", args.remote, args.recursive);
+    let files = ls_files(
+        args,
+        |path| client.list_dir(path).map(|res| res.list().to_vec()),
+        |path, max_depth| client.list_dir_recursive(path, max_depth),
+    )?;
+    if args.recursive && !files.is_empty() {
+        println!("{}", format_tree(&files));
+    } else {
+        println!("{}", render_ls(&files));
+    }
+    Ok(())
+}
+
+/// 按 ls 参数列出文件，`list` 列出单个目录，`list_recursive` 按最大深度递归列出
+fn ls_files<L, R>(args: &LsArgs, list: L, list_recursive: R) -> Result<Vec<PcsFileItem>, AppError>
+where
+    L: FnOnce(&str) -> Result<Vec<PcsFileItem>, AppError>,
+    R: FnOnce(&str, Option<usize>) -> Result<Vec<PcsFileItem>, AppError>,
+{
+    if args.recursive {
+        list_recursive(args.remote.as_str(), args.max_depth)
+    } else {
+        list(args.remote.as_str())
+    }
+    .map_err(with_context("列出文件失败"))
+}
+
+/// 格式化 ls 输出：类型、大小、文件名、路径、fs_id，每行一个文件
+fn render_ls(files: &[PcsFileItem]) -> String {
+    if files.is_empty() {
+        return "目录为空".to_string();
+    }
+    files
+        .iter()
+        .map(|file| {
+            format!(
+                "{}\t{}\t{}\t{} \t {}",
+                if *file.is_dir() == 1 { "d" } else { "-" },
+                file.size(),
+                file.server_filename(),
+                file.path(),
+                file.fs_id()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn handle_rm(args: &RmArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let app_root = to_remote_path(&client.get_apps_path());
+    let paths: Vec<String> = args.remote.iter().map(|p| client.resolve_path(p)).collect();
+    sync::refuse_root_paths(
+        paths.iter().map(String::as_str),
+        args.force_root,
+        |path| sync::is_protected_root(path, app_root.as_str()),
+        "删除",
+    )?;
+    sync::run_remove_task(args, client)
+}
+
+fn handle_whoami(client: &BaiduPcsClient) -> Result<(), AppError> {
+    let info = client
+        .get_user_info()
+        .map_err(with_context("获取用户信息失败"))?;
+    let quota = client
+        .get_user_quota(true, true)
+        .map_err(with_context("获取容量信息失败"))?;
+    println!("{}", render_whoami(&info, &quota));
+    Ok(())
+}
+
+/// 格式化当前用户信息：账号、会员类型、用户 ID 与容量概况，每项一行
+fn render_whoami(info: &PcsUserInfo, quota: &PcsDiskQuota) -> String {
+    format!(
+        "百度账号: {}\n网盘账号: {}\n会员类型: {}\n用户 ID: {}\n容量: {}",
+        info.baidu_name(),
+        info.netdisk_name(),
+        info.vip_type_name(),
+        info.uk(),
+        quota
+    )
+}
+
+/// 格式化登录凭据状态：过期时间（本地时区）、剩余有效期及是否需要刷新，每项一行
+fn render_token_status(expires_at: i64, now: i64, need_refresh: bool) -> String {
+    if expires_at == i64::MAX {
+        return format!(
+            "访问令牌来自环境变量 {}，有效期未知，不会自动刷新",
+            ENV_ACCESS_TOKEN
+        );
+    }
+    let expires = timestamp_to_utc(expires_at)
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M:%S");
+    let remaining = expires_at - now;
+    let remaining = if remaining > 0 {
+        format!(
+            "{} 天 {} 小时",
+            remaining / (24 * 3600),
+            remaining % (24 * 3600) / 3600
+        )
+    } else {
+        "已过期".to_string()
+    };
+    let refresh = if need_refresh {
+        "是（下次执行命令时自动刷新）"
+    } else {
+        "否"
+    };
+    format!(
+        "过期时间: {}\n剩余有效期: {}\n需要刷新: {}",
+        expires, remaining, refresh
+    )
+}
+
+fn handle_stat(args: &StatArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    sync::run_stat_task(args, client).map_err(with_context("查询文件信息失败"))
+}
+
+fn handle_search(args: &SearchArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let options = SearchOptions {
+        dir: args.dir.clone(),
+        category: args.category.map(|c| c.code()),
+        recursion: args.recursive,
+        web: false,
+    };
+    let mut found = 0;
+    for file in client.search_stream(args.key.as_str(), options) {
+        let file = file.map_err(with_context("搜索失败"))?;
+        println!(
+            "{}\t{}\t{}\t{}",
+            if *file.is_dir() == 1 { "d" } else { "-" },
+            file.size(),
+            file.path(),
+            file.fs_id()
+        );
+        found += 1;
+    }
+    if found == 0 {
+        println!("未找到匹配的文件");
+    }
+    Ok(())
+}
+
+fn handle_cp(args: &CpArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    println!("复制: {} -> {}", args.src, args.dest);
+    let res = client
+        .copy_file(&args.src, &args.dest)
+        .map_err(with_context("复制失败"))?;
+    println!("复制成功: {:?}", res);
+    Ok(())
+}
+
+fn handle_mv(args: &MvArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    println!("移动: {} -> {}", args.src, args.dest);
+    let res = client
+        .move_file(&args.src, &args.dest)
+        .map_err(with_context("移动失败"))?;
+    println!("移动成功: {:?}", res);
+    Ok(())
+}
+
+fn handle_backup(
+    args: &BackupArgs,
+    cli: &CommandLineArgs,
+    config: &mut Config,
+    client: &BaiduPcsClient,
+) -> Result<(), AppError> {
+    let state_dir = get_state_dir(cli.config.as_ref(), cli.state_dir.as_ref());
+    if args.all {
+        if config.backup_jobs.is_empty() {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                "配置文件中未定义任何备份任务 [[backup_jobs]]",
+                None,
+            ));
         }
-        Some(Commands::Backup(args)) => {
-            // 路径解析优先级：CLI 参数 → 配置文件 → 交互输入
-            let saved = config.backup.clone();
-
-            let local = args
-                .local
-                .clone()
-                .or_else(|| saved.as_ref().map(|b| b.local_path.clone()));
-            let remote = args
-                .remote
-                .clone()
-                .or_else(|| saved.as_ref().map(|b| b.remote_path.clone()));
-
-            // 如果任一路径缺失，进入交互输入并写回配置
-            let (local, remote) = match (local, remote) {
-                (Some(l), Some(r)) => (l, r),
-                (l, r) => {
-                    use std::io::{self, Write};
-                    println!("备份路径未配置，请输入以下信息（将保存到配置文件）：");
-                    let local = if let Some(v) = l {
-                        v
-                    } else {
-                        print!("  本地备份目录: ");
-                        io::stdout().flush().unwrap();
-                        let mut buf = String::new();
-                        io::stdin().read_line(&mut buf).unwrap();
-                        buf.trim().to_string()
-                    };
-                    let remote = if let Some(v) = r {
-                        v
-                    } else {
-                        print!("  远程备份目录: ");
-                        io::stdout().flush().unwrap();
-                        let mut buf = String::new();
-                        io::stdin().read_line(&mut buf).unwrap();
-                        buf.trim().to_string()
-                    };
-                    if local.is_empty() || remote.is_empty() {
-                        eprintln!("备份路径不能为空，操作已取消");
-                        return;
-                    }
-                    // 写回配置
-                    config.backup = Some(BackupConfig {
-                        local_path: local.clone(),
-                        remote_path: remote.clone(),
-                    });
-                    save_or_update_config(&mut config, cli.config.as_ref());
-                    println!("已保存备份路径到配置文件");
-                    (local, remote)
-                }
-            };
+        let tasks = config
+            .backup_jobs
+            .iter()
+            .map(|job| {
+                let checkpoint_path =
+                    get_checkpoint_file_path(&state_dir, &job.local_path, &job.remote_path);
+                sync::BackupTask::from_job(args, job, checkpoint_path)
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        println!("批量备份: 共 {} 个任务", tasks.len());
+        return sync::run_backup_task(args, &tasks, client, cli.quiet);
+    }
 
-            if args.daemon {
-                println!("备份(守护模式): {} -> {}", local, remote);
+    // 路径解析优先级：CLI 参数 → 配置文件 → 交互输入
+    let saved = config.backup.clone();
+
+    let local = args
+        .local
+        .as_deref()
+        .map(sync::expand_local_path)
+        .or_else(|| saved.as_ref().map(|b| b.local_path.clone()));
+    let remote = args
+        .remote
+        .clone()
+        .or_else(|| saved.as_ref().map(|b| b.remote_path.clone()));
+
+    // 如果任一路径缺失，进入交互输入并写回配置
+    let (local, remote) = match (local, remote) {
+        (Some(l), Some(r)) => (l, r),
+        (l, r) => {
+            use std::io::{self, Write};
+            println!("备份路径未配置，请输入以下信息（将保存到配置文件）：");
+            let local = if let Some(v) = l {
+                v
+            } else {
+                print!("  本地备份目录: ");
+                io::stdout().flush().unwrap();
+                let mut buf = String::new();
+                io::stdin().read_line(&mut buf).unwrap();
+                buf.trim().to_string()
+            };
+            let remote = if let Some(v) = r {
+                v
             } else {
-                println!("备份: {} -> {}", local, remote);
+                print!("  远程备份目录: ");
+                io::stdout().flush().unwrap();
+                let mut buf = String::new();
+                io::stdin().read_line(&mut buf).unwrap();
+                buf.trim().to_string()
+            };
+            if local.is_empty() || remote.is_empty() {
+                return Err(AppError::new(
+                    AppErrorType::Client,
+                    "备份路径不能为空，操作已取消",
+                    None,
+                ));
             }
-            sync::run_backup_task(args, &local, &remote, &client);
-        }
-        Some(Commands::Wget(args)) => {
-            println!(
-                "分享下载: {} -> {}",
-                args.share_url,
-                args.output.as_deref().unwrap_or(".")
-            );
-            sync::run_wget_task(args, &client);
-        }
-        Some(Commands::Mkdir(args)) => {
-            for remote_path in &args.remote {
-                println!("创建目录: {}", remote_path);
-                match client.create_folder(remote_path) {
-                    Ok(res) => {
-                        println!("✓ 创建成功: {}", remote_path);
-                        println!("  路径: {}", res.path());
-                    }
-                    Err(e) => {
-                        let err_msg = e.to_string();
-                        if args.parents && err_msg.contains("父目录不存在")
-                            || err_msg.contains("110")
-                        {
-                            eprintln!("✗ 创建失败 (父目录不存在): {}", remote_path);
-                        } else if err_msg.contains("文件已存在")
-                            || err_msg.contains("已存在")
-                            || err_msg.contains("112")
-                        {
-                            println!("⊘ 目录已存在: {}", remote_path);
-                        } else {
-                            eprintln!("✗ 创建失败: {}", e);
-                        }
-                    }
-                }
+            // 写回配置
+            config.backup = Some(BackupConfig {
+                local_path: local.clone(),
+                remote_path: remote.clone(),
+            });
+            if save_or_update_config(config, cli.config.as_ref()) {
+                println!("已保存备份路径到配置文件");
             }
+            (local, remote)
         }
-        Some(Commands::Version) => unreachable!("已在前面提前处理"),
-        Some(Commands::AppSelf(_)) => unreachable!("已在前面提前处理"),
-        Some(Commands::Completion(_)) => unreachable!("已在前面提前处理"),
-        Some(Commands::Quota(args)) => match client.get_user_quota(true, true) {
-            Ok(quota) => {
-                let total = *quota.total();
-                let used = *quota.used();
-                let free = *quota.free();
-                let idle = total - used + free;
-
-                let print_human = |v: u64| {
-                    let adj = byte_unit::Byte::from_u64(v).get_appropriate_unit(UnitType::Binary);
-                    format!("{:.3} {}", adj.get_value(), adj.get_unit())
-                };
+    };
+
+    if args.daemon {
+        println!("备份(守护模式): {} -> {}", local, remote);
+    } else {
+        println!("备份: {} -> {}", local, remote);
+    }
+    let checkpoint_path = get_checkpoint_file_path(&state_dir, local.as_str(), remote.as_str());
+    let task = sync::BackupTask::from_args(args, &local, &remote, checkpoint_path)?;
+    sync::run_backup_task(args, &[task], client, cli.quiet)
+}
+
+fn handle_wget(args: &WgetArgs, client: &BaiduPcsClient, quiet: bool) -> Result<(), AppError> {
+    println!(
+        "分享下载: {} -> {}",
+        args.share_url,
+        args.output.as_deref().unwrap_or(".")
+    );
+    sync::run_wget_task(args, client, quiet)
+}
 
-                if args.human {
-                    println!(
-                        "总空间: {}, 已用: {}, 免费空间: {}, 空闲空间: {}",
-                        print_human(total),
-                        print_human(used),
-                        print_human(free),
-                        print_human(idle)
-                    );
+fn handle_mkdir(args: &MkdirArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let mut failed = 0;
+    for remote_path in &args.remote {
+        println!("创建目录: {}", remote_path);
+        match client.create_folder(remote_path) {
+            Ok(res) => {
+                println!("✓ 创建成功: {}", remote_path);
+                println!("  路径: {}", res.path());
+            }
+            Err(e) => {
+                let err_msg = e.to_string();
+                if args.parents && err_msg.contains("父目录不存在") || err_msg.contains("110")
+                {
+                    eprintln!("✗ 创建失败 (父目录不存在): {}", remote_path);
+                    failed += 1;
+                } else if err_msg.contains("文件已存在")
+                    || err_msg.contains("已存在")
+                    || err_msg.contains("112")
+                {
+                    println!("⊘ 目录已存在: {}", remote_path);
                 } else {
-                    let (unit, div): (&str, f64) = if args.gb {
-                        ("GB", 1024f64 * 1024f64 * 1024f64)
-                    } else if args.mb {
-                        ("MB", 1024f64 * 1024f64)
-                    } else if args.kb {
-                        ("KB", 1024f64)
-                    } else {
-                        ("B", 1.0)
-                    };
-
-                    let fmt = |v: u64| -> String {
-                        if div == 1.0 {
-                            format!("{} {}", v, unit)
-                        } else {
-                            format!("{:.3} {}", v as f64 / div, unit)
-                        }
-                    };
-
-                    println!(
-                        "总空间: {}, 已用: {}, 免费空间: {}, 空闲空间: {}",
-                        fmt(total),
-                        fmt(used),
-                        fmt(free),
-                        fmt(idle)
-                    );
+                    eprintln!("✗ 创建失败: {}", e);
+                    failed += 1;
                 }
             }
-            Err(app) => {
-                eprintln!("{}", app)
-            }
-        },
-        None => {
-            //TODO 进入 shell 交互 可以 ls mv rename rm upload download
         }
     }
+    if failed > 0 {
+        return Err(AppError::new(
+            AppErrorType::Client,
+            format!("{} 个目录创建失败", failed).as_str(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+fn handle_quota(args: &DiskQuotaArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let report = quota_report(
+        args,
+        |path| client.dir_size_breakdown(path),
+        || client.get_user_quota(true, true),
+    )?;
+    println!("{}", report);
+    Ok(())
+}
+
+/// 按 quota 参数生成输出：指定路径时用 `breakdown` 统计各子目录大小，否则用 `quota` 查询配额
+fn quota_report<B, Q>(args: &DiskQuotaArgs, breakdown: B, quota: Q) -> Result<String, AppError>
+where
+    B: FnOnce(&str) -> Result<Vec<(String, u64)>, AppError>,
+    Q: FnOnce() -> Result<PcsDiskQuota, AppError>,
+{
+    if let Some(path) = &args.path {
+        let sizes = breakdown(path).map_err(with_context("统计目录大小失败"))?;
+        return Ok(render_size_breakdown(&sizes, args));
+    }
+    let quota = quota().map_err(with_context("获取容量信息失败"))?;
+    Ok(render_quota(&quota, args))
+}
+
+/// 按单位参数格式化配额信息
+fn render_quota(quota: &PcsDiskQuota, args: &DiskQuotaArgs) -> String {
+    let total = *quota.total();
+    let used = *quota.used();
+    let free = *quota.free();
+    let idle = total - used + free;
+
+    format!(
+        "总空间: {}, 已用: {}, 免费空间: {}, 空闲空间: {}",
+        format_size(total, args),
+        format_size(used, args),
+        format_size(free, args),
+        format_size(idle, args)
+    )
+}
+
+/// 按单位参数格式化字节数
+fn format_size(v: u64, args: &DiskQuotaArgs) -> String {
+    if args.human {
+        let adj = byte_unit::Byte::from_u64(v).get_appropriate_unit(UnitType::Binary);
+        return format!("{:.3} {}", adj.get_value(), adj.get_unit());
+    }
+    let (unit, div): (&str, f64) = if args.gb {
+        ("GB", 1024f64 * 1024f64 * 1024f64)
+    } else if args.mb {
+        ("MB", 1024f64 * 1024f64)
+    } else if args.kb {
+        ("KB", 1024f64)
+    } else {
+        ("B", 1.0)
+    };
+    if div == 1.0 {
+        format!("{} {}", v, unit)
+    } else {
+        format!("{:.3} {}", v as f64 / div, unit)
+    }
+}
+
+/// 格式化各子目录大小：大小、路径，每行一个目录
+fn render_size_breakdown(sizes: &[(String, u64)], args: &DiskQuotaArgs) -> String {
+    if sizes.is_empty() {
+        return "没有子目录".to_string();
+    }
+    sizes
+        .iter()
+        .map(|(path, size)| format!("{}\t{}", format_size(*size, args), path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{CommandLineArgs, Commands, DiskQuotaArgs};
+    use crate::config::Config;
+    use crate::{
+        handle_backup, handle_rm, handle_rx, handle_tx, handle_wget, is_standalone_command,
+        ls_files, parse_shell, quota_report, render_ls, render_quota, render_size_breakdown,
+        render_token_status, render_whoami, with_context, write_completion, BAIDU_PCS_APP,
+    };
+    use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::BaiduPcsClient;
+    use baidu_pcs_rs_sdk::baidu_pcs_sdk::{
+        AppError, AppErrorType, PcsDiskQuota, PcsFileItem, PcsUserInfo,
+    };
+    use clap::Parser;
+    use std::env;
+
+    fn quota_args() -> DiskQuotaArgs {
+        DiskQuotaArgs {
+            path: None,
+            verbose: false,
+            human: false,
+            kb: false,
+            mb: false,
+            gb: false,
+        }
+    }
+
+    #[test]
+    fn test_write_bash_completion() {
+        let mut out = Vec::new();
+        write_completion(parse_shell("bash").unwrap(), &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(!script.is_empty());
+        assert!(script.contains("baidu-pcs-cli-rs"));
+        assert!(parse_shell("PWSH").is_some());
+        assert!(parse_shell("tcsh").is_none());
+    }
+
+    #[test]
+    fn test_render_ls() {
+        assert_eq!(render_ls(&[]), "目录为空");
+        let files: Vec<PcsFileItem> = serde_json::from_value(serde_json::json!([
+            {"fs_id": 1, "path": "/a", "server_filename": "a", "size": 0, "server_mtime": 0,
+             "server_ctime": 0, "local_mtime": 0, "local_ctime": 0, "isdir": 1, "category": 6},
+            {"fs_id": 2, "path": "/a/b.txt", "server_filename": "b.txt", "size": 10, "server_mtime": 0,
+             "server_ctime": 0, "local_mtime": 0, "local_ctime": 0, "isdir": 0, "category": 4}
+        ]))
+        .unwrap();
+        assert_eq!(
+            render_ls(&files),
+            "d\t0\ta\t/a \t 1\n-\t10\tb.txt\t/a/b.txt \t 2"
+        );
+    }
+
+    #[test]
+    fn test_render_quota() {
+        let quota: PcsDiskQuota = serde_json::from_value(serde_json::json!({
+            "total": 2048, "expire": false, "used": 1024, "free": 0
+        }))
+        .unwrap();
+        assert_eq!(
+            render_quota(&quota, &quota_args()),
+            "总空间: 2048 B, 已用: 1024 B, 免费空间: 0 B, 空闲空间: 1024 B"
+        );
+        let args = DiskQuotaArgs {
+            kb: true,
+            ..quota_args()
+        };
+        assert_eq!(
+            render_quota(&quota, &args),
+            "总空间: 2.000 KB, 已用: 1.000 KB, 免费空间: 0.000 KB, 空闲空间: 1.000 KB"
+        );
+    }
+
+    #[test]
+    fn test_render_size_breakdown() {
+        let sizes = vec![("/r/b".to_string(), 2048), ("/r/a".to_string(), 1024)];
+        let args = DiskQuotaArgs {
+            kb: true,
+            ..quota_args()
+        };
+        assert_eq!(
+            render_size_breakdown(&sizes, &args),
+            "2.000 KB\t/r/b\n1.000 KB\t/r/a"
+        );
+        assert_eq!(render_size_breakdown(&[], &args), "没有子目录");
+    }
+
+    #[test]
+    fn test_render_token_status() {
+        let now = 1_700_000_000;
+        let expires_at = now + 10 * 24 * 3600 + 5 * 3600 + 59;
+        let expires = chrono::DateTime::from_timestamp(expires_at, 0)
+            .unwrap()
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        assert_eq!(
+            render_token_status(expires_at, now, false),
+            format!(
+                "过期时间: {}\n剩余有效期: 10 天 5 小时\n需要刷新: 否",
+                expires
+            )
+        );
+        let status = render_token_status(now - 1, now, true);
+        assert!(status.contains("剩余有效期: 已过期"));
+        assert!(status.contains("需要刷新: 是"));
+        assert!(render_token_status(i64::MAX, now, false).contains("环境变量"));
+    }
+
+    #[test]
+    fn test_render_whoami() {
+        let info: PcsUserInfo = serde_json::from_value(serde_json::json!({
+            "baidu_name": "百度用户", "netdisk_name": "网盘用户", "avatar_url": "",
+            "vip_type": 2, "uk": 123456
+        }))
+        .unwrap();
+        let quota: PcsDiskQuota = serde_json::from_value(serde_json::json!({
+            "total": 2048, "expire": false, "used": 1024, "free": 0
+        }))
+        .unwrap();
+        assert_eq!(
+            render_whoami(&info, &quota),
+            format!(
+                "百度账号: 百度用户\n网盘账号: 网盘用户\n会员类型: {}\n用户 ID: 123456\n容量: {}",
+                info.vip_type_name(),
+                quota
+            )
+        );
+    }
+
+    #[test]
+    fn test_with_context_keeps_errno() {
+        let e =
+            with_context("列出文件失败")(AppError::new(AppErrorType::Server, "", Some(-9)));
+        assert_eq!(e.error_type, AppErrorType::Server);
+        assert_eq!(e.errno, Some(-9));
+        assert_eq!(e.to_string(), "列出文件失败: 文件或目录不存在");
+    }
+
+    /// 请求全部失败的客户端：本机 1 号端口无服务监听，连接立即被拒绝
+    fn unreachable_client() -> BaiduPcsClient {
+        let mut client = BaiduPcsClient::new("mock-token", BAIDU_PCS_APP);
+        client.set_base_urls("http://127.0.0.1:1", "http://127.0.0.1:1");
+        client
+    }
+
+    fn parse_cli(args: &[&str]) -> CommandLineArgs {
+        CommandLineArgs::try_parse_from(
+            std::iter::once("baidu-pcs-cli-rs").chain(args.iter().copied()),
+        )
+        .unwrap()
+    }
+
+    fn test_config() -> Config {
+        toml::from_str(
+            r#"
+            [baidu_pan]
+            access_token = "mock-token"
+            refresh_token = ""
+            expires_at = 0
+            root_path = "/apps/mock"
+
+            [local_pan]
+            root_path = "."
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_transfer_handlers_fail_when_files_fail() {
+        let dir = env::temp_dir().join(format!("pcs-test-handlers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "hello").unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let client = unreachable_client();
+        let config = test_config();
+        let remote = format!("{}/handlers", client.get_apps_path().to_string_lossy());
+
+        let cli = parse_cli(&["tx", dir_str, remote.as_str()]);
+        let Some(Commands::Tx(args)) = &cli.command else {
+            unreachable!()
+        };
+        assert!(handle_tx(args, &config, &client, true).is_err());
+
+        let out = dir.join("out.txt");
+        let cli = parse_cli(&["rx", "/apps/mock/a.txt", out.to_str().unwrap()]);
+        let Some(Commands::Rx(args)) = &cli.command else {
+            unreachable!()
+        };
+        assert!(handle_rx(args, &config, &client, true).is_err());
+
+        let cli = parse_cli(&["rm", "-y", remote.as_str()]);
+        let Some(Commands::Rm(args)) = &cli.command else {
+            unreachable!()
+        };
+        assert!(handle_rm(args, &client).is_err());
+
+        let cli = parse_cli(&["wget", "https://pan.baidu.com/s/1abcdef"]);
+        let Some(Commands::Wget(args)) = &cli.command else {
+            unreachable!()
+        };
+        assert!(handle_wget(args, &client, true).is_err());
+
+        let cli = parse_cli(&["--state-dir", dir_str, "backup", dir_str, remote.as_str()]);
+        let Some(Commands::Backup(args)) = &cli.command else {
+            unreachable!()
+        };
+        let mut config = test_config();
+        let e = handle_backup(args, &cli, &mut config, &client).unwrap_err();
+        assert_eq!(e.message, "1 个备份任务未全部完成");
+
+        client.shutdown();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_standalone_commands_skip_state_dir() {
+        for args in [
+            &["version"][..],
+            &["completion", "bash"],
+            &["self", "config"],
+            &["--log", "stderr", "self", "update", "--dry-run"],
+        ] {
+            let cli = parse_cli(args);
+            assert!(is_standalone_command(cli.command.as_ref()), "{:?}", args);
+        }
+        for args in [&["ls", "/"][..], &["whoami"], &["auth", "--status"]] {
+            let cli = parse_cli(args);
+            assert!(!is_standalone_command(cli.command.as_ref()), "{:?}", args);
+        }
+        assert!(!is_standalone_command(None));
+    }
+
+    #[test]
+    fn test_ls_files() {
+        let file: PcsFileItem = serde_json::from_value(serde_json::json!({
+            "fs_id": 11, "path": "/apps/mock-app/a.txt", "server_filename": "a.txt", "size": 5,
+            "server_mtime": 1, "server_ctime": 1, "local_mtime": 1, "local_ctime": 1,
+            "isdir": 0, "category": 4
+        }))
+        .unwrap();
+        let cli = parse_cli(&["ls", "/apps/mock-app"]);
+        let Some(Commands::Ls(args)) = &cli.command else {
+            unreachable!()
+        };
+        let files = ls_files(
+            args,
+            |path| {
+                assert_eq!(path, "/apps/mock-app");
+                Ok(vec![file.clone()])
+            },
+            |_, _| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(files.len(), 1);
+
+        let cli = parse_cli(&["ls", "-r", "--max-depth", "2", "/apps/mock-app"]);
+        let Some(Commands::Ls(args)) = &cli.command else {
+            unreachable!()
+        };
+        let e = ls_files(
+            args,
+            |_| unreachable!(),
+            |path, max_depth| {
+                assert_eq!((path, max_depth), ("/apps/mock-app", Some(2)));
+                Err(AppError::new(
+                    AppErrorType::Server,
+                    "file not exist",
+                    Some(-9),
+                ))
+            },
+        )
+        .unwrap_err();
+        assert_eq!(e.errno, Some(-9));
+        assert!(e.to_string().starts_with("列出文件失败: "), "{}", e);
+    }
+
+    #[test]
+    fn test_quota_report() {
+        let quota: PcsDiskQuota = serde_json::from_value(serde_json::json!({
+            "total": 2048, "expire": false, "used": 1024, "free": 0
+        }))
+        .unwrap();
+        let report = quota_report(&quota_args(), |_| unreachable!(), || Ok(quota)).unwrap();
+        assert!(report.starts_with("总空间: 2048 B"), "{}", report);
+
+        let e = quota_report(
+            &quota_args(),
+            |_| unreachable!(),
+            || Err(AppError::new(AppErrorType::Server, "invalid", Some(-6))),
+        )
+        .unwrap_err();
+        assert_eq!(e.errno, Some(-6));
+        assert!(e.to_string().starts_with("获取容量信息失败: "), "{}", e);
+
+        let args = DiskQuotaArgs {
+            path: Some("/apps".to_string()),
+            ..quota_args()
+        };
+        let e = quota_report(
+            &args,
+            |path| {
+                assert_eq!(path, "/apps");
+                Err(AppError::new(AppErrorType::Server, "invalid", Some(-6)))
+            },
+            || unreachable!(),
+        )
+        .unwrap_err();
+        assert!(e.to_string().starts_with("统计目录大小失败: "), "{}", e);
+    }
 }
@@ -1,11 +1,21 @@
-use crate::cli::{BackupArgs, RxArgs, TxArgs, WgetArgs};
-use crate::config::Config;
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::{BaiduPcsClient, PcsUploadPolicy};
-use baidu_pcs_rs_sdk::baidu_pcs_sdk::{PcsFileItem, PcsFileUploadResult, ShareFileInfo};
-use indicatif::{ProgressBar, ProgressStyle};
-use log::{error, info};
+use crate::cli::{BackupArgs, RmArgs, RxArgs, StatArgs, TxArgs, WgetArgs};
+use crate::config::{BackupJob, BackupPolicy, Config};
+use crate::logging::warnings_on_stderr;
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::{
+    to_remote_path, AppError, AppErrorType, BaiduPcsClient, PcsDownloadPolicy, PcsUploadPolicy,
+};
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::{
+    PcsFileItem, PcsFileMetaInfo, PcsFileTaskOperationResult, PcsFileUploadResult, ShareFileInfo,
+};
+use chrono::TimeZone;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{error, info, warn};
+use std::cell::Cell;
 use std::collections::HashSet;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use std::{error::Error, fs};
 use tokio_util::either::Either;
 use tokio_util::either::Either::{Left, Right};
@@ -22,105 +32,354 @@ impl LocalSyncFileManager {
     }
 }
 
-pub fn scan_files_recursive(dir: &str, mut files: Vec<String>) -> Vec<String> {
-    fn is_path_hidden(path: &Path) -> bool {
-        path.file_name()
-            .and_then(|name| name.to_str())
-            .map(|name| name.starts_with("."))
-            .unwrap_or(false)
+/// 深度优先遍历本地目录，边遍历边产出文件路径，无需先把全部路径收集到内存
+/// `dir` 为文件时只产出该文件（规范化后的路径）；跳过隐藏文件（目录）、符号链接和文件名不是有效 UTF-8 的路径
+pub fn scan_files(dir: &str) -> ScanFiles {
+    let mut scan = ScanFiles {
+        file: None,
+        stack: Vec::new(),
+        since: None,
+    };
+    let path = Path::new(dir);
+    if path.is_file() {
+        let canonical = fs::canonicalize(path)
+            .ok()
+            .and_then(|p| p.to_str().map(|s| s.to_string()));
+        scan.file = Some(canonical.unwrap_or_else(|| dir.to_string()));
+    } else if path.is_dir() {
+        scan.stack.extend(read_scan_dir(dir));
     }
-    if !Path::new(dir).exists() {
-        return files;
+    scan
+}
+
+/// 本地文件遍历器，由 `scan_files` 创建；内存占用只与目录深度有关，与文件数量无关
+pub struct ScanFiles {
+    /// 扫描路径本身是文件时待产出的路径
+    file: Option<String>,
+    /// 从根目录到当前目录，各级尚未读完的目录
+    stack: Vec<fs::ReadDir>,
+    /// 只产出修改时间不早于该时间的文件
+    since: Option<SystemTime>,
+}
+
+impl ScanFiles {
+    /// 只产出修改时间不早于 `since` 的文件，`None` 不限制；更早的文件直接跳过，不会被读取或计算校验和
+    pub fn modified_since(mut self, since: Option<SystemTime>) -> Self {
+        self.since = since;
+        self
     }
-    if Path::new(dir).is_file() {
-        let path = fs::canonicalize(PathBuf::from(dir));
-        match path.is_ok() {
-            true => {
-                files.push(path.unwrap().to_string_lossy().to_string());
+
+    fn is_modified_since(&self, meta: io::Result<fs::Metadata>) -> bool {
+        let Some(since) = self.since else {
+            return true;
+        };
+        // 无法取得修改时间时保守地视为已修改
+        meta.and_then(|meta| meta.modified())
+            .map(|modified| modified >= since)
+            .unwrap_or(true)
+    }
+}
+
+impl Iterator for ScanFiles {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if let Some(file) = self.file.take() {
+            if self.is_modified_since(fs::metadata(&file)) {
+                return Some(file);
             }
-            false => {
-                files.push(dir.to_string());
+        }
+        loop {
+            let Some(entry) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if is_path_hidden(&path) || !(file_type.is_dir() || file_type.is_file()) {
+                // 符号链接可能指向扫描目录之外或形成循环，与设备文件等一并跳过
+                if file_type.is_symlink() {
+                    info!("跳过符号链接: {}", path.to_string_lossy());
+                }
+                continue;
+            }
+            // 非 UTF-8 的文件名无法作为远程路径上传，跳过（目录则跳过其下所有文件）
+            let Some(path_str) = path.to_str() else {
+                warn!(
+                    "跳过文件名不是有效 UTF-8 的路径: {}",
+                    path.to_string_lossy()
+                );
+                if !warnings_on_stderr() {
+                    eprintln!(
+                        "警告: 跳过文件名不是有效 UTF-8 的路径: {}",
+                        path.to_string_lossy()
+                    );
+                }
+                continue;
+            };
+            if file_type.is_dir() {
+                self.stack.extend(read_scan_dir(path_str));
+            } else if self.is_modified_since(entry.metadata()) {
+                return Some(path_str.to_string());
             }
         }
-        return files;
     }
-    let paths = fs::read_dir(dir).unwrap();
-    for path in paths {
-        let path = path.unwrap().path();
-        if path.is_dir() && !is_path_hidden(&path) {
-            files.append(&mut scan_files_recursive(path.to_str().unwrap(), vec![]));
-        } else if path.is_file() && !is_path_hidden(&path) {
-            files.push(path.to_str().unwrap().to_string());
+}
+
+/// 解析 `tx --since` 的取值，返回修改时间的下限，`all` 表示不限制
+/// 支持时长（如 `90m`、`1h`、`7d`，单位 s/m/h/d/w，相对 `now` 往前推）、Unix 时间戳（秒）、
+/// `YYYY-MM-DD`、`YYYY-MM-DD HH:MM:SS`（本地时间）及 RFC 3339 时间
+pub(crate) fn parse_since(
+    value: &str,
+    now: chrono::DateTime<chrono::Local>,
+) -> Result<Option<SystemTime>, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("all") {
+        return Ok(None);
+    }
+    let invalid = || {
+        format!(
+            "无法识别的时间 `{}`，应为时长（如 90m、1h、7d）、Unix 时间戳或 YYYY-MM-DD[ HH:MM:SS]",
+            value
+        )
+    };
+    let cutoff = if let Ok(secs) = value.parse::<i64>() {
+        chrono::DateTime::from_timestamp(secs, 0).ok_or_else(invalid)?
+    } else if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        // RFC 3339 时间可能以 `Z` 结尾，需先于时长解析
+        time.to_utc()
+    } else if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        let amount: i64 = value[..value.len() - 1].parse().map_err(|_| invalid())?;
+        let seconds = match unit.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 7 * 86400,
+            _ => return Err(invalid()),
+        };
+        let duration = amount
+            .checked_mul(seconds)
+            .and_then(chrono::TimeDelta::try_seconds)
+            .ok_or_else(invalid)?;
+        (now - duration).to_utc()
+    } else {
+        let naive = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+            })
+            .map_err(|_| invalid())?;
+        chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(invalid)?
+            .to_utc()
+    };
+    Ok(Some(SystemTime::from(cutoff)))
+}
+
+fn is_path_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with("."))
+        .unwrap_or(false)
+}
+
+/// 打开待遍历的目录，失败时给出警告并跳过该目录
+fn read_scan_dir(dir: &str) -> Option<fs::ReadDir> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            warn!("无法读取目录 {}: {}", dir, e);
+            if !warnings_on_stderr() {
+                eprintln!("警告: 无法读取目录 {}: {}", dir, e);
+            }
+            None
         }
     }
-    files
 }
 
-pub fn task_scheduler<F>(dir: &str, remote_dir: &str, include_prefix: bool, consumer: F)
+/// 本地文件路径到远程路径的映射方式
+///
+/// 以 `tx /data/photos /remote` 上传 `/data/photos/2024/a.jpg` 为例：
+/// * `StripComponents(0)` -> `/remote/photos/2024/a.jpg`（保留源目录名）
+/// * `StripComponents(1)` -> `/remote/2024/a.jpg`（只保留目录内容）
+/// * `StripComponents(2)` -> `/remote/a.jpg`
+/// * `Base("/data")` -> `/remote/photos/2024/a.jpg`，`Base("/")` -> `/remote/data/photos/2024/a.jpg`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PathMapping {
+    /// 取文件相对于源路径父目录的路径（以源路径自身的名称开头），去掉前 N 级
+    StripComponents(usize),
+    /// 取文件相对于指定本地目录的路径
+    Base(PathBuf),
+}
+
+impl PathMapping {
+    /// 由命令行参数构造，均未指定时使用 `default`
+    /// `base` 会展开 `~` 与环境变量并转换为绝对路径
+    pub(crate) fn from_args(
+        strip_components: Option<usize>,
+        base: Option<&String>,
+        default: PathMapping,
+    ) -> Result<Self, AppError> {
+        match (strip_components, base) {
+            (Some(n), _) => Ok(PathMapping::StripComponents(n)),
+            (None, Some(base)) => {
+                let expanded = expand_local_path(base.as_str());
+                fs::canonicalize(expanded.as_str())
+                    .map(PathMapping::Base)
+                    .map_err(|e| {
+                        AppError::new(
+                            AppErrorType::Client,
+                            format!("--base 路径不可用: {} - {}", expanded, e).as_str(),
+                            None,
+                        )
+                    })
+            }
+            (None, None) => Ok(default),
+        }
+    }
+
+    /// 计算 `file` 对应的远程路径，`source` 为本次上传的本地源路径，二者均应为绝对路径
+    /// 文件不在基准目录下，或去掉前 N 级后不剩任何部分时返回 None
+    pub(crate) fn remote_path(
+        &self,
+        source: &Path,
+        file: &Path,
+        remote_root: &str,
+    ) -> Option<String> {
+        let relative: PathBuf = match self {
+            PathMapping::StripComponents(n) => {
+                let parent = source.parent().unwrap_or(source);
+                file.strip_prefix(parent)
+                    .ok()?
+                    .components()
+                    .skip(*n)
+                    .collect()
+            }
+            PathMapping::Base(base) => file.strip_prefix(base).ok()?.to_path_buf(),
+        };
+        if relative.as_os_str().is_empty() {
+            return None;
+        }
+        Some(to_remote_path(&PathBuf::from(remote_root).join(relative)))
+    }
+}
+
+/// 返回 `consumer` 处理失败的文件数
+pub fn task_scheduler<F>(
+    dir: &str,
+    remote_dir: &str,
+    mapping: &PathMapping,
+    since: Option<SystemTime>,
+    consumer: F,
+) -> Result<usize, AppError>
 where
     F: Fn(String, String) -> Result<PcsFileUploadResult, Box<dyn Error>>,
 {
-    let local_path = PathBuf::from(dir).canonicalize().unwrap();
-    let scanned_local_files = if local_path.is_dir() {
-        scan_files_recursive(dir, vec![])
-    } else {
-        vec![local_path.to_string_lossy().to_string()]
+    let local_path = match PathBuf::from(dir).canonicalize() {
+        Ok(path) => path,
+        Err(e) => {
+            error!("本地路径不可用: {} - {}", dir, e);
+            return Err(AppError::new(
+                AppErrorType::Client,
+                format!("本地路径不可用: {} - {}", dir, e).as_str(),
+                None,
+            ));
+        }
     };
-    info!("{:?}", scanned_local_files);
-    for file in scanned_local_files {
-        let pcs_path_buf = PathBuf::from(remote_dir);
-        let file_path = PathBuf::from(file.clone());
-        let remote_file_path = pcs_path_buf.join(if include_prefix {
-            file_path.strip_prefix("/").unwrap()
-        } else if local_path.is_absolute() {
-            file_path
-                .strip_prefix(local_path.parent().unwrap())
-                .unwrap()
-        } else {
-            file_path.as_path()
-        });
+    let mut failed = 0;
+    // 以绝对路径扫描，保证扫描结果都位于 local_path 下；边扫描边上传
+    for file in scan_files(local_path.to_str().unwrap_or(dir)).modified_since(since) {
+        let Some(remote_file_path) =
+            mapping.remote_path(&local_path, Path::new(file.as_str()), remote_dir)
+        else {
+            warn!(
+                "按路径映射规则 {:?} 无法确定远程路径，跳过: {}",
+                mapping, file
+            );
+            if !warnings_on_stderr() {
+                eprintln!("警告: 无法确定远程路径，跳过: {}", file);
+            }
+            continue;
+        };
         info!("{:?}", remote_file_path);
-        let _ = consumer(file, remote_file_path.to_string_lossy().to_string());
+        if consumer(file, remote_file_path).is_err() {
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+/// 批量操作中有失败项时返回错误，使命令以非零状态退出；`what` 描述失败项，如 `文件上传失败`
+fn failures_to_result(failed: usize, what: &str) -> Result<(), AppError> {
+    if failed == 0 {
+        return Ok(());
     }
+    Err(AppError::new(
+        AppErrorType::Client,
+        format!("{} 个{}", failed, what).as_str(),
+        None,
+    ))
 }
 
-pub(crate) fn run_upload_task(args: &TxArgs, _config: &Config, client: &BaiduPcsClient) {
+/// `since` 为 `--since` 解析后的修改时间下限，更早的本地文件不上传
+pub(crate) fn run_upload_task(
+    args: &TxArgs,
+    _config: &Config,
+    client: &BaiduPcsClient,
+    since: Option<SystemTime>,
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mode = ProgressMode::detect(quiet, is_interactive_terminal());
     let local_root = &args.local;
     let remote_root = &args.remote;
     let remove_source = args.remove_source;
-    task_scheduler(
+    let stats = TransferStats::counting(scan_files(local_root.as_str()).modified_since(since));
+    let stats = &stats;
+    let mapping = PathMapping::from_args(
+        args.strip_components,
+        args.base.as_ref(),
+        PathMapping::StripComponents(0),
+    )?;
+    let failed = task_scheduler(
         local_root.as_str(),
         remote_root.as_str(),
-        false,
+        &mapping,
+        since,
         move |local: String, remote: String| {
             let file_size = fs::metadata(&local).map(|m| m.len()).unwrap_or(0);
-            let pb = ProgressBar::new(file_size);
-            pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{bar:72.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} ETA {eta_precise} | {msg}", )
-                             .unwrap()
-                             .progress_chars("=>-"));
+            let pb = new_progress_bar(mode, Some(file_size));
             pb.set_message(format!("{} -> {}", local, remote));
-            let result = client.upload_large_file(
+            let result = client.upload_large_file_with_hash_progress(
                 local.as_str(),
                 remote.as_str(),
                 PcsUploadPolicy::Overwrite,
+                hashing_progress(&pb, mode),
                 {
                     let pb = pb.clone();
-                    move |p| {
-                        // 保障长度一致
-                        if pb.length().unwrap_or(0) != p.total_bytes {
-                            pb.set_length(p.total_bytes);
-                        }
-                        pb.set_position(p.uploaded_bytes);
-                    }
+                    move |p| update_progress(&pb, mode, p.uploaded_bytes, p.total_bytes)
                 },
             );
             match result {
                 Ok(result) => {
-                    pb.finish_with_message("上传完成");
+                    // 重命名策略下服务端实际保存的路径可能与请求的不同，以返回结果为准
+                    pb.set_message(upload_done_message(&local, &remote, &result));
+                    finish_progress(&pb, true, "上传完成");
+                    stats.add_done(file_size);
+                    print_overall_progress(&pb, mode, stats);
                     if remove_source {
                         if let Err(e) = fs::remove_file(&local) {
                             error!("删除本地文件失败: {} - {}", local, e);
-                            eprintln!("警告: 上传成功但删除本地文件失败: {}", local);
+                            if !warnings_on_stderr() {
+                                eprintln!("警告: 上传成功但删除本地文件失败: {}", local);
+                            }
                         } else {
                             info!("已删除本地文件: {}", local);
                         }
@@ -128,17 +387,75 @@ pub(crate) fn run_upload_task(args: &TxArgs, _config: &Config, client: &BaiduPcs
                     Ok(result)
                 }
                 Err(error) => {
-                    pb.abandon_with_message("上传失败");
+                    finish_progress(&pb, false, "上传失败");
+                    stats.add_failed(file_size);
+                    print_overall_progress(&pb, mode, stats);
                     error!("error: {:?}", error);
                     Err(Box::new(error))
                 }
             }
         },
-    );
+    )?;
+    failures_to_result(failed, "文件上传失败")
+}
+
+/// 上传完成后的提示信息，远程路径取自上传结果
+fn upload_done_message(local: &str, requested: &str, result: &PcsFileUploadResult) -> String {
+    if !result.is_renamed_from(requested) {
+        format!("{} -> {}", local, result.path())
+    } else {
+        format!(
+            "{} -> {}（服务端已重命名，请求路径 {}）",
+            local,
+            result.path(),
+            requested
+        )
+    }
+}
+
+/// 展开本地路径中的 `~`（当前用户主目录）与环境变量 `$VAR`、`${VAR}`
+/// 仅展开开头的 `~` 或 `~/`；未定义的环境变量保留原样
+/// 例如 "~/dl" -> "/home/me/dl"，"$HOME/dl" -> "/home/me/dl"
+pub(crate) fn expand_local_path(path: &str) -> String {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            match directories::BaseDirs::new() {
+                Some(dirs) => format!("{}{}", dirs.home_dir().to_string_lossy(), rest),
+                None => path.to_string(),
+            }
+        }
+        _ => path.to_string(),
+    };
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path.as_str();
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(value.as_str()),
+            _ => expanded.push_str(&rest[pos..pos + 1 + len]),
+        }
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    expanded
 }
 
 // 将 name 和 path 组合成一个完整的路径，只保留 name中的不含 / 的最后的部分
 // 例如 name = "a/b/c.txt" path = "/d/e/" -> "/d/e/c.txt"
+// path 中的 `~` 与环境变量会被展开（见 `expand_local_path`）
 fn get_local_path(name: &str, path: Option<&String>) -> String {
     let name_path = PathBuf::from(name);
     let file_name = name_path
@@ -146,82 +463,466 @@ fn get_local_path(name: &str, path: Option<&String>) -> String {
         .unwrap_or_default()
         .to_str()
         .unwrap_or(name);
-    let path_buf = PathBuf::from(path.unwrap_or(&"./".to_string()));
+    let path_buf = PathBuf::from(expand_local_path(path.map_or("./", |p| p.as_str())));
     let full_path = path_buf.join(file_name);
     full_path.to_string_lossy().to_string()
 }
 
-pub(crate) fn run_download_task(args: &RxArgs, _config: &Config, client: &BaiduPcsClient) {
+// 按 remote_path 相对于 remote_root 的路径拼接到本地目录下，保持目录结构
+// 例如 remote_root = "/a" remote_path = "/a/b/c.txt" path = "/d/" -> "/d/b/c.txt"
+// remote_path 不在 remote_root 下时退化为 get_local_path
+fn get_relative_local_path(remote_root: &str, remote_path: &str, path: Option<&String>) -> String {
+    match Path::new(remote_path).strip_prefix(remote_root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => {
+            let path_buf = PathBuf::from(expand_local_path(path.map_or("./", |p| p.as_str())));
+            path_buf.join(relative).to_string_lossy().to_string()
+        }
+        _ => get_local_path(remote_path, path),
+    }
+}
+
+pub(crate) fn run_download_task(
+    args: &RxArgs,
+    _config: &Config,
+    client: &BaiduPcsClient,
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mode = ProgressMode::detect(quiet, is_interactive_terminal());
     // 获取远程文件信息，获得文件大小
-    let pb = ProgressBar::no_length();
-    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{bar:72.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} ETA {eta_precise} | {msg}", )
-                     .unwrap()
-                     .progress_chars("=>-"));
+    let pb = new_progress_bar(mode, None);
     pb.set_message(format!(
         "{} -> {}",
         args.remote,
         get_local_path(args.remote.as_str(), args.local.as_ref())
     ));
+    // --no-clobber 时本地已存在的文件不会被覆盖
+    let policy = if args.no_clobber {
+        PcsDownloadPolicy::Skip
+    } else {
+        PcsDownloadPolicy::Overwrite
+    };
     match resolve_remote_path(args.remote.as_str(), client) {
         Left(remote_path) => {
+            let local_path = get_local_path(args.remote.as_str(), args.local.as_ref());
             let pbm = pb.clone();
 
-            let result = client.down_file(
+            let result = client.down_file_with_policy(
                 remote_path.as_str(),
-                get_local_path(args.remote.as_str(), args.local.as_ref()).as_str(),
-                Some(move |downloaded, total| {
-                    pbm.set_length(total);
-                    pbm.set_position(downloaded);
-                }),
+                local_path.as_str(),
+                policy,
+                Some(move |downloaded, total| update_progress(&pbm, mode, downloaded, total)),
             );
             match result {
                 Ok(_) => {
-                    pb.finish_with_message("下载完成");
+                    finish_progress(&pb, true, "下载完成");
+                    Ok(())
                 }
                 Err(error) => {
-                    pb.abandon_with_message(format!("下载失败: {}", error.message));
+                    finish_progress(&pb, false, format!("下载失败: {}", error.message));
                     error!("error: {:?}", error);
+                    Err(error)
                 }
             }
         }
         Right(files) => {
+            pb.finish_and_clear();
             if !args.recursive {
-                pb.finish_and_clear();
-                eprintln!("指定文件夹下载时请使用 -r 参数，将递归下载该目录下的所有文件");
-                return;
+                return Err(AppError::new(
+                    AppErrorType::Client,
+                    "指定文件夹下载时请使用 -r 参数，将递归下载该目录下的所有文件",
+                    None,
+                ));
             }
-            for file in files {
-                if *file.is_dir() == 1 {
-                    info!("跳过目录: {}", file.path());
-                    continue;
-                }
-
-                let remote_path = file.path();
-                let pbm = pb.clone();
-                let result = client.down_file_by_id(
-                    *file.fs_id(),
-                    get_local_path(remote_path, args.local.as_ref()).as_str(),
-                    Some(move |downloaded, total| {
-                        pbm.set_length(total);
-                        pbm.set_position(downloaded);
-                    }),
-                );
-                match result {
-                    Ok(_) => {
-                        pb.finish_with_message("下载完成");
-                    }
-                    Err(error) => {
-                        pb.abandon_with_message(format!(
-                            "下载 {} 失败: {}",
-                            file.server_filename(),
-                            error.message
-                        ));
-                        error!("error: {:?}", error);
+            let files = if args.max_depth == Some(1) {
+                files
+            } else {
+                client
+                    .list_dir_recursive(args.remote.as_str(), args.max_depth)
+                    .map_err(crate::with_context("列出远程目录失败"))?
+            };
+            let files: Vec<PcsFileItem> = files
+                .into_iter()
+                .filter(|file| {
+                    if *file.is_dir() == 1 {
+                        info!("跳过目录: {}", file.path());
                     }
+                    *file.is_dir() != 1
+                })
+                .collect();
+
+            let mp = if mode == ProgressMode::Bar {
+                MultiProgress::new()
+            } else {
+                MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+            };
+            let overall = mp.add(ProgressBar::new(total_download_bytes(&files)));
+            let failed = download_files_with_progress(
+                &files,
+                args.remote.as_str(),
+                args.local.as_ref(),
+                mode,
+                &mp,
+                &overall,
+                |file, local, cb| {
+                    client.down_file_by_id_with_policy(*file.fs_id(), local, policy, Some(cb))
+                },
+            );
+            failures_to_result(failed, "文件下载失败")
+        }
+    }
+}
+
+/// 下载进度回调
+type DownloadProgressFn = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+/// 依次下载多个文件并显示进度
+/// 总进度条仅在全部文件处理完成后结束；每个文件单独创建进度条
+/// # Arguments
+/// * `remote_root` - 远程根目录，本地按相对于该目录的路径保存，保持目录结构
+/// * `overall` - 总进度条，长度应为所有文件大小之和（见 `total_download_bytes`）
+/// * `download` - 实际执行下载的函数，参数为 (文件, 本地路径, 进度回调)
+///
+/// 返回下载失败的文件数
+fn download_files_with_progress<F>(
+    files: &[PcsFileItem],
+    remote_root: &str,
+    local: Option<&String>,
+    mode: ProgressMode,
+    mp: &MultiProgress,
+    overall: &ProgressBar,
+    mut download: F,
+) -> usize
+where
+    F: FnMut(&PcsFileItem, &str, DownloadProgressFn) -> Result<(), AppError>,
+{
+    if mode == ProgressMode::Bar {
+        overall.set_style(transfer_progress_style());
+    }
+    overall.set_message(format!("共 {} 个文件", files.len()));
+
+    let mut success = 0usize;
+    let mut failed = 0usize;
+    for file in files {
+        let remote_path = file.path();
+        let local_path = get_relative_local_path(remote_root, remote_path, local);
+        if let Some(parent) = Path::new(local_path.as_str()).parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                error!("创建目录失败: {} - {}", parent.display(), e);
+                failed += 1;
+                continue;
+            }
+        }
+        let pb = mp.add(new_progress_bar(mode, Some(*file.size())));
+        pb.set_message(format!("{} -> {}", remote_path, local_path));
+
+        let base = overall.position();
+        let pbm = pb.clone();
+        let overall_m = overall.clone();
+        let result = download(
+            file,
+            local_path.as_str(),
+            Box::new(move |downloaded, total| {
+                update_progress(&pbm, mode, downloaded, total);
+                overall_m.set_position(base + downloaded);
+            }),
+        );
+        // 无论成功与否，总进度都跳过该文件的全部字节
+        overall.set_position(base + *file.size());
+        match result {
+            Ok(_) => {
+                if mode == ProgressMode::Bar {
+                    pb.finish_and_clear();
+                } else {
+                    finish_progress(&pb, true, "下载完成");
                 }
+                success += 1;
+            }
+            Err(error) => {
+                finish_progress(
+                    &pb,
+                    false,
+                    format!("下载 {} 失败: {}", file.server_filename(), error.message),
+                );
+                error!("error: {:?}", error);
+                failed += 1;
             }
         }
     }
+    let summary = format!("下载完成: 成功 {}, 失败 {}", success, failed);
+    if mode != ProgressMode::Bar {
+        println!("{}", summary);
+    }
+    overall.finish_with_message(summary);
+    failed
+}
+
+/// 进度显示方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProgressMode {
+    /// 终端进度条
+    Bar,
+    /// 非交互输出（管道、重定向、cron）：隐藏进度条，每完成 10% 输出一行进度
+    Lines,
+    /// 静默：仅输出每个操作的结果
+    Quiet,
+}
+
+impl ProgressMode {
+    /// 根据 `--quiet` 与终端检测结果确定进度显示方式
+    pub(crate) fn detect(quiet: bool, interactive: bool) -> Self {
+        if quiet {
+            ProgressMode::Quiet
+        } else if interactive {
+            ProgressMode::Bar
+        } else {
+            ProgressMode::Lines
+        }
+    }
+}
+
+/// 标准输出与标准错误是否均连接到终端
+fn is_interactive_terminal() -> bool {
+    std::io::stdout().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// 创建传输进度条
+/// 非 `Bar` 模式下返回隐藏的进度条且不设置样式，结果由 `finish_progress` 以单行文本输出
+fn new_progress_bar(mode: ProgressMode, len: Option<u64>) -> ProgressBar {
+    if mode != ProgressMode::Bar {
+        let pb = ProgressBar::hidden();
+        if let Some(len) = len {
+            pb.set_length(len);
+        }
+        return pb;
+    }
+    let pb = match len {
+        Some(len) => ProgressBar::new(len),
+        None => ProgressBar::no_length(),
+    };
+    pb.set_style(transfer_progress_style());
+    pb
+}
+
+/// 更新进度条；`Lines` 模式下每跨过一个 10% 刻度输出一行进度
+fn update_progress(pb: &ProgressBar, mode: ProgressMode, position: u64, total: u64) {
+    let previous = pb.position();
+    // 保障长度一致
+    if pb.length() != Some(total) {
+        pb.set_length(total);
+    }
+    pb.set_position(position);
+    if mode == ProgressMode::Lines {
+        if let Some(percent) = crossed_percent_step(previous, position, total) {
+            println!("{} | {}%", pb.message(), percent);
+        }
+    }
+}
+
+/// 上传前计算校验和阶段的进度回调
+/// `Bar` 模式下进度条显示“计算校验和”及进度，计算完成后恢复原消息并归零，供后续上传进度使用
+fn hashing_progress(pb: &ProgressBar, mode: ProgressMode) -> impl FnMut(u64, u64) {
+    let pb = pb.clone();
+    let message = pb.message();
+    move |hashed, total| {
+        if mode != ProgressMode::Bar {
+            return;
+        }
+        if hashed >= total {
+            pb.set_message(message.clone());
+            pb.set_position(0);
+        } else {
+            pb.set_message(format!("计算校验和 | {}", message));
+            pb.set_length(total);
+            pb.set_position(hashed);
+        }
+    }
+}
+
+/// 进度从 `previous` 增长到 `current` 时跨过的最高 10% 刻度，未跨过刻度时返回 None
+fn crossed_percent_step(previous: u64, current: u64, total: u64) -> Option<u64> {
+    if total == 0 || current <= previous {
+        return None;
+    }
+    let step = |position: u64| position.min(total) as u128 * 10 / total as u128 * 10;
+    let (before, after) = (step(previous), step(current));
+    (after > before).then_some(after as u64)
+}
+
+/// 结束进度条并显示结果
+/// 进度条隐藏时（静默模式或非终端）改为输出一行 `{进度条消息} | {结果}`
+fn finish_progress(pb: &ProgressBar, success: bool, result: impl Into<String>) {
+    let result = result.into();
+    if pb.is_hidden() {
+        let line = format!("{} | {}", pb.message(), result);
+        if success {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    } else if success {
+        pb.finish_with_message(result);
+    } else {
+        pb.abandon_with_message(result);
+    }
+}
+
+/// 传输进度条的统一样式
+fn transfer_progress_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{bar:72.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} ETA {eta_precise} | {msg}", )
+        .unwrap()
+        .progress_chars("=>-")
+}
+
+/// 批量上传的总体进度：累计已处理字节数与耗时，用于估算总体速率与剩余时间
+/// 总字节数由后台线程统计，上传无需等待整棵目录树遍历完毕
+struct TransferStats {
+    total: Arc<OnceLock<u64>>,
+    excluded: Cell<u64>,
+    done: Cell<u64>,
+    failed: Cell<u64>,
+    failed_files: Cell<usize>,
+    started: Instant,
+}
+
+impl TransferStats {
+    /// 在后台线程中遍历 `files` 统计总字节数，统计完成前总量未知
+    fn counting<I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = String> + Send + 'static,
+    {
+        let total = Arc::new(OnceLock::new());
+        let counter = Arc::clone(&total);
+        std::thread::spawn(move || {
+            let _ = counter.set(total_local_bytes(files));
+        });
+        Self {
+            total,
+            excluded: Cell::new(0),
+            done: Cell::new(0),
+            failed: Cell::new(0),
+            failed_files: Cell::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    /// 需要处理的总字节数，后台统计尚未完成时返回 None
+    fn total(&self) -> Option<u64> {
+        self.total
+            .get()
+            .map(|total| total.saturating_sub(self.excluded.get() + self.failed.get()))
+    }
+
+    /// 一个文件上传成功后计入其字节数
+    fn add_done(&self, bytes: u64) {
+        self.done.set(self.done.get() + bytes);
+    }
+
+    /// 一个文件上传失败，单独计数并从总量中扣除，不计入已完成字节数
+    fn add_failed(&self, bytes: u64) {
+        self.failed.set(self.failed.get() + bytes);
+        self.failed_files.set(self.failed_files.get() + 1);
+    }
+
+    /// 从总量中排除无需上传的文件（如远程已存在）
+    fn exclude(&self, bytes: u64) {
+        self.excluded.set(self.excluded.get() + bytes);
+    }
+
+    fn summary(&self) -> String {
+        format_transfer_summary(
+            self.done.get(),
+            self.total(),
+            self.failed_files.get(),
+            self.started.elapsed(),
+        )
+    }
+}
+
+/// 非静默模式下输出一行总体进度
+/// `Bar` 模式下经由进度条输出，避免打乱终端上正在绘制的进度条
+fn print_overall_progress(pb: &ProgressBar, mode: ProgressMode, stats: &TransferStats) {
+    let line = format!("总进度: {}", stats.summary());
+    match mode {
+        ProgressMode::Bar => pb.println(line),
+        ProgressMode::Lines => println!("{}", line),
+        ProgressMode::Quiet => {}
+    }
+}
+
+/// 按平均速率估算剩余时间，速率未知（为 0）时返回 None
+fn estimate_eta(done: u64, total: u64, bytes_per_sec: f64) -> Option<Duration> {
+    if bytes_per_sec <= 0.0 {
+        return None;
+    }
+    let remaining = total.saturating_sub(done);
+    Some(Duration::from_secs_f64(remaining as f64 / bytes_per_sec))
+}
+
+/// 格式化剩余时间，如 `1h5m`、`56m`、`30s`
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    if secs >= 3600 {
+        format!("{}h{}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// 总体进度摘要，如 `12.3 GiB / 40.0 GiB, 8.2 MiB/s, ETA 56m`；总量未知时显示 `统计中`，有失败文件时追加失败数
+fn format_transfer_summary(
+    done: u64,
+    total: Option<u64>,
+    failed_files: usize,
+    elapsed: Duration,
+) -> String {
+    let human = |v: u64| {
+        let adj = byte_unit::Byte::from_u64(v).get_appropriate_unit(byte_unit::UnitType::Binary);
+        format!("{:.1} {}", adj.get_value(), adj.get_unit())
+    };
+    let rate = if elapsed.as_secs_f64() > 0.0 {
+        done as f64 / elapsed.as_secs_f64()
+    } else {
+        0.0
+    };
+    let eta = total
+        .and_then(|total| estimate_eta(done, total, rate))
+        .map(format_eta)
+        .unwrap_or_else(|| "--".to_string());
+    let mut summary = format!(
+        "{} / {}, {}/s, ETA {}",
+        human(done),
+        total.map(human).unwrap_or_else(|| "统计中".to_string()),
+        human(rate as u64),
+        eta
+    );
+    if failed_files > 0 {
+        summary.push_str(&format!(", 失败 {} 个文件", failed_files));
+    }
+    summary
+}
+
+/// 计算本地文件的总字节数
+fn total_local_bytes<I>(files: I) -> u64
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    files
+        .into_iter()
+        .filter_map(|file| fs::metadata(file).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// 计算批量下载的总字节数（目录不计入）
+fn total_download_bytes(files: &[PcsFileItem]) -> u64 {
+    files
+        .iter()
+        .filter(|file| *file.is_dir() != 1)
+        .map(|file| *file.size())
+        .sum()
 }
 
 pub(crate) fn resolve_remote_path(
@@ -239,48 +940,601 @@ pub(crate) fn resolve_remote_path(
                 // 目录不为空，返回目录下的所有文件路径
                 Right(files.list().to_vec())
             }
-        }
-        Err(_) => {
-            // 列出目录失败，说明可能是文件
-            Left(remote.to_string())
-        }
+        }
+        Err(_) => {
+            // 列出目录失败，说明可能是文件
+            Left(remote.to_string())
+        }
+    }
+}
+
+/// 判断远程路径是否为目录：列出其父目录的全部条目（自动分页）并查找对应条目
+fn remote_is_dir(client: &BaiduPcsClient, remote: &str) -> Result<bool, AppError> {
+    let path = remote.trim_end_matches('/');
+    if path.is_empty() {
+        return Ok(true);
+    }
+    let parent = Path::new(path)
+        .parent()
+        .and_then(|p| p.to_str())
+        .filter(|p| !p.is_empty())
+        .unwrap_or("/");
+    client
+        .list_dir_all(parent)?
+        .iter()
+        .find(|item| item.path() == path)
+        .map(|item| *item.is_dir() == 1)
+        .ok_or_else(|| {
+            AppError::new(
+                AppErrorType::Client,
+                format!("远程路径不存在: {}", remote).as_str(),
+                None,
+            )
+        })
+}
+
+/// 递归删除目录的提交顺序：子条目按层级从深到浅分批，目录本身最后单独一批
+/// 保证每个目录在其下所有条目删除之后才被删除
+fn delete_order(dir: &str, children: &[PcsFileItem]) -> Vec<Vec<String>> {
+    let depth = |path: &str| path.trim_end_matches('/').matches('/').count();
+    let mut levels: Vec<(usize, Vec<String>)> = Vec::new();
+    for child in children {
+        let d = depth(child.path());
+        match levels.iter_mut().find(|(level, _)| *level == d) {
+            Some((_, paths)) => paths.push(child.path().clone()),
+            None => levels.push((d, vec![child.path().clone()])),
+        }
+    }
+    levels.sort_by_key(|(level, _)| std::cmp::Reverse(*level));
+    let mut batches: Vec<Vec<String>> = levels.into_iter().map(|(_, paths)| paths).collect();
+    batches.push(vec![dir.to_string()]);
+    batches
+}
+
+/// 提交一批删除，输出失败的条目，返回失败数（请求本身失败时视为整批失败）
+fn submit_delete<D>(delete: &mut D, batch: &[String]) -> usize
+where
+    D: FnMut(&[String]) -> Result<PcsFileTaskOperationResult, AppError>,
+{
+    match delete(batch) {
+        Ok(res) => {
+            let failures = res.failures();
+            for task in &failures {
+                eprintln!("删除失败: {} (errno {})", task.path(), task.errno());
+            }
+            failures.len()
+        }
+        Err(e) => {
+            eprintln!("删除失败: {}", e);
+            batch.len()
+        }
+    }
+}
+
+/// 删除远程路径：文件直接删除，目录仅在 `recursive` 时删除，否则拒绝
+/// 无法判断路径类型（如路径不存在或列目录失败）时跳过该路径，避免未指定 `recursive` 时误删目录
+/// 提交删除前调用 `confirm(待删除路径, [(目录, 目录下条目数)])`，返回 false 时取消删除
+/// 递归删除目录时先按 `delete_order` 由深到浅删除其下条目，全部成功后再删除目录本身；
+/// 有条目删除失败时停止删除该目录，避免部分失败被上层目录的删除掩盖
+/// 返回 (实际提交删除的路径, 失败项数)，失败项包括被跳过的路径与删除失败的条目
+fn remove_remote_paths<S, L, C, D>(
+    paths: &[String],
+    recursive: bool,
+    mut is_dir: S,
+    mut list_children: L,
+    mut confirm: C,
+    mut delete: D,
+) -> (Vec<String>, usize)
+where
+    S: FnMut(&str) -> Result<bool, AppError>,
+    L: FnMut(&str) -> Result<Vec<PcsFileItem>, AppError>,
+    C: FnMut(&[String], &[(String, Option<usize>)]) -> bool,
+    D: FnMut(&[String]) -> Result<PcsFileTaskOperationResult, AppError>,
+{
+    let mut targets = Vec::new();
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut skipped = 0;
+    for path in paths {
+        match is_dir(path) {
+            Ok(true) if !recursive => {
+                eprintln!("{} 是目录，请使用 -r 删除目录", path);
+                skipped += 1;
+            }
+            Ok(true) => {
+                targets.push(path.clone());
+                dirs.push((path.clone(), list_children(path).ok()));
+            }
+            Ok(false) => {
+                targets.push(path.clone());
+                files.push(path.clone());
+            }
+            Err(e) => {
+                eprintln!("无法判断 {} 是否为目录，已跳过删除: {}", path, e);
+                skipped += 1;
+            }
+        }
+    }
+    if targets.is_empty() {
+        return (targets, skipped);
+    }
+    let summary: Vec<(String, Option<usize>)> = dirs
+        .iter()
+        .map(|(dir, children)| (dir.clone(), children.as_ref().map(|c| c.len())))
+        .collect();
+    if !confirm(&targets, &summary) {
+        println!("已取消");
+        return (Vec::new(), skipped);
+    }
+    let mut failed = 0;
+    if !files.is_empty() {
+        failed += submit_delete(&mut delete, &files);
+    }
+    for (dir, children) in &dirs {
+        let batches = delete_order(dir, children.as_deref().unwrap_or_default());
+        let (dir_batch, child_batches) = batches.split_last().unwrap();
+        // 某一层有失败时停止，上层目录的删除会连带删除失败的条目
+        let child_failed = child_batches
+            .iter()
+            .map(|batch| submit_delete(&mut delete, batch))
+            .find(|failed| *failed > 0)
+            .unwrap_or(0);
+        if child_failed > 0 {
+            eprintln!(
+                "目录 {} 中有 {} 项删除失败，已跳过删除该目录",
+                dir, child_failed
+            );
+            failed += child_failed;
+            continue;
+        }
+        failed += submit_delete(&mut delete, dir_batch);
+    }
+    if failed == 0 {
+        println!("删除成功: {} 项", targets.len());
+    } else {
+        eprintln!("删除完成，{} 项失败", failed);
+    }
+    (targets, skipped + failed)
+}
+
+/// 规范化后的远程路径各级名称，忽略重复的 `/` 与 `.`，`..` 回到上一级
+fn remote_path_parts(path: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            name => parts.push(name),
+        }
+    }
+    parts
+}
+
+/// 是否为网盘根目录 `/` 或存放各应用目录的 `/apps`
+pub(crate) fn is_disk_root(path: &str) -> bool {
+    matches!(remote_path_parts(path).as_slice(), [] | ["apps"])
+}
+
+/// 是否为网盘根目录、`/apps` 或应用根目录 `app_root`（如 `/apps/myapp`）
+pub(crate) fn is_protected_root(path: &str, app_root: &str) -> bool {
+    is_disk_root(path) || remote_path_parts(path) == remote_path_parts(app_root)
+}
+
+/// 未指定 `--force-root` 时拒绝对根目录（由 `is_root` 判断）执行 `action`，防止误操作整个网盘或应用目录
+/// `paths` 须为已按当前目录解析的绝对路径
+pub(crate) fn refuse_root_paths<'a, I, F>(
+    paths: I,
+    force_root: bool,
+    is_root: F,
+    action: &str,
+) -> Result<(), AppError>
+where
+    I: IntoIterator<Item = &'a str>,
+    F: Fn(&str) -> bool,
+{
+    if force_root {
+        return Ok(());
+    }
+    let roots: Vec<&str> = paths.into_iter().filter(|path| is_root(path)).collect();
+    if roots.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::new(
+        AppErrorType::Client,
+        format!(
+            "拒绝{} {}：该路径为网盘根目录或应用根目录，确认无误请添加 --force-root",
+            action,
+            roots.join(", ")
+        )
+        .as_str(),
+        None,
+    ))
+}
+
+/// 删除远程文件/目录
+pub(crate) fn run_remove_task(args: &RmArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let (_, failed) = remove_remote_paths(
+        &args.remote,
+        args.recursive,
+        |path| remote_is_dir(client, path),
+        |path| client.list_dir_recursive(path, None),
+        |targets, dirs| {
+            println!("即将删除网盘文件:");
+            for path in targets {
+                match dirs.iter().find(|(dir, _)| dir == path) {
+                    Some((_, Some(count))) => println!("  {} (目录，包含 {} 项)", path, count),
+                    Some((_, None)) => println!("  {} (目录)", path),
+                    None => println!("  {}", path),
+                }
+            }
+            args.yes || confirm_prompt(std::io::stdin().lock())
+        },
+        // 同步删除，保证子条目在目录之前删除完成
+        |paths| client.delete(paths, Some(true)),
+    );
+    failures_to_result(failed, "路径未能删除")
+}
+
+/// 询问用户是否继续，仅输入 y/yes 时返回 true
+fn confirm_prompt<R: std::io::BufRead>(mut input: R) -> bool {
+    println!("是否继续? [y/N] ");
+    let mut line = String::new();
+    if input.read_line(&mut line).is_err() {
+        eprintln!("读取输入失败");
+        return false;
+    }
+    let answer = line.trim();
+    answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+}
+
+/// 显示远程文件元信息
+pub(crate) fn run_stat_task(args: &StatArgs, client: &BaiduPcsClient) -> Result<(), AppError> {
+    let meta = match (args.fs_id, args.remote.as_deref()) {
+        (Some(fs_id), _) => client.stat_by_id(fs_id, args.dlink),
+        (None, Some(remote)) => client.stat(remote, args.dlink),
+        (None, None) => unreachable!("clap 已保证 remote 与 --fs-id 至少提供一个"),
+    }?;
+    println!("{}", format_file_meta(&meta));
+    Ok(())
+}
+
+/// 文件类型编号对应的名称
+fn category_name(category: i32) -> &'static str {
+    match category {
+        1 => "视频",
+        2 => "音频",
+        3 => "图片",
+        4 => "文档",
+        5 => "应用",
+        7 => "种子",
+        _ => "其他",
+    }
+}
+
+/// 将 Unix 时间戳格式化为本地时间
+fn format_local_time(timestamp: i64) -> String {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// 格式化文件元信息，每行一个字段
+fn format_file_meta(meta: &PcsFileMetaInfo) -> String {
+    let mut lines = vec![format!("文件名: {}", meta.filename())];
+    if let Some(path) = meta.path() {
+        lines.push(format!("路径: {}", path));
+    }
+    lines.push(format!(
+        "类型: {}",
+        if *meta.is_dir() == 1 {
+            "目录"
+        } else {
+            category_name(*meta.category())
+        }
+    ));
+    lines.push(format!("大小: {} 字节", meta.size()));
+    if let Some(md5) = meta.md5() {
+        lines.push(format!("MD5: {}", md5));
+    }
+    lines.push(format!(
+        "创建时间: {}",
+        format_local_time(*meta.server_ctime())
+    ));
+    lines.push(format!(
+        "修改时间: {}",
+        format_local_time(*meta.server_mtime())
+    ));
+    if let Some(dlink) = meta.dlink() {
+        lines.push(format!("下载地址: {}", dlink));
+    }
+    lines.join("\n")
+}
+
+/// 递归列出远程目录下所有文件路径
+fn list_remote_files_recursive(client: &BaiduPcsClient, dir: &str) -> HashSet<String> {
+    let mut result = HashSet::new();
+    if let Ok(files) = client.list_dir(dir) {
+        for item in files.list() {
+            if *item.is_dir() == 1 {
+                let sub = list_remote_files_recursive(client, item.path());
+                result.extend(sub);
+            } else {
+                result.insert(item.path().to_string());
+            }
+        }
+    }
+    result
+}
+
+/// 备份断点：记录本轮备份中已完成的本地文件，以 `--resume` 重新运行时跳过这些文件
+/// 每个文件按 路径 + 大小 + 修改时间 记录，完成后又被修改的文件不会被跳过
+pub(crate) struct BackupCheckpoint {
+    path: PathBuf,
+    done: HashSet<String>,
+}
+
+/// 断点中记录的文件标识 `{大小}\t{修改时间(纳秒)}\t{路径}`，无法读取文件信息时为 None
+fn checkpoint_key(file: &str) -> Option<String> {
+    let meta = fs::metadata(file).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some(format!("{}\t{}\t{}", meta.len(), mtime, file))
+}
+
+impl BackupCheckpoint {
+    /// 加载断点文件（每行一个 `checkpoint_key`），文件不存在时为空
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let done = fs::read_to_string(&path)
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        BackupCheckpoint { path, done }
+    }
+
+    /// 文件是否已在断点中记录为完成，且此后未被修改
+    pub(crate) fn is_done(&self, file: &str) -> bool {
+        checkpoint_key(file).is_some_and(|key| self.done.contains(&key))
+    }
+
+    /// 已完成的文件数
+    pub(crate) fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    /// 记录文件已完成，并立即追加写入断点文件，保证进程中断时不丢失
+    pub(crate) fn mark_done(&mut self, file: &str) {
+        use std::io::Write;
+        let Some(key) = checkpoint_key(file) else {
+            return;
+        };
+        if !self.done.insert(key.clone()) {
+            return;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| writeln!(f, "{}", key));
+        if let Err(e) = written {
+            error!("写入备份断点失败: {} - {}", self.path.display(), e);
+        }
+    }
+
+    /// 清空断点并删除断点文件
+    pub(crate) fn clear(&mut self) {
+        self.done.clear();
+        if self.path.exists() {
+            if let Err(e) = fs::remove_file(&self.path) {
+                error!("删除备份断点失败: {} - {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// 备份源下符合过滤规则的文件，边遍历边产出
+fn backup_files(
+    local_root: &str,
+    local_path: PathBuf,
+    filter: BackupFilter,
+) -> impl Iterator<Item = String> + Send + 'static {
+    scan_files(local_root).filter(move |file| {
+        let file_path = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+        filter.is_match(&backup_relative_path(&local_path, &file_path))
+    })
+}
+
+/// 备份文件过滤规则，`include` 为空时不限制，`exclude` 优先
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct BackupFilter {
+    pub(crate) include: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+}
+
+impl BackupFilter {
+    /// `relative` 为文件相对备份源目录的路径，以 `/` 分隔
+    /// 模式不含 `/` 时只匹配文件名，否则匹配整个相对路径
+    pub(crate) fn is_match(&self, relative: &str) -> bool {
+        let hit = |pattern: &String| {
+            let pattern = pattern.trim_start_matches('/');
+            if pattern.contains('/') {
+                glob_match(pattern, relative)
+            } else {
+                glob_match(pattern, relative.rsplit('/').next().unwrap_or(relative))
+            }
+        };
+        !self.exclude.iter().any(hit) && (self.include.is_empty() || self.include.iter().any(hit))
     }
 }
 
-/// 递归列出远程目录下所有文件路径
-fn list_remote_files_recursive(client: &BaiduPcsClient, dir: &str) -> HashSet<String> {
-    let mut result = HashSet::new();
-    if let Ok(files) = client.list_dir(dir) {
-        for item in files.list() {
-            if *item.is_dir() == 1 {
-                let sub = list_remote_files_recursive(client, item.path());
-                result.extend(sub);
-            } else {
-                result.insert(item.path().to_string());
+/// 简单的通配符匹配：`*` 匹配不含 `/` 的任意字符，`**` 可跨越目录，`?` 匹配单个非 `/` 字符
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(p: &[char], t: &[char]) -> bool {
+        match p.first() {
+            None => t.is_empty(),
+            Some('*') if p.get(1) == Some(&'*') => {
+                // `**/` 也可匹配零级目录
+                let rest = &p[2..];
+                if rest.first() == Some(&'/') && matches(&rest[1..], t) {
+                    return true;
+                }
+                (0..=t.len()).any(|i| matches(rest, &t[i..]))
             }
+            Some('*') => (0..=t.len())
+                .take_while(|&i| i == 0 || t[i - 1] != '/')
+                .any(|i| matches(&p[1..], &t[i..])),
+            Some('?') => t.first().is_some_and(|c| *c != '/') && matches(&p[1..], &t[1..]),
+            Some(c) => t.first() == Some(c) && matches(&p[1..], &t[1..]),
         }
     }
-    result
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    matches(&p, &t)
+}
+
+/// 文件相对备份源的路径（以 `/` 分隔），备份单个文件时为文件名
+fn backup_relative_path(source: &Path, file: &Path) -> String {
+    match file.strip_prefix(source) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        _ => file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// 一个备份任务：本地源、远程目标、路径映射、过滤规则与策略
+pub(crate) struct BackupTask {
+    pub(crate) local_root: String,
+    pub(crate) remote_root: String,
+    pub(crate) mapping: PathMapping,
+    pub(crate) filter: BackupFilter,
+    pub(crate) policy: BackupPolicy,
+    pub(crate) checkpoint_path: PathBuf,
+}
+
+impl BackupTask {
+    /// 由命令行参数构造，`--rm` 对应 `BackupPolicy::Move`
+    pub(crate) fn from_args(
+        args: &BackupArgs,
+        local_root: &str,
+        remote_root: &str,
+        checkpoint_path: PathBuf,
+    ) -> Result<Self, AppError> {
+        Ok(BackupTask {
+            local_root: local_root.to_string(),
+            remote_root: remote_root.to_string(),
+            mapping: backup_mapping(args, local_root)?,
+            filter: BackupFilter::default(),
+            policy: if args.remove_source {
+                BackupPolicy::Move
+            } else {
+                BackupPolicy::Skip
+            },
+            checkpoint_path,
+        })
+    }
+
+    /// 由配置文件中的备份任务构造，命令行的 `--rm` 与路径映射参数同样生效
+    pub(crate) fn from_job(
+        args: &BackupArgs,
+        job: &BackupJob,
+        checkpoint_path: PathBuf,
+    ) -> Result<Self, AppError> {
+        let local_root = expand_local_path(job.local_path.as_str());
+        Ok(BackupTask {
+            mapping: backup_mapping(args, &local_root)?,
+            local_root,
+            remote_root: job.remote_path.clone(),
+            filter: BackupFilter {
+                include: job.include.clone(),
+                exclude: job.exclude.clone(),
+            },
+            policy: if args.remove_source {
+                BackupPolicy::Move
+            } else {
+                job.policy
+            },
+            checkpoint_path,
+        })
+    }
+}
+
+/// 默认备份目录时只上传目录内容，备份单个文件时上传到远程目录下
+fn backup_mapping(args: &BackupArgs, local_root: &str) -> Result<PathMapping, AppError> {
+    let default_mapping = if Path::new(local_root).is_dir() {
+        PathMapping::StripComponents(1)
+    } else {
+        PathMapping::StripComponents(0)
+    };
+    PathMapping::from_args(args.strip_components, args.base.as_ref(), default_mapping)
 }
 
-/// backup 模式：扫描本地文件，跳过远程已存在的，仅上传缺失的
+/// backup 模式：依次执行各备份任务，扫描本地文件，跳过远程已存在的，仅上传缺失的
 /// daemon 模式下持续监控，每隔一段时间重新扫描
+/// 备份进度记录在各任务的 `checkpoint_path`，中断后以 `--resume` 重新运行时跳过已完成且未修改的文件，
+/// `--fresh` 清除断点重新开始；本轮全部成功后清除断点
 pub(crate) fn run_backup_task(
     args: &BackupArgs,
-    local_root: &str,
-    remote_root: &str,
+    tasks: &[BackupTask],
     client: &BaiduPcsClient,
-) {
-    let remove_source = args.remove_source;
-    let daemon = args.daemon;
-    let local_root = local_root.to_string();
-    let remote_root = remote_root.to_string();
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mode = ProgressMode::detect(quiet, is_interactive_terminal());
+    let mut checkpoints: Vec<BackupCheckpoint> = tasks
+        .iter()
+        .map(|task| {
+            let mut checkpoint = BackupCheckpoint::load(task.checkpoint_path.clone());
+            if args.fresh {
+                checkpoint.clear();
+            } else if checkpoint.len() > 0 && args.resume {
+                println!(
+                    "从断点继续: 跳过上次已完成且未修改的文件（共记录 {} 个）",
+                    checkpoint.len()
+                );
+            } else if checkpoint.len() > 0 {
+                println!(
+                    "发现上次未完成的备份断点（{} 个文件），可使用 --resume 跳过其中已完成的文件",
+                    checkpoint.len()
+                );
+            }
+            checkpoint
+        })
+        .collect();
 
     loop {
-        do_backup(&local_root, &remote_root, remove_source, client);
+        let mut failed = 0;
+        for (task, checkpoint) in tasks.iter().zip(checkpoints.iter_mut()) {
+            if tasks.len() > 1 {
+                println!("备份任务: {} -> {}", task.local_root, task.remote_root);
+            }
+            if let Err(e) = do_backup(task, client, mode, checkpoint, args.resume) {
+                eprintln!("{}", e);
+                failed += 1;
+            }
+        }
 
-        if !daemon {
-            break;
+        if !args.daemon {
+            return failures_to_result(failed, "备份任务未全部完成");
         }
         let interval = std::time::Duration::from_secs(60);
         info!("守护模式: 等待 {} 秒后再次扫描...", interval.as_secs());
@@ -288,94 +1542,131 @@ pub(crate) fn run_backup_task(
     }
 }
 
-fn do_backup(local_root: &str, remote_root: &str, remove_source: bool, client: &BaiduPcsClient) {
-
-    let local_path = PathBuf::from(local_root)
-        .canonicalize()
-        .expect("本地路径不存在");
-    let local_base = if local_path.is_dir() {
-        local_path.clone()
-    } else {
-        local_path.parent().unwrap().to_path_buf()
+/// `resume` 为 true 时跳过断点中已完成的文件
+fn do_backup(
+    task: &BackupTask,
+    client: &BaiduPcsClient,
+    mode: ProgressMode,
+    checkpoint: &mut BackupCheckpoint,
+    resume: bool,
+) -> Result<(), AppError> {
+    let remote_root = task.remote_root.as_str();
+    let Ok(local_path) = PathBuf::from(&task.local_root).canonicalize() else {
+        return Err(AppError::new(
+            AppErrorType::Client,
+            format!("本地路径不存在: {}", task.local_root).as_str(),
+            None,
+        ));
     };
 
-    let scanned = scan_files_recursive(local_root, vec![]);
-    if scanned.is_empty() {
-        println!("没有找到需要备份的本地文件");
-        return;
-    }
-
-    // 列出远程目录已有文件
-    println!("正在检查远程目录 {} ...", remote_root);
-    let existing = list_remote_files_recursive(client, remote_root);
-
+    // 边扫描边上传，总字节数由后台线程另行统计
+    let stats = TransferStats::counting(backup_files(
+        &task.local_root,
+        local_path.clone(),
+        task.filter.clone(),
+    ));
+    let mut scanned = 0usize;
     let mut skipped = 0usize;
     let mut uploaded = 0usize;
+    let mut failed = 0usize;
+    // 远程目录已有文件，遇到第一个需要上传的文件时才列出
+    let mut existing: Option<HashSet<String>> = None;
 
-    for file in &scanned {
-        let file_path = PathBuf::from(file);
-        let relative = file_path.strip_prefix(&local_base).unwrap_or(&file_path);
-        let remote_path = PathBuf::from(remote_root)
-            .join(relative)
-            .to_string_lossy()
-            .to_string();
+    for file in backup_files(&task.local_root, local_path.clone(), task.filter.clone()) {
+        scanned += 1;
+        let file = file.as_str();
+        let file_size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        if resume && checkpoint.is_done(file) {
+            stats.exclude(file_size);
+            skipped += 1;
+            continue;
+        }
+        let file_path = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+        let Some(remote_path) = task
+            .mapping
+            .remote_path(&local_path, &file_path, remote_root)
+        else {
+            warn!(
+                "按路径映射规则 {:?} 无法确定远程路径，跳过: {}",
+                task.mapping, file
+            );
+            if !warnings_on_stderr() {
+                eprintln!("警告: 无法确定远程路径，跳过: {}", file);
+            }
+            stats.exclude(file_size);
+            skipped += 1;
+            continue;
+        };
 
+        let existing = existing.get_or_insert_with(|| {
+            if task.policy == BackupPolicy::Overwrite {
+                HashSet::new()
+            } else {
+                println!("正在检查远程目录 {} ...", remote_root);
+                list_remote_files_recursive(client, remote_root)
+            }
+        });
         if existing.contains(&remote_path) {
             info!("跳过已存在: {}", remote_path);
+            checkpoint.mark_done(file);
+            stats.exclude(file_size);
             skipped += 1;
             continue;
         }
 
-        let file_size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
-        let pb = ProgressBar::new(file_size);
-        pb.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:72.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} ETA {eta_precise} | {msg}",
-            )
-            .unwrap()
-            .progress_chars("=>-"),
-        );
+        let pb = new_progress_bar(mode, Some(file_size));
         pb.set_message(format!("{} -> {}", file, remote_path));
 
-        let result = client.upload_large_file(
-            file.as_str(),
+        let result = client.upload_large_file_with_hash_progress(
+            file,
             remote_path.as_str(),
             PcsUploadPolicy::Overwrite,
+            hashing_progress(&pb, mode),
             {
                 let pb = pb.clone();
-                move |p| {
-                    if pb.length().unwrap_or(0) != p.total_bytes {
-                        pb.set_length(p.total_bytes);
-                    }
-                    pb.set_position(p.uploaded_bytes);
-                }
+                move |p| update_progress(&pb, mode, p.uploaded_bytes, p.total_bytes)
             },
         );
         match result {
             Ok(_) => {
-                pb.finish_with_message("上传完成");
+                finish_progress(&pb, true, "上传完成");
+                stats.add_done(file_size);
+                print_overall_progress(&pb, mode, &stats);
+                checkpoint.mark_done(file);
                 uploaded += 1;
-                if remove_source {
+                if task.policy == BackupPolicy::Move {
                     if let Err(e) = fs::remove_file(file) {
                         error!("删除本地文件失败: {} - {}", file, e);
-                        eprintln!("警告: 上传成功但删除本地文件失败: {}", file);
+                        if !warnings_on_stderr() {
+                            eprintln!("警告: 上传成功但删除本地文件失败: {}", file);
+                        }
                     } else {
                         info!("已删除本地文件: {}", file);
                     }
                 }
             }
             Err(err) => {
-                pb.abandon_with_message("上传失败");
+                finish_progress(&pb, false, "上传失败");
+                stats.add_failed(file_size);
+                print_overall_progress(&pb, mode, &stats);
                 error!("备份失败: {} -> {} : {:?}", file, remote_path, err);
+                failed += 1;
             }
         }
     }
+    if scanned == 0 {
+        println!("没有找到需要备份的本地文件");
+        return Ok(());
+    }
+    // 本轮全部完成后清除断点，下次备份重新检查所有文件
+    if failed == 0 {
+        checkpoint.clear();
+    }
     println!(
         "备份完成: 共 {} 个文件, 上传 {}, 跳过 {}",
-        scanned.len(),
-        uploaded,
-        skipped
+        scanned, uploaded, skipped
     );
+    failures_to_result(failed, "文件备份失败")
 }
 
 /// 从百度网盘分享链接中提取 short_url 和提取码
@@ -451,7 +1742,12 @@ fn list_share_files_recursive(
 }
 
 /// 下载分享链接文件到本地
-pub(crate) fn run_wget_task(args: &WgetArgs, client: &BaiduPcsClient) {
+pub(crate) fn run_wget_task(
+    args: &WgetArgs,
+    client: &BaiduPcsClient,
+    quiet: bool,
+) -> Result<(), AppError> {
+    let mode = ProgressMode::detect(quiet, is_interactive_terminal());
     let (short_url, url_pwd) = extract_short_url(&args.share_url);
     let output_dir = args.output.as_deref().unwrap_or(".");
     // -p 参数优先，其次从链接中提取的 pwd
@@ -460,13 +1756,9 @@ pub(crate) fn run_wget_task(args: &WgetArgs, client: &BaiduPcsClient) {
     // 1. 验证提取码，获取 spwd
     println!("正在验证分享链接...");
     let verify_res = client.share_verify(&short_url, password);
-    let spwd = match verify_res {
-        Ok(res) => res.data().spwd().clone(),
-        Err(e) => {
-            eprintln!("验证分享提取码失败: {}", e);
-            return;
-        }
-    };
+    let spwd = verify_res
+        .map(|res| res.data().spwd().clone())
+        .map_err(crate::with_context("验证分享提取码失败"))?;
     info!("分享提取码验证成功");
 
     // 2. 获取分享文件列表（根目录）
@@ -474,46 +1766,47 @@ pub(crate) fn run_wget_task(args: &WgetArgs, client: &BaiduPcsClient) {
     let files = list_share_files_recursive(client, &short_url, &spwd, "/");
     if files.is_empty() {
         // 尝试根目录文件列表（有些分享根目录就是文件）
-        let root_res = client.share_list(&short_url, &spwd, None, None, None);
-        match root_res {
-            Ok(list) => {
-                if list.data().list().is_empty() {
-                    eprintln!("分享链接中没有找到文件");
-                    return;
-                }
-                // 处理根目录中的文件
-                download_share_files(
-                    client,
-                    &short_url,
-                    &spwd,
-                    &list
-                        .data()
-                        .list()
-                        .iter()
-                        .map(|f| (f.clone(), "/".to_string()))
-                        .collect::<Vec<_>>(),
-                    output_dir,
-                );
-            }
-            Err(e) => {
-                eprintln!("获取分享文件列表失败: {}", e);
-            }
+        let list = client
+            .share_list(&short_url, &spwd, None, None, None)
+            .map_err(crate::with_context("获取分享文件列表失败"))?;
+        if list.data().list().is_empty() {
+            return Err(AppError::new(
+                AppErrorType::Client,
+                "分享链接中没有找到文件",
+                None,
+            ));
         }
-        return;
+        // 处理根目录中的文件
+        let failed = download_share_files(
+            client,
+            &short_url,
+            &spwd,
+            &list
+                .data()
+                .list()
+                .iter()
+                .map(|f| (f.clone(), "/".to_string()))
+                .collect::<Vec<_>>(),
+            output_dir,
+            mode,
+        );
+        return failures_to_result(failed, "文件下载失败");
     }
 
     println!("共找到 {} 个文件，开始下载...", files.len());
-    download_share_files(client, &short_url, &spwd, &files, output_dir);
+    let failed = download_share_files(client, &short_url, &spwd, &files, output_dir, mode);
+    failures_to_result(failed, "文件下载失败")
 }
 
-/// 下载分享文件列表中的每个文件
+/// 下载分享文件列表中的每个文件，返回失败的文件数
 fn download_share_files(
     client: &BaiduPcsClient,
     short_url: &str,
     spwd: &str,
     files: &[(ShareFileInfo, String)],
     output_dir: &str,
-) {
+    mode: ProgressMode,
+) -> usize {
     let mut success = 0usize;
     let mut failed = 0usize;
 
@@ -557,14 +1850,7 @@ fn download_share_files(
         match download_res {
             Ok(res) => {
                 if let Some(dlink) = res.data().dlink() {
-                    let pb = ProgressBar::no_length();
-                    pb.set_style(
-                        ProgressStyle::with_template(
-                            "{spinner:.green} [{elapsed_precise}] [{bar:72.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} ETA {eta_precise} | {msg}",
-                        )
-                        .unwrap()
-                        .progress_chars("=>-"),
-                    );
+                    let pb = new_progress_bar(mode, None);
                     pb.set_message(format!("下载 {}", relative_path));
 
                     let pbm = pb.clone();
@@ -572,17 +1858,16 @@ fn download_share_files(
                         dlink,
                         local_path.to_str().unwrap_or("."),
                         Some(move |downloaded, total| {
-                            pbm.set_length(total);
-                            pbm.set_position(downloaded);
+                            update_progress(&pbm, mode, downloaded, total)
                         }),
                     );
                     match result {
                         Ok(_) => {
-                            pb.finish_with_message(format!("下载完成: {}", relative_path));
+                            finish_progress(&pb, true, format!("下载完成: {}", relative_path));
                             success += 1;
                         }
                         Err(e) => {
-                            pb.abandon_with_message(format!("下载失败: {}", relative_path));
+                            finish_progress(&pb, false, format!("下载失败: {}", relative_path));
                             error!("下载文件失败: {} - {}", relative_path, e);
                             failed += 1;
                         }
@@ -599,16 +1884,700 @@ fn download_share_files(
         }
     }
     println!("下载完成: 成功 {}, 失败 {}", success, failed);
+    failed
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::sync::scan_files_recursive;
+    use crate::sync::{
+        backup_relative_path, confirm_prompt, crossed_percent_step, download_files_with_progress,
+        estimate_eta, expand_local_path, format_eta, format_file_meta, format_local_time,
+        format_transfer_summary, get_relative_local_path, glob_match, is_disk_root,
+        is_protected_root, new_progress_bar, parse_since, refuse_root_paths, remove_remote_paths,
+        scan_files, total_download_bytes, upload_done_message, BackupCheckpoint, BackupFilter,
+        PathMapping, ProgressMode, TransferStats,
+    };
+    use baidu_pcs_rs_sdk::baidu_pcs_sdk::{
+        AppError, AppErrorType, PcsFileItem, PcsFileUploadResult,
+    };
+    use chrono::TimeZone;
+    use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_estimate_eta() {
+        const MB: u64 = 1024 * 1024;
+        assert_eq!(
+            estimate_eta(10 * MB, 40 * MB, MB as f64),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            estimate_eta(40 * MB, 40 * MB, MB as f64),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(estimate_eta(0, 40 * MB, 0.0), None);
+
+        assert_eq!(format_eta(Duration::from_secs(30)), "30s");
+        assert_eq!(format_eta(Duration::from_secs(56 * 60 + 10)), "56m");
+        assert_eq!(format_eta(Duration::from_secs(3900)), "1h5m");
+
+        assert_eq!(
+            format_transfer_summary(10 * MB, Some(40 * MB), 0, Duration::from_secs(10)),
+            "10.0 MiB / 40.0 MiB, 1.0 MiB/s, ETA 30s"
+        );
+        assert_eq!(
+            format_transfer_summary(0, Some(40 * MB), 0, Duration::ZERO),
+            "0.0 B / 40.0 MiB, 0.0 B/s, ETA --"
+        );
+        assert_eq!(
+            format_transfer_summary(10 * MB, None, 0, Duration::from_secs(10)),
+            "10.0 MiB / 统计中, 1.0 MiB/s, ETA --"
+        );
+        assert_eq!(
+            format_transfer_summary(10 * MB, Some(30 * MB), 2, Duration::from_secs(10)),
+            "10.0 MiB / 30.0 MiB, 1.0 MiB/s, ETA 20s, 失败 2 个文件"
+        );
+    }
+
+    #[test]
+    fn test_transfer_stats_counts_in_background() {
+        let dir = env::temp_dir().join(format!("pcs-test-stats-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "12345").unwrap();
+        std::fs::write(dir.join("b.txt"), "123").unwrap();
+
+        let stats = TransferStats::counting(scan_files(dir.to_str().unwrap()));
+        stats.exclude(3);
+        stats.add_failed(1);
+        let started = std::time::Instant::now();
+        while stats.total().is_none() && started.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stats.total(), Some(4));
+        stats.add_done(4);
+        assert!(stats.summary().ends_with(", 失败 1 个文件"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_remote_paths_requires_recursive_for_dir() {
+        let paths = vec!["/apps/x/file.txt".to_string(), "/apps/x/dir".to_string()];
+        let is_dir = |path: &str| Ok(path.ends_with("dir"));
+        let yes = |_: &[String], _: &[(String, Option<usize>)]| true;
+        let no_children = |_: &str| Ok(vec![]);
+        let mut calls: Vec<Vec<String>> = Vec::new();
+        let (removed, failed) =
+            remove_remote_paths(&paths, false, is_dir, no_children, yes, |batch| {
+                calls.push(batch.to_vec());
+                Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+            });
+        assert_eq!(removed, vec!["/apps/x/file.txt".to_string()]);
+        // 未指定 -r 的目录计为失败
+        assert_eq!(failed, 1);
+        assert_eq!(calls, vec![vec!["/apps/x/file.txt".to_string()]]);
+
+        let mut calls = 0;
+        let (removed, failed) =
+            remove_remote_paths(&paths[1..], false, is_dir, no_children, yes, |_| {
+                calls += 1;
+                Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+            });
+        assert!(removed.is_empty());
+        assert_eq!(failed, 1);
+        assert_eq!(calls, 0);
+
+        let (removed, failed) = remove_remote_paths(&paths, true, is_dir, no_children, yes, |_| {
+            Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+        });
+        assert_eq!(removed.len(), 2);
+        assert_eq!(failed, 0);
+    }
+
+    #[test]
+    fn test_refuse_removing_app_root() {
+        let app_root = "/apps/myapp";
+        let is_root = |path: &str| is_protected_root(path, app_root);
+        for path in [
+            "/apps/myapp",
+            "/apps/myapp/",
+            "//apps//myapp",
+            "/apps/myapp/sub/..",
+            "/",
+            "/apps",
+        ] {
+            let e = refuse_root_paths([path], false, is_root, "删除").unwrap_err();
+            assert_eq!(e.error_type, AppErrorType::Client);
+            assert!(e.message.contains("--force-root"), "{}", e.message);
+        }
+        // 混有普通路径时整体拒绝
+        assert!(
+            refuse_root_paths(["/apps/myapp/a.txt", "/apps/myapp"], false, is_root, "删除")
+                .is_err()
+        );
+        // 应用目录下的子目录、其他应用的子目录不受限制
+        assert!(
+            refuse_root_paths(["/apps/myapp/sub", "/apps/other/x"], false, is_root, "删除").is_ok()
+        );
+        assert!(refuse_root_paths(["/apps/myapp", "/"], true, is_root, "删除").is_ok());
+
+        // 上传只拒绝 / 与 /apps，允许上传到应用根目录
+        assert!(refuse_root_paths(["/"], false, is_disk_root, "上传到").is_err());
+        assert!(refuse_root_paths(["/apps/"], false, is_disk_root, "上传到").is_err());
+        assert!(refuse_root_paths(["/apps/myapp"], false, is_disk_root, "上传到").is_ok());
+    }
+
+    #[test]
+    fn test_remove_remote_paths_skips_unknown_type() {
+        let paths = vec![
+            "/apps/x/unknown".to_string(),
+            "/apps/x/file.txt".to_string(),
+        ];
+        let mut calls: Vec<Vec<String>> = Vec::new();
+        let (removed, failed) = remove_remote_paths(
+            &paths,
+            false,
+            |path| match path {
+                "/apps/x/file.txt" => Ok(false),
+                _ => Err(AppError::new(AppErrorType::Network, "list failed", None)),
+            },
+            |_| panic!("文件不应列出子条目"),
+            |_, _| true,
+            |batch| {
+                calls.push(batch.to_vec());
+                Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+            },
+        );
+        // 类型未知的路径可能是目录，不能在未指定 -r 时删除
+        assert_eq!(removed, vec!["/apps/x/file.txt".to_string()]);
+        assert_eq!(calls, vec![vec!["/apps/x/file.txt".to_string()]]);
+        assert_eq!(failed, 1);
+    }
+
+    #[test]
+    fn test_remove_remote_paths_declined() {
+        let paths = vec!["/apps/x/file.txt".to_string(), "/apps/x/dir".to_string()];
+        let mut prompted: Vec<(String, Option<usize>)> = Vec::new();
+        let mut calls = 0;
+        let (removed, failed) = remove_remote_paths(
+            &paths,
+            true,
+            |path| Ok(path.ends_with("dir")),
+            |_| Ok(vec![pcs_file_item(1, "/d/1", 0, 0)]),
+            |_, dirs| {
+                prompted = dirs.to_vec();
+                confirm_prompt("n\n".as_bytes())
+            },
+            |_| {
+                calls += 1;
+                Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+            },
+        );
+        assert!(removed.is_empty());
+        assert_eq!(failed, 0);
+        assert_eq!(calls, 0);
+        assert_eq!(prompted, vec![("/apps/x/dir".to_string(), Some(1))]);
+
+        assert!(confirm_prompt("Y\n".as_bytes()));
+        assert!(confirm_prompt("yes\n".as_bytes()));
+        assert!(!confirm_prompt("".as_bytes()));
+    }
+
+    /// 测试用的文件条目：文件名取路径最后一段，时间均为 0
+    fn pcs_file_item(fs_id: u64, path: &str, size: u64, is_dir: i32) -> PcsFileItem {
+        serde_json::from_value(serde_json::json!({
+            "fs_id": fs_id, "path": path, "server_filename": path.rsplit('/').next().unwrap(),
+            "size": size, "server_mtime": 0, "server_ctime": 0, "local_mtime": 0,
+            "local_ctime": 0, "isdir": is_dir, "category": 6
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_remove_remote_paths_children_before_dir() {
+        let children = vec![
+            pcs_file_item(1, "/a/sub", 0, 1),
+            pcs_file_item(1, "/a/f1", 0, 0),
+            pcs_file_item(1, "/a/sub/f2", 0, 0),
+        ];
+        let mut calls: Vec<Vec<String>> = Vec::new();
+        let (removed, failed) = remove_remote_paths(
+            &["/a".to_string()],
+            true,
+            |_| Ok(true),
+            |_| Ok(children.clone()),
+            |_, _| true,
+            |batch| {
+                calls.push(batch.to_vec());
+                Ok(serde_json::from_value(serde_json::json!({"info": []})).unwrap())
+            },
+        );
+        assert_eq!(removed, vec!["/a".to_string()]);
+        assert_eq!(failed, 0);
+        assert_eq!(
+            calls,
+            vec![
+                vec!["/a/sub/f2".to_string()],
+                vec!["/a/sub".to_string(), "/a/f1".to_string()],
+                vec!["/a".to_string()],
+            ]
+        );
+
+        // 子条目删除失败时不再删除目录本身
+        let mut calls: Vec<Vec<String>> = Vec::new();
+        let (_, failed) = remove_remote_paths(
+            &["/a".to_string()],
+            true,
+            |_| Ok(true),
+            |_| Ok(children.clone()),
+            |_, _| true,
+            |batch| {
+                calls.push(batch.to_vec());
+                let info: Vec<_> = batch
+                    .iter()
+                    .map(|p| serde_json::json!({"errno": if p == "/a/sub/f2" { -7 } else { 0 }, "path": p}))
+                    .collect();
+                Ok(serde_json::from_value(serde_json::json!({ "info": info })).unwrap())
+            },
+        );
+        assert_eq!(calls, vec![vec!["/a/sub/f2".to_string()]]);
+        assert_eq!(failed, 1);
+    }
+
+    #[test]
+    fn test_format_file_meta() {
+        let meta = serde_json::from_value(serde_json::json!({
+            "category": 3, "filename": "a.jpg", "path": "/apps/a.jpg", "isdir": 0,
+            "md5": "d41d8cd98f00b204e9800998ecf8427e", "size": 1024,
+            "server_ctime": 1700000000, "server_mtime": 1700000100
+        }))
+        .unwrap();
+        let text = format_file_meta(&meta);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "文件名: a.jpg");
+        assert_eq!(lines[1], "路径: /apps/a.jpg");
+        assert_eq!(lines[2], "类型: 图片");
+        assert_eq!(lines[3], "大小: 1024 字节");
+        assert_eq!(lines[4], "MD5: d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            lines[5],
+            format!("创建时间: {}", format_local_time(1700000000))
+        );
+        assert_eq!(
+            lines[6],
+            format!("修改时间: {}", format_local_time(1700000100))
+        );
+        assert_eq!(lines.len(), 7);
+        assert_eq!(format_local_time(1700000000).len(), 19);
+    }
 
     #[test]
     fn test_scan_files_recursive() {
-        let files = scan_files_recursive(".", vec![]);
+        let files: Vec<String> = scan_files(".").collect();
         println!("{:?}", files);
         assert!(!files.is_empty());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_files_recursive_skips_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = env::temp_dir().join(format!("pcs-test-non-utf8-{}", std::process::id()));
+        let bad_dir = dir.join(OsStr::from_bytes(b"dir\xff"));
+        std::fs::create_dir_all(&bad_dir).unwrap();
+        std::fs::write(dir.join("good.txt"), b"1").unwrap();
+        std::fs::write(dir.join(OsStr::from_bytes(b"bad\xfe.txt")), b"2").unwrap();
+        std::fs::write(bad_dir.join("inner.txt"), b"3").unwrap();
+
+        let files: Vec<String> = scan_files(dir.to_str().unwrap()).collect();
+        assert_eq!(
+            files,
+            vec![dir.join("good.txt").to_string_lossy().to_string()]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_files_is_lazy() {
+        let dir = env::temp_dir().join(format!("pcs-test-scan-lazy-{}", std::process::id()));
+        let sub = dir.join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        let mut files = scan_files(dir.to_str().unwrap());
+
+        // 子目录在遍历到时才读取，因此创建遍历器之后写入的文件也会被产出
+        std::fs::write(sub.join("a.txt"), b"1").unwrap();
+        std::fs::create_dir_all(sub.join("deep")).unwrap();
+        std::fs::write(sub.join("deep/b.txt"), b"2").unwrap();
+        std::fs::write(sub.join(".hidden"), b"3").unwrap();
+        std::fs::create_dir_all(sub.join(".git")).unwrap();
+        std::fs::write(sub.join(".git/config"), b"4").unwrap();
+        std::os::unix::fs::symlink(sub.join("a.txt"), sub.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir, sub.join("loop")).unwrap();
+
+        let first = files.next().unwrap();
+        let mut all: Vec<String> = std::iter::once(first).chain(files).collect();
+        all.sort();
+        assert_eq!(
+            all,
+            vec![
+                sub.join("a.txt").to_string_lossy().to_string(),
+                sub.join("deep/b.txt").to_string_lossy().to_string(),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_files_modified_since() {
+        let dir = env::temp_dir().join(format!("pcs-test-scan-since-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let day = Duration::from_secs(86400);
+        let now = SystemTime::now();
+        for (name, age) in [
+            ("old.txt", 3),
+            ("sub/old.txt", 2),
+            ("new.txt", 0),
+            ("sub/new.txt", 0),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, name).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - day * age).unwrap();
+        }
+
+        let cutoff = Some(now - day);
+        let mut files: Vec<String> = scan_files(dir.to_str().unwrap())
+            .modified_since(cutoff)
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                dir.join("new.txt").to_string_lossy().to_string(),
+                dir.join("sub/new.txt").to_string_lossy().to_string(),
+            ]
+        );
+        // 扫描路径本身是文件时同样按修改时间过滤
+        let old = dir.join("old.txt");
+        assert_eq!(
+            scan_files(old.to_str().unwrap())
+                .modified_since(cutoff)
+                .count(),
+            0
+        );
+        assert_eq!(
+            scan_files(dir.to_str().unwrap())
+                .modified_since(None)
+                .count(),
+            4
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 6, 1, 12, 0, 0)
+            .unwrap();
+        let at = |secs: u64| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let now_secs = now.timestamp() as u64;
+        assert_eq!(parse_since("all", now).unwrap(), None);
+        assert_eq!(parse_since("90m", now).unwrap(), at(now_secs - 90 * 60));
+        assert_eq!(parse_since("1h", now).unwrap(), at(now_secs - 3600));
+        assert_eq!(parse_since("7d", now).unwrap(), at(now_secs - 7 * 86400));
+        assert_eq!(parse_since("2w", now).unwrap(), at(now_secs - 14 * 86400));
+        assert_eq!(parse_since("1700000000", now).unwrap(), at(1_700_000_000));
+        assert_eq!(
+            parse_since("2024-01-02T03:04:05Z", now).unwrap(),
+            at(1_704_164_645)
+        );
+        let local = chrono::Local.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        assert_eq!(
+            parse_since("2024-01-02", now).unwrap(),
+            at(local.timestamp() as u64)
+        );
+        let local = chrono::Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            parse_since("2024-01-02 03:04:05", now).unwrap(),
+            at(local.timestamp() as u64)
+        );
+        for invalid in ["", "abc", "10x", "h", "2024-13-01"] {
+            assert!(parse_since(invalid, now).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_total_download_bytes() {
+        let files = vec![
+            pcs_file_item(1, "/d/1", 100, 0),
+            pcs_file_item(2, "/d/2", 2048, 0),
+            pcs_file_item(3, "/d/3", 0, 1),
+            pcs_file_item(4, "/d/4", 7, 0),
+        ];
+        assert_eq!(total_download_bytes(&files), 100 + 2048 + 7);
+    }
+
+    #[test]
+    fn test_backup_filter() {
+        assert!(glob_match("*.jpg", "a.jpg"));
+        assert!(!glob_match("*.jpg", "dir/a.jpg"));
+        assert!(glob_match("**/*.jpg", "a.jpg"));
+        assert!(glob_match("**/*.jpg", "x/y/a.jpg"));
+        assert!(glob_match("tmp/**", "tmp/a/b.txt"));
+        assert!(glob_match("?.txt", "a.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+
+        let filter = BackupFilter {
+            include: vec!["*.jpg".to_string(), "docs/*.md".to_string()],
+            exclude: vec!["tmp/**".to_string(), "*.part.jpg".to_string()],
+        };
+        assert!(filter.is_match("2024/a.jpg"));
+        assert!(filter.is_match("docs/readme.md"));
+        assert!(!filter.is_match("docs/sub/readme.md"));
+        assert!(!filter.is_match("tmp/a.jpg"));
+        assert!(!filter.is_match("b.part.jpg"));
+        assert!(!filter.is_match("a.png"));
+        assert!(BackupFilter::default().is_match("any/file"));
+
+        assert_eq!(
+            backup_relative_path(
+                Path::new("/data/photos"),
+                Path::new("/data/photos/2024/a.jpg")
+            ),
+            "2024/a.jpg"
+        );
+        assert_eq!(
+            backup_relative_path(Path::new("/data/a.jpg"), Path::new("/data/a.jpg")),
+            "a.jpg"
+        );
+    }
+
+    #[test]
+    fn test_path_mapping() {
+        let source = Path::new("/data/photos");
+        let file = Path::new("/data/photos/2024/a.jpg");
+        let map = |mapping: PathMapping| mapping.remote_path(source, file, "/remote");
+
+        assert_eq!(
+            map(PathMapping::StripComponents(0)).as_deref(),
+            Some("/remote/photos/2024/a.jpg")
+        );
+        assert_eq!(
+            map(PathMapping::StripComponents(1)).as_deref(),
+            Some("/remote/2024/a.jpg")
+        );
+        assert_eq!(
+            map(PathMapping::StripComponents(2)).as_deref(),
+            Some("/remote/a.jpg")
+        );
+        assert_eq!(map(PathMapping::StripComponents(3)), None);
+        assert_eq!(
+            map(PathMapping::Base(PathBuf::from("/"))).as_deref(),
+            Some("/remote/data/photos/2024/a.jpg")
+        );
+        assert_eq!(
+            map(PathMapping::Base(PathBuf::from("/data/photos/2024"))).as_deref(),
+            Some("/remote/a.jpg")
+        );
+        assert_eq!(map(PathMapping::Base(PathBuf::from("/other"))), None);
+
+        // 单个文件作为源路径时，N=0 即上传到远程目录下
+        let single = Path::new("/data/a.txt");
+        assert_eq!(
+            PathMapping::StripComponents(0)
+                .remote_path(single, single, "/remote")
+                .as_deref(),
+            Some("/remote/a.txt")
+        );
+
+        assert_eq!(
+            PathMapping::from_args(Some(1), None, PathMapping::StripComponents(0)).unwrap(),
+            PathMapping::StripComponents(1)
+        );
+        assert_eq!(
+            PathMapping::from_args(None, None, PathMapping::StripComponents(0)).unwrap(),
+            PathMapping::StripComponents(0)
+        );
+        let base = env::temp_dir().to_string_lossy().to_string();
+        assert_eq!(
+            PathMapping::from_args(None, Some(&base), PathMapping::StripComponents(0)).unwrap(),
+            PathMapping::Base(fs::canonicalize(&base).unwrap())
+        );
+        let missing = "/nonexistent/pcs-base".to_string();
+        assert!(
+            PathMapping::from_args(None, Some(&missing), PathMapping::StripComponents(0)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_expand_local_path() {
+        let home = directories::BaseDirs::new()
+            .unwrap()
+            .home_dir()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(expand_local_path("~/foo"), format!("{}/foo", home));
+        assert_eq!(expand_local_path("~"), home);
+        assert_eq!(expand_local_path("/a/~b"), "/a/~b");
+        assert_eq!(expand_local_path("~user/x"), "~user/x");
+
+        env::set_var("PCS_TEST_EXPAND_DIR", "/data/dl");
+        assert_eq!(
+            expand_local_path("$PCS_TEST_EXPAND_DIR/bar"),
+            "/data/dl/bar"
+        );
+        assert_eq!(
+            expand_local_path("${PCS_TEST_EXPAND_DIR}bar"),
+            "/data/dlbar"
+        );
+        assert_eq!(
+            expand_local_path("$PCS_TEST_EXPAND_UNSET/bar"),
+            "$PCS_TEST_EXPAND_UNSET/bar"
+        );
+        assert_eq!(expand_local_path("cost$/x${"), "cost$/x${");
+    }
+
+    #[test]
+    fn test_get_relative_local_path() {
+        let local = Some("/tmp/out".to_string());
+        assert_eq!(
+            get_relative_local_path("/a", "/a/b/c.txt", local.as_ref()),
+            "/tmp/out/b/c.txt"
+        );
+        assert_eq!(
+            get_relative_local_path("/a/", "/a/c.txt", local.as_ref()),
+            "/tmp/out/c.txt"
+        );
+        assert_eq!(
+            get_relative_local_path("/x", "/a/c.txt", local.as_ref()),
+            "/tmp/out/c.txt"
+        );
+    }
+
+    #[test]
+    fn test_quiet_progress_bar_hidden() {
+        let pb = new_progress_bar(ProgressMode::Quiet, Some(42));
+        assert!(pb.is_hidden());
+        assert_eq!(pb.length(), Some(42));
+        let pb = new_progress_bar(ProgressMode::Quiet, None);
+        assert!(pb.is_hidden());
+        assert_eq!(pb.length(), None);
+    }
+
+    #[test]
+    fn test_non_tty_progress_bar_hidden() {
+        assert_eq!(ProgressMode::detect(false, true), ProgressMode::Bar);
+        assert_eq!(ProgressMode::detect(true, true), ProgressMode::Quiet);
+        let mode = ProgressMode::detect(false, false);
+        assert_eq!(mode, ProgressMode::Lines);
+        assert!(new_progress_bar(mode, Some(10)).is_hidden());
+    }
+
+    #[test]
+    fn test_crossed_percent_step() {
+        assert_eq!(crossed_percent_step(0, 5, 100), None);
+        assert_eq!(crossed_percent_step(5, 10, 100), Some(10));
+        assert_eq!(crossed_percent_step(10, 35, 100), Some(30));
+        assert_eq!(crossed_percent_step(90, 100, 100), Some(100));
+        assert_eq!(crossed_percent_step(100, 100, 100), None);
+        assert_eq!(crossed_percent_step(0, 10, 0), None);
+    }
+
+    #[test]
+    fn test_upload_done_message_uses_result_path() {
+        let result: PcsFileUploadResult = serde_json::from_value(serde_json::json!({
+            "path": "/apps/demo/text(1).txt", "size": 4, "ctime": 0, "mtime": 0,
+            "md5": "abcd", "fs_id": 1
+        }))
+        .unwrap();
+        let message = upload_done_message("./text.txt", "/apps/demo/text.txt", &result);
+        assert!(message.starts_with("./text.txt -> /apps/demo/text(1).txt"));
+        assert!(message.contains("请求路径 /apps/demo/text.txt"));
+
+        let message = upload_done_message("./text.txt", "/apps/demo/text(1).txt", &result);
+        assert_eq!(message, "./text.txt -> /apps/demo/text(1).txt");
+    }
+
+    #[test]
+    fn test_backup_checkpoint_skips_completed() {
+        let path = env::temp_dir().join(format!("pcs-test-checkpoint-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let dir = env::temp_dir().join(format!("pcs-test-checkpoint-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scanned: Vec<String> = ["a.txt", "b.txt", "c.txt", "d.txt"]
+            .iter()
+            .map(|name| {
+                let file = dir.join(name);
+                std::fs::write(&file, name).unwrap();
+                file.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let pending = |checkpoint: &BackupCheckpoint| -> Vec<String> {
+            scanned
+                .iter()
+                .filter(|file| !checkpoint.is_done(file))
+                .cloned()
+                .collect()
+        };
+        let mut checkpoint = BackupCheckpoint::load(path.clone());
+        assert_eq!(pending(&checkpoint).len(), 4);
+        checkpoint.mark_done(&scanned[0]);
+        checkpoint.mark_done(&scanned[2]);
+        checkpoint.mark_done(&scanned[3]);
+        // 完成后又被修改的文件需要重新备份
+        std::fs::write(&scanned[3], "changed").unwrap();
+
+        // 模拟中断后重新加载
+        let mut checkpoint = BackupCheckpoint::load(path.clone());
+        assert_eq!(checkpoint.len(), 3);
+        assert_eq!(
+            pending(&checkpoint),
+            vec![scanned[1].clone(), scanned[3].clone()]
+        );
+
+        checkpoint.clear();
+        assert!(!path.exists());
+        assert_eq!(pending(&checkpoint).len(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_files_overall_not_finished_early() {
+        let files = vec![
+            pcs_file_item(1, "/d/1", 10, 0),
+            pcs_file_item(2, "/d/2", 20, 0),
+            pcs_file_item(3, "/d/3", 30, 0),
+        ];
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let overall = mp.add(ProgressBar::new(total_download_bytes(&files)));
+        let observer = overall.clone();
+        let mut calls = 0usize;
+        let failed = download_files_with_progress(
+            &files,
+            "/d",
+            None,
+            ProgressMode::Quiet,
+            &mp,
+            &overall,
+            |file, _local, cb| {
+                calls += 1;
+                assert!(!observer.is_finished());
+                cb(*file.size(), *file.size());
+                if *file.fs_id() == 2 {
+                    return Err(AppError::new(AppErrorType::Network, "mock", None));
+                }
+                Ok(())
+            },
+        );
+        assert_eq!(calls, 3);
+        assert_eq!(failed, 1);
+        assert!(overall.is_finished());
+        assert_eq!(overall.position(), 60);
+    }
 }
@@ -0,0 +1,240 @@
+//! 集成测试使用的模拟百度网盘服务器
+//!
+//! 基于 `wiremock`，按 `请求方法 + 路径 + method 参数`（及可选的其他查询参数）匹配预设的响应，
+//! 并记录收到的全部请求，便于断言客户端发出的请求内容。无需网络与真实 access token。
+
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::BaiduPcsClient;
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::BaiduPcsApp;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::runtime::Runtime;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockBuilder, Request, ResponseTemplate};
+
+pub const TEST_APP: BaiduPcsApp = BaiduPcsApp {
+    app_key: "mock-app-key",
+    app_secret: "mock-app-secret",
+    app_name: "mock-app",
+    app_id: None,
+};
+
+pub const TEST_TOKEN: &str = "mock-token";
+
+/// 服务器收到的一次请求
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub path: String,
+    request: Request,
+}
+
+impl RecordedRequest {
+    /// 查询参数的值（已解码）
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.request
+            .url
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// 请求头的值，名称不区分大小写
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.request.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// 请求体作为文本
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.request.body).to_string()
+    }
+
+    /// 查询参数中的接口方法，如 `list`、`precreate`
+    pub fn api_method(&self) -> Option<String> {
+        self.param("method")
+    }
+}
+
+/// 模拟服务器，作用域结束后停止
+/// `wiremock` 在独立线程中运行服务器，`runtime` 只用于调用其异步接口
+pub struct MockServer {
+    url: String,
+    server: wiremock::MockServer,
+    runtime: Runtime,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = runtime.block_on(wiremock::MockServer::builder().start());
+        Self {
+            url: server.uri(),
+            server,
+            runtime,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// 匹配 `method path` 及可选的 `method=` 查询参数
+    fn route(&self, http_method: &str, url_path: &str, api_method: Option<&str>) -> MockBuilder {
+        let builder = Mock::given(method(http_method)).and(path(url_path));
+        match api_method {
+            Some(api_method) => builder.and(query_param("method", api_method)),
+            None => builder,
+        }
+    }
+
+    fn mount(&self, mock: Mock) {
+        self.runtime.block_on(mock.mount(&self.server));
+    }
+
+    /// 为 `method path`（及可选的 `method=` 查询参数）设置 200 响应
+    pub fn mock(&self, method: &str, path: &str, api_method: Option<&str>, body: &str) {
+        self.mock_raw(method, path, api_method, 200, body.as_bytes());
+    }
+
+    /// 设置任意状态码与响应体
+    pub fn mock_raw(
+        &self,
+        method: &str,
+        path: &str,
+        api_method: Option<&str>,
+        status: u16,
+        body: &[u8],
+    ) {
+        self.mount(
+            self.route(method, path, api_method)
+                .respond_with(json_response(status, body)),
+        );
+    }
+
+    /// 与 `mock_raw` 相同（状态码 200），并附加响应头，如 `Content-Encoding`
+    pub fn mock_with_headers(
+        &self,
+        method: &str,
+        path: &str,
+        api_method: Option<&str>,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) {
+        let response = headers
+            .iter()
+            .fold(json_response(200, body), |response, (name, value)| {
+                response.insert_header(*name, *value)
+            });
+        self.mount(self.route(method, path, api_method).respond_with(response));
+    }
+
+    /// 与 `mock` 相同，但还要求查询参数 `name` 的值为 `value`，如按 `dir` 区分不同目录的 `list`
+    pub fn mock_with_param(
+        &self,
+        method: &str,
+        path: &str,
+        api_method: Option<&str>,
+        param: (&str, &str),
+        body: &str,
+    ) {
+        self.mount(
+            self.route(method, path, api_method)
+                .and(query_param(param.0, param.1))
+                .respond_with(json_response(200, body.as_bytes())),
+        );
+    }
+
+    /// 已收到的请求
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.runtime
+            .block_on(self.server.received_requests())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|request| RecordedRequest {
+                path: request.url.path().to_string(),
+                request,
+            })
+            .collect()
+    }
+
+    /// 指向本服务器的客户端（接口与上传地址均为本服务器）
+    pub fn client(&self) -> BaiduPcsClient {
+        let mut client = BaiduPcsClient::new_with_dns(TEST_TOKEN, TEST_APP, None);
+        client.set_base_urls(self.url(), self.url());
+        client
+    }
+
+    /// 预设用户信息与容量接口，`ware` 及分片上传需要
+    pub fn mock_user(&self) {
+        self.mock(
+            "GET",
+            "/rest/2.0/xpan/nas",
+            Some("uinfo"),
+            r#"{"errno":0,"baidu_name":"mock","netdisk_name":"mock","avatar_url":"","vip_type":0,"uk":1}"#,
+        );
+        self.mock(
+            "GET",
+            "/api/quota",
+            None,
+            r#"{"errno":0,"total":1024,"expire":false,"used":0,"free":1024}"#,
+        );
+    }
+}
+
+fn json_response(status: u16, body: &[u8]) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body.to_vec(), "application/json")
+}
+
+/// 统计 TCP 连接数的转发器：把每个连接原样转发给 `target`，用于验证客户端的连接复用
+pub struct ConnectionCounter {
+    url: String,
+    connections: Arc<AtomicUsize>,
+}
+
+impl ConnectionCounter {
+    pub fn start(target: &str) -> Self {
+        let target = target.trim_start_matches("http://").to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for client in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let upstream = TcpStream::connect(target.as_str()).unwrap();
+                forward(client.try_clone().unwrap(), upstream.try_clone().unwrap());
+                forward(upstream, client);
+            }
+        });
+        Self { url, connections }
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// 已接受的 TCP 连接数，连接被复用时小于请求数
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+/// 把 `from` 读到的数据写入 `to`，`from` 关闭后关闭 `to` 的写端
+fn forward(mut from: TcpStream, mut to: TcpStream) {
+    thread::spawn(move || {
+        let _ = io::copy(&mut from, &mut to);
+        let _ = to.shutdown(Shutdown::Write);
+    });
+}
+
+/// 解析 `a=1&b=2` 形式的 urlencoded 请求体
+pub fn parse_pairs(text: &str) -> HashMap<String, String> {
+    url::form_urlencoded::parse(text.as_bytes())
+        .into_owned()
+        .collect()
+}
@@ -0,0 +1,939 @@
+//! 使用模拟服务器验证完整的请求/响应流程，无需网络与真实 access token
+
+mod common;
+
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::pcs::{
+    BaiduPcsClient, HttpOptions, PauseControl, PcsDownloadPolicy, PcsFileSliceInfo,
+    PcsUploadPolicy, SearchOptions, UploadOptions,
+};
+use baidu_pcs_rs_sdk::baidu_pcs_sdk::AppErrorType;
+use common::{parse_pairs, ConnectionCounter, MockServer, TEST_APP, TEST_TOKEN};
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fn temp_file(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("pcs-mock-{}-{}", std::process::id(), name))
+}
+
+const LIST_RESPONSE: &str = r#"{"errno":0,"guid":0,"list":[
+    {"fs_id":11,"path":"/apps/mock-app/a.txt","server_filename":"a.txt","size":5,
+     "server_mtime":1,"server_ctime":1,"local_mtime":1,"local_ctime":1,"isdir":0,"category":4,"md5":"x"},
+    {"fs_id":12,"path":"/apps/mock-app/dir","server_filename":"dir","size":0,
+     "server_mtime":1,"server_ctime":1,"local_mtime":1,"local_ctime":1,"isdir":1,"category":6}]}"#;
+
+#[test]
+fn test_list_dir() {
+    let server = MockServer::start();
+    server.mock("GET", "/rest/2.0/xpan/file", Some("list"), LIST_RESPONSE);
+    let client = server.client();
+
+    let result = client.list_dir("/apps/mock-app").unwrap();
+    assert_eq!(result.list().len(), 2);
+    assert_eq!(result.list()[0].path(), "/apps/mock-app/a.txt");
+    assert_eq!(*result.list()[1].is_dir(), 1);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].param("dir").as_deref(), Some("/apps/mock-app"));
+    assert_eq!(
+        requests[0].param("access_token").as_deref(),
+        Some(TEST_TOKEN)
+    );
+}
+
+#[test]
+fn test_client_with_http_options() {
+    let server = MockServer::start();
+    server.mock("GET", "/rest/2.0/xpan/file", Some("list"), LIST_RESPONSE);
+    let options = HttpOptions {
+        pool_max_idle_per_host: 32,
+        pool_idle_timeout: None,
+        tcp_keepalive: Some(std::time::Duration::from_secs(15)),
+    };
+    assert_ne!(options, HttpOptions::default());
+    let mut client = BaiduPcsClient::new_with_http_options(
+        TEST_TOKEN,
+        TEST_APP,
+        None,
+        reqwest::header::HeaderMap::new(),
+        &options,
+    );
+    let counter = ConnectionCounter::start(server.url());
+    client.set_base_urls(counter.url(), counter.url());
+
+    // 连续请求复用连接池中的连接
+    for _ in 0..3 {
+        assert_eq!(client.list_dir("/apps/mock-app").unwrap().list().len(), 2);
+    }
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(counter.connections(), 1);
+
+    // 不保留空闲连接时每个请求都新建连接
+    let counter = ConnectionCounter::start(server.url());
+    let options = HttpOptions {
+        pool_max_idle_per_host: 0,
+        ..options
+    };
+    let mut client = BaiduPcsClient::new_with_http_options(
+        TEST_TOKEN,
+        TEST_APP,
+        None,
+        reqwest::header::HeaderMap::new(),
+        &options,
+    );
+    client.set_base_urls(counter.url(), counter.url());
+    for _ in 0..3 {
+        client.list_dir("/apps/mock-app").unwrap();
+    }
+    assert_eq!(counter.connections(), 3);
+}
+
+#[test]
+fn test_compressed_response_is_decoded() {
+    // gzip 压缩的单个文件列表，与 LIST_RESPONSE 的第一项相同
+    const GZIP_LIST: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4d, 0x8e, 0x41, 0x0e, 0xc2,
+        0x20, 0x14, 0x44, 0xef, 0x32, 0x6b, 0x14, 0x49, 0xec, 0x86, 0xab, 0x18, 0xd3, 0x10, 0xa0,
+        0xf5, 0x47, 0x28, 0x04, 0xd0, 0x54, 0x9b, 0xde, 0x5d, 0x5a, 0x13, 0x74, 0x37, 0xf3, 0xde,
+        0x24, 0xff, 0x2f, 0xb0, 0x29, 0x4d, 0x01, 0xf2, 0xc4, 0x30, 0x3e, 0xc8, 0xec, 0xc1, 0x51,
+        0x2e, 0x90, 0x97, 0x05, 0x43, 0xee, 0x37, 0x24, 0x04, 0x43, 0x54, 0xe5, 0x06, 0x09, 0xae,
+        0x62, 0xcc, 0xdc, 0x07, 0x7d, 0x3f, 0xd4, 0xc4, 0xd5, 0xb1, 0xcc, 0x05, 0x0c, 0xd9, 0xa6,
+        0xa7, 0x4d, 0xfd, 0x40, 0xce, 0x4e, 0xca, 0xdb, 0x3a, 0x6c, 0x86, 0xde, 0xb5, 0x76, 0x6d,
+        0xe2, 0x0b, 0x6d, 0x5e, 0x34, 0xa0, 0x1b, 0x70, 0x41, 0x2b, 0xf7, 0x37, 0xf8, 0xf6, 0x9f,
+        0xa7, 0x6c, 0x28, 0xed, 0x0f, 0x6a, 0x55, 0xec, 0x18, 0xd2, 0x0b, 0xf2, 0xcc, 0xe0, 0x4d,
+        0x57, 0xef, 0xcd, 0x58, 0xaf, 0xeb, 0x07, 0x6b, 0xce, 0x38, 0xa2, 0xce, 0x00, 0x00, 0x00,
+    ];
+    // 同一内容的 deflate（zlib 格式）压缩
+    const DEFLATE_LIST: &[u8] = &[
+        0x78, 0xda, 0x4d, 0x8e, 0x41, 0x0e, 0xc2, 0x20, 0x14, 0x44, 0xef, 0x32, 0x6b, 0x14, 0x49,
+        0xec, 0x86, 0xab, 0x18, 0xd3, 0x10, 0xa0, 0xf5, 0x47, 0x28, 0x04, 0xd0, 0x54, 0x9b, 0xde,
+        0x5d, 0x5a, 0x13, 0x74, 0x37, 0xf3, 0xde, 0x24, 0xff, 0x2f, 0xb0, 0x29, 0x4d, 0x01, 0xf2,
+        0xc4, 0x30, 0x3e, 0xc8, 0xec, 0xc1, 0x51, 0x2e, 0x90, 0x97, 0x05, 0x43, 0xee, 0x37, 0x24,
+        0x04, 0x43, 0x54, 0xe5, 0x06, 0x09, 0xae, 0x62, 0xcc, 0xdc, 0x07, 0x7d, 0x3f, 0xd4, 0xc4,
+        0xd5, 0xb1, 0xcc, 0x05, 0x0c, 0xd9, 0xa6, 0xa7, 0x4d, 0xfd, 0x40, 0xce, 0x4e, 0xca, 0xdb,
+        0x3a, 0x6c, 0x86, 0xde, 0xb5, 0x76, 0x6d, 0xe2, 0x0b, 0x6d, 0x5e, 0x34, 0xa0, 0x1b, 0x70,
+        0x41, 0x2b, 0xf7, 0x37, 0xf8, 0xf6, 0x9f, 0xa7, 0x6c, 0x28, 0xed, 0x0f, 0x6a, 0x55, 0xec,
+        0x18, 0xd2, 0x0b, 0xf2, 0xcc, 0xe0, 0x4d, 0x57, 0xef, 0xcd, 0x58, 0xaf, 0xeb, 0x07, 0x17,
+        0x3a, 0x43, 0x01,
+    ];
+    for (encoding, body) in [("gzip", GZIP_LIST), ("deflate", DEFLATE_LIST)] {
+        let server = MockServer::start();
+        server.mock_with_headers(
+            "GET",
+            "/rest/2.0/xpan/file",
+            Some("list"),
+            &[("Content-Encoding", encoding)],
+            body,
+        );
+        let client = server.client();
+
+        let result = client.list_dir("/apps/mock-app").unwrap();
+        assert_eq!(result.list().len(), 1, "{}", encoding);
+        assert_eq!(result.list()[0].path(), "/apps/mock-app/a.txt");
+        let accept_encoding = server.requests()[0]
+            .header("accept-encoding")
+            .unwrap()
+            .to_string();
+        for expected in ["gzip", "br", "deflate"] {
+            assert!(accept_encoding.contains(expected), "{}", accept_encoding);
+        }
+    }
+}
+
+/// 预设分片上传流程（precreate → locateupload → superfile2 → create）的成功响应
+fn mock_slice_upload(server: &MockServer, pcs_path: &str, size: u64) {
+    server.mock(
+        "POST",
+        "/rest/2.0/xpan/file",
+        Some("precreate"),
+        format!(
+            r#"{{"errno":0,"path":"{}","uploadid":"U-1","return_type":1,"block_list":[0]}}"#,
+            pcs_path
+        )
+        .as_str(),
+    );
+    server.mock(
+        "GET",
+        "/rest/2.0/pcs/file",
+        Some("locateupload"),
+        format!(
+            r#"{{"errno":0,"client_ip":"127.0.0.1","host":"mock","server_time":1,"servers":[{{"server":"{}"}}],"bak_servers":[]}}"#,
+            server.url()
+        )
+        .as_str(),
+    );
+    server.mock(
+        "POST",
+        "/rest/2.0/pcs/superfile2",
+        Some("upload"),
+        r#"{"md5":"5d41402abc4b2a76b9719d911017c592"}"#,
+    );
+    server.mock(
+        "POST",
+        "/rest/2.0/xpan/file",
+        Some("create"),
+        format!(
+            r#"{{"errno":0,"path":"{}","size":{},"ctime":1,"mtime":1,"md5":"m","fs_id":99}}"#,
+            pcs_path, size
+        )
+        .as_str(),
+    );
+}
+
+#[test]
+fn test_upload_from_reader() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/gen.txt", 5);
+    let client = server.client();
+
+    let mut reader = Cursor::new(b"hello".to_vec());
+    // 读取位置不在起始处时也应从头上传
+    reader.set_position(3);
+    let result = client
+        .upload_from_reader(
+            reader,
+            5,
+            "/apps/mock-app/gen.txt",
+            PcsUploadPolicy::Overwrite,
+        )
+        .unwrap();
+    assert_eq!(*result.fs_id(), 99);
+
+    let requests = server.requests();
+    let precreate = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("precreate"))
+        .unwrap();
+    let form = parse_pairs(precreate.body_text().as_str());
+    assert_eq!(form.get("size").map(String::as_str), Some("5"));
+    assert_eq!(
+        form.get("block_list").map(String::as_str),
+        Some(r#"["5d41402abc4b2a76b9719d911017c592"]"#)
+    );
+    let slice = requests
+        .iter()
+        .find(|r| r.path == "/rest/2.0/pcs/superfile2")
+        .unwrap();
+    assert!(slice.body_text().contains("hello"));
+    assert!(requests
+        .iter()
+        .any(|r| r.api_method().as_deref() == Some("create")));
+}
+
+#[test]
+fn test_upload_with_checksums() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/pipe.txt", 5);
+    let client = server.client();
+
+    // md5("hello")，数据不足一个分片，三者相同
+    let md5 = "5d41402abc4b2a76b9719d911017c592".to_string();
+    let checksums =
+        PcsFileSliceInfo::with_checksums(5, Some(md5.clone()), Some(md5.clone()), vec![md5]);
+    // &[u8] 只实现了 Read，不支持 Seek
+    let reader: &[u8] = b"hello";
+    let result = client
+        .upload_with_checksums(
+            reader,
+            checksums,
+            "/apps/mock-app/pipe.txt",
+            PcsUploadPolicy::Overwrite,
+        )
+        .unwrap();
+    assert_eq!(*result.fs_id(), 99);
+
+    let requests = server.requests();
+    let precreate = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("precreate"))
+        .unwrap();
+    let form = parse_pairs(precreate.body_text().as_str());
+    assert_eq!(
+        form.get("content_md5").map(String::as_str),
+        Some("5d41402abc4b2a76b9719d911017c592")
+    );
+    let slice = requests
+        .iter()
+        .find(|r| r.path == "/rest/2.0/pcs/superfile2")
+        .unwrap();
+    assert!(slice.body_text().contains("hello"));
+
+    // 分片数量与数据大小不符时在预上传前报错
+    let bad = PcsFileSliceInfo::with_checksums(5, None, None, vec![]);
+    let e = client
+        .upload_with_checksums(
+            &b"hello"[..],
+            bad,
+            "/apps/mock-app/pipe.txt",
+            PcsUploadPolicy::Overwrite,
+        )
+        .unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Client);
+    let precreates = server
+        .requests()
+        .iter()
+        .filter(|r| r.api_method().as_deref() == Some("precreate"))
+        .count();
+    assert_eq!(precreates, 1);
+}
+
+#[test]
+fn test_upload_stream_with_options() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/gen.txt", 5);
+    let client = server.client();
+
+    // 未指定 content_type 时按远程路径的扩展名推断
+    let options = UploadOptions {
+        pause: Some(PauseControl::new()),
+        ..UploadOptions::from(PcsUploadPolicy::Overwrite)
+    };
+    client
+        .upload_from_reader_with_options(
+            Cursor::new(b"hello".to_vec()),
+            5,
+            "/apps/mock-app/gen.txt",
+            &options,
+        )
+        .unwrap();
+    // 显式指定的 content_type 优先
+    let md5 = "5d41402abc4b2a76b9719d911017c592".to_string();
+    let checksums =
+        PcsFileSliceInfo::with_checksums(5, Some(md5.clone()), Some(md5.clone()), vec![md5]);
+    let options = UploadOptions {
+        content_type: Some("application/x-custom".to_string()),
+        ..UploadOptions::from(PcsUploadPolicy::Overwrite)
+    };
+    client
+        .upload_with_checksums_with_options(
+            &b"hello"[..],
+            checksums,
+            "/apps/mock-app/gen.txt",
+            &options,
+        )
+        .unwrap();
+
+    let slices: Vec<String> = server
+        .requests()
+        .iter()
+        .filter(|r| r.path == "/rest/2.0/pcs/superfile2")
+        .map(|r| r.body_text())
+        .collect();
+    assert_eq!(slices.len(), 2);
+    assert!(slices[0].contains("text/plain"));
+    assert!(slices[1].contains("application/x-custom"));
+
+    // 只设置压缩程度且没有整文件 MD5 可作为 zip_sign：预上传前报错
+    let options = UploadOptions {
+        zip_quality: Some(70),
+        ..UploadOptions::from(PcsUploadPolicy::Overwrite)
+    };
+    let e = client
+        .upload_with_checksums_with_options(
+            &b"hello"[..],
+            PcsFileSliceInfo::with_checksums(5, None, None, vec!["x".to_string()]),
+            "/apps/mock-app/gen.txt",
+            &options,
+        )
+        .unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Client);
+    let precreates = server
+        .requests()
+        .iter()
+        .filter(|r| r.api_method().as_deref() == Some("precreate"))
+        .count();
+    assert_eq!(precreates, 2);
+}
+
+#[test]
+fn test_can_rapid_upload() {
+    let local = temp_file("rapid.txt");
+    fs::write(&local, b"hello").unwrap();
+    for (return_type, expected) in [(2, true), (1, false)] {
+        let server = MockServer::start();
+        server.mock_user();
+        server.mock(
+            "POST",
+            "/rest/2.0/xpan/file",
+            Some("precreate"),
+            format!(
+                r#"{{"errno":0,"path":"/apps/mock-app/rapid.txt","uploadid":"U-1","return_type":{},"block_list":[]}}"#,
+                return_type
+            )
+            .as_str(),
+        );
+        let client = server.client();
+        let rapid = client
+            .can_rapid_upload(local.to_str().unwrap(), "/apps/mock-app/rapid.txt")
+            .unwrap();
+        assert_eq!(rapid, expected);
+
+        // 只执行预上传，并提交秒传所需的校验和
+        let requests = server.requests();
+        let methods: Vec<String> = requests.iter().filter_map(|r| r.api_method()).collect();
+        assert_eq!(methods, ["uinfo", "precreate"]);
+        let form = parse_pairs(requests[1].body_text().as_str());
+        assert_eq!(
+            form.get("content_md5").map(String::as_str),
+            Some("5d41402abc4b2a76b9719d911017c592")
+        );
+        assert!(form.contains_key("slice_md5"));
+    }
+    fs::remove_file(&local).unwrap();
+}
+
+#[test]
+fn test_upload_large_file_happy_path() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/up.txt", 5);
+
+    let local = temp_file("upload.txt");
+    fs::write(&local, b"hello").unwrap();
+    let mut client = server.client();
+    client.ware().unwrap();
+    let result = client
+        .upload_large_file(
+            local.to_str().unwrap(),
+            "/apps/mock-app/up.txt",
+            PcsUploadPolicy::Overwrite,
+            |_| {},
+        )
+        .unwrap();
+    fs::remove_file(&local).unwrap();
+    assert_eq!(result.path(), "/apps/mock-app/up.txt");
+    assert_eq!(*result.fs_id(), 99);
+
+    let methods: Vec<String> = server
+        .requests()
+        .iter()
+        .filter_map(|r| r.api_method())
+        .filter(|m| m != "uinfo")
+        .collect();
+    assert_eq!(methods, ["precreate", "locateupload", "upload", "create"]);
+
+    let requests = server.requests();
+    let slice = requests
+        .iter()
+        .find(|r| r.path == "/rest/2.0/pcs/superfile2")
+        .unwrap();
+    assert_eq!(slice.param("uploadid").as_deref(), Some("U-1"));
+    assert_eq!(slice.param("partseq").as_deref(), Some("0"));
+    assert!(slice.body_text().contains("hello"));
+
+    let create = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("create"))
+        .unwrap();
+    let form = parse_pairs(create.body_text().as_str());
+    assert_eq!(form.get("uploadid").map(String::as_str), Some("U-1"));
+    // 创建文件的 rtype 与预上传一致
+    let precreate = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("precreate"))
+        .unwrap();
+    let precreate_form = parse_pairs(precreate.body_text().as_str());
+    assert_eq!(form.get("rtype"), precreate_form.get("rtype"));
+    assert_eq!(form.get("rtype").map(String::as_str), Some("3"));
+    assert_eq!(
+        form.get("block_list").map(String::as_str),
+        Some(r#"["5d41402abc4b2a76b9719d911017c592"]"#)
+    );
+}
+
+#[test]
+fn test_backup_dir_preserves_structure() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/backup", 5);
+
+    let local = temp_file("backup-dir");
+    fs::create_dir_all(local.join("sub").join("deeper")).unwrap();
+    fs::write(local.join("a.txt"), b"hello").unwrap();
+    fs::write(local.join("sub").join("b.txt"), b"hello").unwrap();
+    fs::write(local.join("sub").join("deeper").join("c.txt"), b"hello").unwrap();
+    fs::write(local.join(".hidden"), b"hello").unwrap();
+    let mut client = server.client();
+    client.ware().unwrap();
+    let results = client
+        .backup_file(local.to_str().unwrap(), "/apps/mock-app/backup")
+        .unwrap();
+    fs::remove_dir_all(&local).unwrap();
+    assert_eq!(results.len(), 3);
+
+    // create 使用 precreate 返回的路径（模拟响应固定），因此以 precreate 请求的路径为准
+    let mut uploaded: Vec<String> = server
+        .requests()
+        .iter()
+        .filter(|r| r.api_method().as_deref() == Some("precreate"))
+        .filter_map(|r| parse_pairs(r.body_text().as_str()).remove("path"))
+        .collect();
+    uploaded.sort();
+    assert_eq!(
+        uploaded,
+        [
+            "/apps/mock-app/backup/a.txt",
+            "/apps/mock-app/backup/sub/b.txt",
+            "/apps/mock-app/backup/sub/deeper/c.txt",
+        ]
+    );
+}
+
+#[test]
+fn test_backup_dedup_copies_identical_files() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_slice_upload(&server, "/apps/mock-app/dedup/a.txt", 5);
+    server.mock(
+        "POST",
+        "/rest/2.0/xpan/file",
+        Some("filemanager"),
+        r#"{"errno":0,"info":[{"errno":0,"path":"/apps/mock-app/dedup/a.txt"}],"request_id":1}"#,
+    );
+    // 复制得到的文件位于目录列表的第二页
+    mock_list_page(&server, "0", 0..1000);
+    server.mock_with_param(
+        "GET",
+        "/rest/2.0/xpan/file",
+        Some("list"),
+        ("start", "1000"),
+        format!(
+            r#"{{"errno":0,"guid":0,"list":[{}]}}"#,
+            list_entry(42, "/apps/mock-app/dedup/b.txt", false)
+        )
+        .as_str(),
+    );
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/multimedia",
+        Some("filemetas"),
+        r#"{"errno":0,"list":[{"fs_id":42,"category":4,"filename":"b.txt","path":"/apps/mock-app/dedup/b.txt","isdir":0,"server_ctime":1,"server_mtime":1,"size":5,"md5":"m"}]}"#,
+    );
+
+    let local = temp_file("backup-dedup");
+    fs::create_dir_all(&local).unwrap();
+    fs::write(local.join("a.txt"), b"hello").unwrap();
+    fs::write(local.join("b.txt"), b"hello").unwrap();
+    let mut client = server.client();
+    client.ware().unwrap();
+    client.set_backup_dedup(true);
+    let results = client
+        .backup_file(local.to_str().unwrap(), "/apps/mock-app/dedup")
+        .unwrap();
+    fs::remove_dir_all(&local).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].path(), "/apps/mock-app/dedup/b.txt");
+    assert_eq!(*results[1].fs_id(), 42);
+
+    let requests = server.requests();
+    let precreates = requests
+        .iter()
+        .filter(|r| r.api_method().as_deref() == Some("precreate"))
+        .count();
+    assert_eq!(precreates, 1);
+    let copy = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("filemanager"))
+        .unwrap();
+    assert_eq!(copy.param("opera").as_deref(), Some("copy"));
+    let form = parse_pairs(copy.body_text().as_str());
+    let file_list: serde_json::Value =
+        serde_json::from_str(form.get("file_list").unwrap().as_str()).unwrap();
+    assert_eq!(file_list[0]["path"], "/apps/mock-app/dedup/a.txt");
+    assert_eq!(file_list[0]["dest"], "/apps/mock-app/dedup");
+    assert_eq!(file_list[0]["newname"], "b.txt");
+    let filemetas = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("filemetas"))
+        .unwrap();
+    assert_eq!(filemetas.param("fsids").as_deref(), Some("[42]"));
+}
+
+#[test]
+fn test_stat_and_download() {
+    let server = MockServer::start();
+    server.mock("GET", "/rest/2.0/xpan/file", Some("list"), LIST_RESPONSE);
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/multimedia",
+        Some("filemetas"),
+        format!(
+            r#"{{"errno":0,"list":[{{"fs_id":11,"category":4,"dlink":"{}/file/a.txt?fid=11","filename":"a.txt","isdir":0,"server_ctime":1,"server_mtime":1,"size":5}}]}}"#,
+            server.url()
+        )
+        .as_str(),
+    );
+    server.mock_raw("GET", "/file/a.txt", None, 200, b"hello");
+    let client = server.client();
+
+    let meta = client.stat("/apps/mock-app/a.txt", true).unwrap();
+    let dlink = meta.dlink().clone().unwrap();
+    let local = temp_file("download.txt");
+    client
+        .download(
+            dlink.as_str(),
+            local.to_str().unwrap(),
+            None::<fn(u64, u64)>,
+        )
+        .unwrap();
+    assert_eq!(fs::read(&local).unwrap(), b"hello");
+    fs::remove_file(&local).unwrap();
+
+    let requests = server.requests();
+    let download = requests.iter().find(|r| r.path == "/file/a.txt").unwrap();
+    assert_eq!(download.param("access_token").as_deref(), Some(TEST_TOKEN));
+}
+
+/// 生成 list 接口返回的条目
+fn list_entry(fs_id: u64, path: &str, is_dir: bool) -> String {
+    format!(
+        r#"{{"fs_id":{},"path":"{}","server_filename":"{}","size":5,"server_mtime":1,"server_ctime":1,"local_mtime":1,"local_ctime":1,"isdir":{},"category":6}}"#,
+        fs_id,
+        path,
+        path.rsplit('/').next().unwrap(),
+        is_dir as i32
+    )
+}
+
+/// 预设 `/apps/mock-app/big` 从 `start` 开始的一页 list 结果，条目为 `{id}.txt` 文件
+fn mock_list_page(server: &MockServer, start: &str, ids: std::ops::Range<u64>) {
+    let entries: Vec<String> = ids
+        .map(|id| list_entry(id, format!("/apps/mock-app/big/{}.txt", id).as_str(), false))
+        .collect();
+    server.mock_with_param(
+        "GET",
+        "/rest/2.0/xpan/file",
+        Some("list"),
+        ("start", start),
+        format!(r#"{{"errno":0,"guid":0,"list":[{}]}}"#, entries.join(",")).as_str(),
+    );
+}
+
+#[test]
+fn test_list_dir_all_paginates() {
+    let server = MockServer::start();
+    mock_list_page(&server, "0", 0..1000);
+    mock_list_page(&server, "1000", 1000..1002);
+    let client = server.client();
+
+    let items = client.list_dir_all("/apps/mock-app/big").unwrap();
+    assert_eq!(items.len(), 1002);
+    assert_eq!(items[1001].path(), "/apps/mock-app/big/1001.txt");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].param("start").as_deref(), Some("1000"));
+    assert_eq!(requests[1].param("limit").as_deref(), Some("1000"));
+}
+
+#[test]
+fn test_dir_size_counts_all_pages() {
+    let server = MockServer::start();
+    mock_list_page(&server, "0", 0..1000);
+    mock_list_page(&server, "1000", 1000..1003);
+    let client = server.client();
+
+    // list_entry 中每个文件大小为 5
+    assert_eq!(client.dir_size("/apps/mock-app/big").unwrap(), 1003 * 5);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_upload_as_sees_names_past_first_page() {
+    let server = MockServer::start();
+    server.mock_user();
+    mock_list_page(&server, "0", 0..1000);
+    mock_list_page(&server, "1000", 1000..1002);
+    mock_slice_upload(&server, "/apps/mock-app/big/1001_2.txt", 5);
+    let mut client = server.client();
+    client.ware().unwrap();
+    let local = temp_file("upload-as");
+    fs::write(&local, b"hello").unwrap();
+
+    // 同名文件位于第二页，也应避开
+    client
+        .upload_as(
+            local.to_str().unwrap(),
+            "/apps/mock-app/big/1001.txt",
+            PcsUploadPolicy::Rename,
+        )
+        .unwrap();
+    fs::remove_file(&local).unwrap();
+
+    let requests = server.requests();
+    let precreate = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("precreate"))
+        .unwrap();
+    let form = parse_pairs(precreate.body_text().as_str());
+    assert_eq!(
+        form.get("path").map(String::as_str),
+        Some("/apps/mock-app/big/1001_2.txt")
+    );
+}
+
+#[test]
+fn test_download_dir() {
+    let server = MockServer::start();
+    let list = |dir: &str, entries: &[String]| {
+        server.mock_with_param(
+            "GET",
+            "/rest/2.0/xpan/file",
+            Some("list"),
+            ("dir", dir),
+            format!(r#"{{"errno":0,"guid":0,"list":[{}]}}"#, entries.join(",")).as_str(),
+        );
+    };
+    list(
+        "/apps/mock-app/tree",
+        &[
+            list_entry(21, "/apps/mock-app/tree/a.txt", false),
+            list_entry(22, "/apps/mock-app/tree/sub", true),
+            list_entry(24, "/apps/mock-app/tree/empty", true),
+        ],
+    );
+    list(
+        "/apps/mock-app/tree/sub",
+        &[list_entry(23, "/apps/mock-app/tree/sub/b.txt", false)],
+    );
+    list("/apps/mock-app/tree/empty", &[]);
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/multimedia",
+        Some("filemetas"),
+        format!(
+            r#"{{"errno":0,"list":[
+                {{"fs_id":21,"category":4,"dlink":"{0}/file/a.txt?fid=21","filename":"a.txt","isdir":0,"server_ctime":1,"server_mtime":1,"size":5}},
+                {{"fs_id":23,"category":4,"dlink":"{0}/file/b.txt?fid=23","filename":"b.txt","isdir":0,"server_ctime":1,"server_mtime":1,"size":5}}]}}"#,
+            server.url()
+        )
+        .as_str(),
+    );
+    server.mock_raw("GET", "/file/a.txt", None, 200, b"hello");
+    server.mock_raw("GET", "/file/b.txt", None, 200, b"world");
+    let client = server.client();
+
+    let local = temp_file("download-dir");
+    let progress = std::sync::Mutex::new(Vec::new());
+    let results = client
+        .download_dir(
+            "/apps/mock-app/tree",
+            local.to_str().unwrap(),
+            2,
+            Some(|done: u64, total: u64| progress.lock().unwrap().push((done, total))),
+        )
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "/apps/mock-app/tree/a.txt");
+    assert_eq!(results[1].0, "/apps/mock-app/tree/sub/b.txt");
+    assert!(results.iter().all(|(_, rs)| rs.is_ok()));
+    assert_eq!(fs::read(local.join("a.txt")).unwrap(), b"hello");
+    assert_eq!(fs::read(local.join("sub").join("b.txt")).unwrap(), b"world");
+    assert!(local.join("empty").is_dir());
+    assert_eq!(fs::read_dir(local.join("empty")).unwrap().count(), 0);
+    assert_eq!(progress.into_inner().unwrap().last(), Some(&(2, 2)));
+    fs::remove_dir_all(&local).unwrap();
+}
+
+#[test]
+fn test_list_and_download_shared_dir() {
+    let server = MockServer::start();
+    server.mock_with_param(
+        "GET",
+        "/rest/2.0/xpan/file",
+        Some("list"),
+        ("path", "/123-456"),
+        format!(
+            r#"{{"errno":0,"guid":0,"list":[{},{}]}}"#,
+            list_entry(31, "/共享/photos/a.jpg", false),
+            list_entry(32, "/共享/photos/2024", true)
+        )
+        .as_str(),
+    );
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/multimedia",
+        Some("filemetas"),
+        format!(
+            r#"{{"errno":0,"list":[{{"fs_id":31,"category":3,"dlink":"{}/file/a.jpg?fid=31","filename":"a.jpg","isdir":0,"server_ctime":1,"server_mtime":1,"size":5}}]}}"#,
+            server.url()
+        )
+        .as_str(),
+    );
+    server.mock_raw("GET", "/file/a.jpg", None, 200, b"image");
+    let client = server.client();
+
+    let res = client.list_shared_dir(123, 456, "photos").unwrap();
+    assert_eq!(res.list().len(), 2);
+    assert_eq!(*res.list()[0].fs_id(), 31);
+    assert_eq!(res.list()[1].path(), "/共享/photos/2024");
+
+    let local = temp_file("shared-a.jpg");
+    client
+        .down_shared_file(
+            123,
+            456,
+            31,
+            local.to_str().unwrap(),
+            PcsDownloadPolicy::Overwrite,
+            None::<fn(u64, u64)>,
+        )
+        .unwrap();
+    assert_eq!(fs::read(&local).unwrap(), b"image");
+    fs::remove_file(&local).unwrap();
+
+    let requests = server.requests();
+    let list = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("list"))
+        .unwrap();
+    assert_eq!(list.param("path").as_deref(), Some("/123-456"));
+    assert_eq!(list.param("dir").as_deref(), Some("/photos"));
+    let metas = requests
+        .iter()
+        .find(|r| r.api_method().as_deref() == Some("filemetas"))
+        .unwrap();
+    assert_eq!(metas.param("path").as_deref(), Some("/123-456"));
+    assert_eq!(metas.param("fsids").as_deref(), Some("[31]"));
+}
+
+/// 生成 search 接口返回的一页结果
+fn search_page(fs_ids: &[u64], has_more: bool) -> String {
+    let list: Vec<String> = fs_ids
+        .iter()
+        .map(|fs_id| {
+            format!(
+                r#"{{"category":4,"fs_id":{},"isdir":0,"local_ctime":1,"local_mtime":1,"server_ctime":1,"server_mtime":1,"md5":"m","size":5}}"#,
+                fs_id
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"errno":0,"has_more":{},"list":[{}]}}"#,
+        has_more as i32,
+        list.join(",")
+    )
+}
+
+#[test]
+fn test_search_stream_fetches_pages_lazily() {
+    let server = MockServer::start();
+    let mock_page = |page: &str, body: String| {
+        server.mock_with_param(
+            "GET",
+            "/rest/2.0/xpan/file",
+            Some("search"),
+            ("page", page),
+            body.as_str(),
+        );
+    };
+    mock_page("1", search_page(&[1, 2], true));
+    mock_page("2", search_page(&[3], false));
+    let client = server.client();
+    let search_requests = || {
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.api_method().as_deref() == Some("search"))
+            .count()
+    };
+
+    let options = SearchOptions {
+        dir: Some("/apps/mock-app".to_string()),
+        ..Default::default()
+    };
+    let mut stream = client.search_stream("report", options);
+    assert_eq!(search_requests(), 0);
+    assert_eq!(*stream.next().unwrap().unwrap().fs_id(), 1);
+    assert_eq!(*stream.next().unwrap().unwrap().fs_id(), 2);
+    assert_eq!(search_requests(), 1);
+    assert_eq!(*stream.next().unwrap().unwrap().fs_id(), 3);
+    assert!(stream.next().is_none());
+    assert_eq!(search_requests(), 2);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].param("key").as_deref(), Some("report"));
+    assert_eq!(requests[0].param("dir").as_deref(), Some("/apps/mock-app"));
+    assert_eq!(requests[0].param("recursion").as_deref(), Some("1"));
+    assert_eq!(requests[1].param("page").as_deref(), Some("2"));
+}
+
+#[test]
+fn test_error_mapping() {
+    let server = MockServer::start();
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/file",
+        Some("list"),
+        r#"{"errno":111,"errmsg":"access token invalid","request_id":"1"}"#,
+    );
+    server.mock(
+        "GET",
+        "/rest/2.0/xpan/nas",
+        Some("uinfo"),
+        r#"{"error_code":"111","error_msg":"Access token expired"}"#,
+    );
+    server.mock(
+        "GET",
+        "/api/quota",
+        None,
+        r#"{"errno":-9,"errmsg":"file not exist"}"#,
+    );
+    server.mock_raw("GET", "/file/gone.txt", None, 404, b"");
+    let client = server.client();
+
+    let e = client.list_dir("/").unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Unauthorized);
+    assert_eq!(e.errno, Some(111));
+    assert!(!client.check_token().unwrap());
+
+    let e = client.get_user_quota(false, false).unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Server);
+    assert_eq!(e.errno, Some(-9));
+
+    let local = temp_file("gone.txt");
+    let e = client
+        .download(
+            format!("{}/file/gone.txt", server.url()).as_str(),
+            local.to_str().unwrap(),
+            None::<fn(u64, u64)>,
+        )
+        .unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Network);
+    assert_eq!(e.errno, Some(404));
+
+    // 服务不可达时返回网络错误而不是 panic
+    let mut offline = BaiduPcsClient::new_with_dns(TEST_TOKEN, TEST_APP, None);
+    offline.set_base_urls("http://127.0.0.1:1", "http://127.0.0.1:1");
+    let e = offline.list_dir("/").unwrap_err();
+    assert_eq!(e.error_type, AppErrorType::Network);
+    assert!(offline.check_token().is_err());
+}
+
+#[test]
+fn test_backup_plan_only_fetches_user_info() {
+    let server = MockServer::start();
+    server.mock_user();
+    let client = server.client();
+
+    let dir = temp_file("plan");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("a.txt"), b"hello").unwrap();
+    fs::write(dir.join("sub/b.bin"), vec![0u8; 4 * 1024 * 1024 + 1]).unwrap();
+    fs::write(dir.join(".hidden"), b"skip").unwrap();
+
+    let plan = client.plan(dir.to_str().unwrap(), "/backup").unwrap();
+    assert_eq!(*plan.files(), 2);
+    assert_eq!(*plan.slice_size(), 4 * 1024 * 1024);
+    assert_eq!(*plan.slice_calls(), 1 + 2);
+    assert_eq!(plan.total_calls(), 2 * 3 + 3);
+    // 除用户信息外不发起任何请求
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].api_method().as_deref(), Some("uinfo"));
+    fs::remove_dir_all(&dir).unwrap();
+}